@@ -20,6 +20,11 @@
         menu.classList.toggle('is-active');
       }
 
+      const selection = document.getElementById('photo-selection-form');
+      if (selection) {
+        selection.classList.toggle('is-hidden');
+      }
+
       // Also toggle the trigger button color
       const btn = document.getElementById('btn-album-menu-trigger');
       if (btn) {
@@ -27,6 +32,15 @@
       }
     }
 
+    function updateSelectionCount() {
+      const counter = document.getElementById('photo-selection-count');
+      if (counter) {
+        counter.innerText = document.querySelectorAll(
+          '#photo-gallery .photo-select:checked',
+        ).length;
+      }
+    }
+
     function handlePhotoDeleted() {
       const currentNode = document.querySelector(
         '#photos-count-w .current-count',
@@ -76,6 +90,13 @@
       }
     });
 
+    document.addEventListener('change', (e) => {
+      if (e.target.closest('.photo-select')) {
+        updateSelectionCount();
+      }
+    });
+
     htmx.on('PhotoDeletedEvent', handlePhotoDeleted);
+    htmx.on('htmx:oobAfterSwap', updateSelectionCount);
   }
 })();
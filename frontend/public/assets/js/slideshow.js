@@ -0,0 +1,193 @@
+(function () {
+  if (!window.X_SLIDESHOW_EVENTS) {
+    window.X_SLIDESHOW_EVENTS = true;
+
+    // Number of upcoming photos kept loaded ahead of time
+    const PREFETCH_COUNT = 2;
+
+    const state = {
+      slides: [],
+      index: 0,
+      interval: 5,
+      repeat: false,
+      playing: true,
+      timer: null,
+      prefetched: {},
+    };
+
+    function shuffle(items) {
+      for (let i = items.length - 1; i > 0; i--) {
+        const j = Math.floor(Math.random() * (i + 1));
+        [items[i], items[j]] = [items[j], items[i]];
+      }
+      return items;
+    }
+
+    function setStatus(text) {
+      const status = document.getElementById('slideshow-status');
+      if (status) {
+        status.innerText = text;
+        status.classList.toggle('is-hidden', !text);
+      }
+    }
+
+    function prefetch() {
+      for (let i = 1; i <= PREFETCH_COUNT; i++) {
+        let next = state.index + i;
+        if (next >= state.slides.length) {
+          if (!state.repeat) {
+            break;
+          }
+          next = next % state.slides.length;
+        }
+
+        const slide = state.slides[next];
+        if (!state.prefetched[slide.id]) {
+          const img = new Image();
+          img.src = slide.url;
+          state.prefetched[slide.id] = img;
+        }
+      }
+    }
+
+    function showSlide() {
+      const img = document.getElementById('slideshow-image');
+      const position = document.getElementById('slideshow-position');
+      const slide = state.slides[state.index];
+      if (!img || !slide) {
+        return;
+      }
+
+      img.src = slide.url;
+      img.alt = slide.name;
+      img.classList.remove('is-hidden');
+      if (position) {
+        position.innerText = `${state.index + 1} / ${state.slides.length}`;
+      }
+
+      prefetch();
+      schedule();
+    }
+
+    function schedule() {
+      if (state.timer) {
+        clearTimeout(state.timer);
+        state.timer = null;
+      }
+      if (state.playing) {
+        state.timer = setTimeout(() => move(1), state.interval * 1000);
+      }
+    }
+
+    function move(step) {
+      let next = state.index + step;
+      if (next >= state.slides.length || next < 0) {
+        if (!state.repeat) {
+          // Stay on the last photo once the album is done
+          setPlaying(false);
+          return;
+        }
+        next = (next + state.slides.length) % state.slides.length;
+      }
+
+      state.index = next;
+      showSlide();
+    }
+
+    function setPlaying(playing) {
+      state.playing = playing;
+
+      const button = document.getElementById('slideshow-play');
+      if (button) {
+        button.setAttribute('aria-label', playing ? 'Pause' : 'Play');
+        const icon = button.querySelector('i');
+        if (icon) {
+          icon.className = playing ? 'fas fa-pause' : 'fas fa-play';
+        }
+      }
+      schedule();
+    }
+
+    function toggleFullscreen() {
+      const container = document.getElementById('slideshow');
+      if (!container) {
+        return;
+      }
+      if (document.fullscreenElement) {
+        document.exitFullscreen();
+      } else if (container.requestFullscreen) {
+        container.requestFullscreen();
+      }
+    }
+
+    async function loadPlaylist() {
+      const container = document.getElementById('slideshow');
+      if (!container) {
+        return;
+      }
+
+      try {
+        const res = await fetch(container.dataset.playlistUrl, {
+          credentials: 'same-origin',
+        });
+        if (!res.ok) {
+          throw new Error('Failed to load photos');
+        }
+
+        const playlist = await res.json();
+        state.interval = playlist.interval;
+        state.repeat = playlist.loop;
+        state.slides = playlist.shuffle
+          ? shuffle(playlist.slides)
+          : playlist.slides;
+      } catch (_err) {
+        setStatus('Failed to load photos.');
+        return;
+      }
+
+      if (state.slides.length === 0) {
+        setStatus('No photos in this album yet.');
+        return;
+      }
+
+      setStatus('');
+      showSlide();
+    }
+
+    document.addEventListener('click', (e) => {
+      if (e.target.closest('#slideshow-prev')) {
+        move(-1);
+      } else if (e.target.closest('#slideshow-next')) {
+        move(1);
+      } else if (e.target.closest('#slideshow-play')) {
+        setPlaying(!state.playing);
+      } else if (e.target.closest('#slideshow-fullscreen')) {
+        toggleFullscreen();
+      }
+    });
+
+    document.addEventListener('keydown', (e) => {
+      if (!document.getElementById('slideshow') || state.slides.length === 0) {
+        return;
+      }
+
+      switch (e.key) {
+        case 'ArrowLeft':
+          move(-1);
+          break;
+        case 'ArrowRight':
+          move(1);
+          break;
+        case ' ':
+          setPlaying(!state.playing);
+          e.preventDefault();
+          break;
+        case 'f':
+          toggleFullscreen();
+          break;
+      }
+    });
+
+    loadPlaylist();
+  }
+})();
@@ -49,8 +49,11 @@
         });
     }
 
-    async function uploadPhoto(action, token, file, onUploadProgress) {
-      const url = `${action}?token=${token}`;
+    async function uploadPhoto(action, token, name, file, onUploadProgress) {
+      let url = `${action}?token=${token}`;
+      if (name) {
+        url += `&name=${encodeURIComponent(name)}`;
+      }
 
       const config = {
         headers: {
@@ -72,6 +75,7 @@
       const form = document.getElementById('upload-photos-form');
       const photosInput = document.getElementById('photos-input');
       const tokenInput = document.getElementById('upload-photos-token');
+      const nameInput = document.getElementById('upload-photos-name');
       const galleryContainer = document.getElementById('photo-gallery');
       const uploadContainer = document.getElementById('photos-input-w');
       const progressContainer = document.getElementById('upload-progress-w');
@@ -99,6 +103,13 @@
       // Token will change on every upload batch
       let token = tokenInput.value.toString();
 
+      // Guest uploads are recorded with the uploader's name
+      const name = nameInput ? nameInput.value.trim() : '';
+      if (nameInput && !name) {
+        alert('Please enter your name');
+        return;
+      }
+
       if (files.length === 0) {
         alert('Please select photos to upload');
         return;
@@ -132,7 +143,7 @@
       // Wanted to upload batch of 4 but concurrency is not good
       // in the backend side due to sqlite locking
       for (const file of files) {
-        await uploadPhoto(action, token, file)
+        await uploadPhoto(action, token, name, file)
           .then((res) => {
             if (res.nextToken) {
               token = res.nextToken;
@@ -41,6 +41,10 @@ const bundles = [
     bundle: `assets/bundles/js/upload-${suffix}.js`,
     files: ['assets/vendors/axios/1.7.2/axios.min.js', 'assets/js/upload.js'],
   },
+  {
+    bundle: `assets/bundles/js/slideshow-${suffix}.js`,
+    files: ['assets/js/slideshow.js'],
+  },
   {
     bundle: `assets/bundles/js/main-${suffix}.js`,
     files: [
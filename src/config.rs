@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand};
 use dotenvy::dotenv;
 use serde::Deserialize;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
@@ -11,9 +12,24 @@ pub const SSL: &str = "SSL";
 pub const FRONTEND_DIR: &str = "FRONTEND_DIR";
 pub const CAPTCHA_SITE_KEY: &str = "CAPTCHA_SITE_KEY";
 pub const CAPTCHA_SITE_SECRET: &str = "CAPTCHA_SITE_SECRET";
+pub const CAPTCHA_VERIFY_URL: &str = "CAPTCHA_VERIFY_URL";
 pub const API_URL: &str = "API_URL";
 pub const JWT_SECRET: &str = "JWT_SECRET";
 pub const GA_TAG_ID: &str = "GA_TAG_ID";
+pub const UPLOAD_MAX_SIZE: &str = "UPLOAD_MAX_SIZE";
+pub const UPLOAD_DIR: &str = "UPLOAD_DIR";
+pub const UPLOAD_NORMALIZE: &str = "UPLOAD_NORMALIZE";
+pub const DATA_DIR: &str = "DATA_DIR";
+pub const SHARE_USERNAME: &str = "SHARE_USERNAME";
+pub const SHARE_PASSWORD: &str = "SHARE_PASSWORD";
+pub const GUEST_UPLOAD_USERNAME: &str = "GUEST_UPLOAD_USERNAME";
+pub const GUEST_UPLOAD_PASSWORD: &str = "GUEST_UPLOAD_PASSWORD";
+pub const TRUSTED_PROXIES: &str = "TRUSTED_PROXIES";
+
+const DEFAULT_CAPTCHA_VERIFY_URL: &str = "https://www.google.com/recaptcha/api/siteverify";
+
+// 200 MB, large enough for camera originals and RAW files
+const DEFAULT_UPLOAD_MAX_SIZE: usize = 200_000_000;
 
 #[derive(Clone, Deserialize)]
 pub struct Config {
@@ -22,9 +38,21 @@ pub struct Config {
     pub frontend_dir: PathBuf,
     pub captcha_site_key: String,
     pub captcha_site_secret: String,
+    pub captcha_verify_url: String,
     pub api_url: String,
     pub jwt_secret: String,
     pub ga_tag_id: Option<String>,
+    pub upload_max_size: usize,
+    pub upload_dir: PathBuf,
+    /// Auto-rotate and strip location from uploads to every album
+    pub upload_normalize: bool,
+    pub data_dir: PathBuf,
+    pub share_username: Option<String>,
+    pub share_password: Option<String>,
+    pub guest_upload_username: Option<String>,
+    pub guest_upload_password: Option<String>,
+    /// Proxies allowed to pass the visitor address in `X-Real-IP`
+    pub trusted_proxies: Vec<IpAddr>,
     pub assets: AssetManifest,
 }
 
@@ -34,6 +62,7 @@ pub struct AssetManifest {
     pub vendor_js: String,
     pub gallery_js: String,
     pub upload_js: String,
+    pub slideshow_js: String,
     pub main_css: String,
     pub gallery_css: String,
 }
@@ -57,9 +86,39 @@ impl Config {
             env::var(CAPTCHA_SITE_KEY).expect("CAPTCHA_SITE_KEY is not set");
         let captcha_site_secret: String =
             env::var(CAPTCHA_SITE_SECRET).expect("CAPTCHA_SITE_SECRET is not set");
+        let captcha_verify_url: String =
+            env::var(CAPTCHA_VERIFY_URL).unwrap_or(DEFAULT_CAPTCHA_VERIFY_URL.to_string());
         let api_url: String = env::var(API_URL).expect("API_URL is not set");
         let jwt_secret: String = env::var(JWT_SECRET).expect("JWT_SECRET is not set");
         let ga_tag_id: Option<String> = env::var(GA_TAG_ID).ok();
+        let upload_max_size: usize = match env::var(UPLOAD_MAX_SIZE) {
+            Ok(value) => value
+                .parse()
+                .expect("UPLOAD_MAX_SIZE is not a valid number"),
+            Err(_) => DEFAULT_UPLOAD_MAX_SIZE,
+        };
+        let upload_dir = match env::var(UPLOAD_DIR) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => env::temp_dir().join("memo-rs-uploads"),
+        };
+        let upload_normalize = env::var(UPLOAD_NORMALIZE).is_ok_and(|value| value == "1");
+        let data_dir = match env::var(DATA_DIR) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => PathBuf::from("data"),
+        };
+        let share_username: Option<String> = env::var(SHARE_USERNAME).ok();
+        let share_password: Option<String> = env::var(SHARE_PASSWORD).ok();
+        let guest_upload_username: Option<String> = env::var(GUEST_UPLOAD_USERNAME).ok();
+        let guest_upload_password: Option<String> = env::var(GUEST_UPLOAD_PASSWORD).ok();
+        let trusted_proxies: Vec<IpAddr> = match env::var(TRUSTED_PROXIES) {
+            Ok(value) => value
+                .split(',')
+                .map(|ip| ip.trim())
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| ip.parse().expect("TRUSTED_PROXIES has an invalid address"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
 
         if !frontend_dir.exists() {
             return Err("Frontend dir does not exists.".into());
@@ -73,9 +132,19 @@ impl Config {
             frontend_dir,
             captcha_site_key,
             captcha_site_secret,
+            captcha_verify_url,
             api_url,
             jwt_secret,
             ga_tag_id,
+            upload_max_size,
+            upload_dir,
+            upload_normalize,
+            data_dir,
+            share_username,
+            share_password,
+            guest_upload_username,
+            guest_upload_password,
+            trusted_proxies,
             assets,
         })
     }
@@ -97,6 +166,7 @@ impl AssetManifest {
             vendor_js: format!("/assets/bundles/js/vendor-{}.js", config.suffix),
             gallery_js: format!("/assets/bundles/js/gallery-{}.js", config.suffix),
             upload_js: format!("/assets/bundles/js/upload-{}.js", config.suffix),
+            slideshow_js: format!("/assets/bundles/js/slideshow-{}.js", config.suffix),
             main_css: format!("/assets/bundles/css/main-{}.css", config.suffix),
             gallery_css: format!("/assets/bundles/css/gallery-{}.css", config.suffix),
         })
@@ -1,4 +1,4 @@
-use crate::models::Actor;
+use crate::models::{Actor, GuestUploadLink};
 
 #[derive(Clone)]
 pub struct Ctx {
@@ -20,9 +20,66 @@ impl Ctx {
     }
 }
 
+#[allow(clippy::manual_map)]
 pub fn extract_ctx_actor(ctx: &Option<Ctx>) -> Option<Actor> {
     match ctx {
         Some(node) => Some(node.actor.clone()),
         None => None,
     }
 }
+
+/// Request context for visitors of a share link
+#[derive(Clone)]
+pub struct ShareCtx {
+    token: String,
+    share_token: String,
+}
+
+impl ShareCtx {
+    pub fn new(token: String, share_token: String) -> Self {
+        ShareCtx { token, share_token }
+    }
+
+    /// files-rs token of the share account
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Where the shared album is served
+    pub fn base_url(&self) -> String {
+        format!("/share/{}", self.share_token)
+    }
+}
+
+/// Request context for guests holding an upload link
+#[derive(Clone)]
+pub struct GuestUploadCtx {
+    token: String,
+    guest_token: String,
+    link: GuestUploadLink,
+}
+
+impl GuestUploadCtx {
+    pub fn new(token: String, guest_token: String, link: GuestUploadLink) -> Self {
+        GuestUploadCtx {
+            token,
+            guest_token,
+            link,
+        }
+    }
+
+    /// files-rs token of the guest upload account
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Where the upload page is served
+    pub fn base_url(&self) -> String {
+        format!("/guest-upload/{}", self.guest_token)
+    }
+
+    /// The link as it was when the request came in
+    pub fn link(&self) -> &GuestUploadLink {
+        &self.link
+    }
+}
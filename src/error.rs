@@ -16,15 +16,23 @@ pub enum Error {
     InvalidCaptcha(String),
     CaptchaResponseError(String),
     LoginRequired(String),
-    NoDefaultBucket,
+    BucketNotFound,
     AlbumNotFound,
     PhotoNotFound,
+    ShareNotFound,
+    GuestUploadNotFound,
+    UploadNotFound,
+    UploadConflict(String),
+    UploadTooLarge,
+    TooManyRequests(String),
     NoAuthCookie,
     InvalidCsrfToken,
     JsonParseError(String),
     ServiceError(String),
 }
 
+/// Error body of files-rs, only the message is shown
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct ErrorResponse {
     pub status_code: u16,
@@ -51,9 +59,15 @@ impl core::fmt::Display for Error {
             Self::InvalidCaptcha(val) => write!(f, "{}", val),
             Self::CaptchaResponseError(val) => write!(f, "{}", val),
             Self::LoginRequired(val) => write!(f, "{}", val),
-            Self::NoDefaultBucket => write!(f, "No default bucket configured"),
+            Self::BucketNotFound => write!(f, "Bucket not found"),
             Self::AlbumNotFound => write!(f, "Album not found"),
             Self::PhotoNotFound => write!(f, "Photo not found"),
+            Self::ShareNotFound => write!(f, "Shared album not found"),
+            Self::GuestUploadNotFound => write!(f, "Upload link not found"),
+            Self::UploadNotFound => write!(f, "Upload not found"),
+            Self::UploadConflict(val) => write!(f, "{}", val),
+            Self::UploadTooLarge => write!(f, "Upload is too large"),
+            Self::TooManyRequests(val) => write!(f, "{}", val),
             Self::NoAuthCookie => write!(f, "Login to continue"),
             Self::InvalidCsrfToken => write!(f, "Stale form data. Refresh the page and try again"),
             Self::JsonParseError(val) => write!(f, "{}", val),
@@ -74,9 +88,15 @@ impl From<Error> for StatusCode {
             Error::InvalidCaptcha(_) => StatusCode::BAD_REQUEST,
             Error::CaptchaResponseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::LoginRequired(_) => StatusCode::UNAUTHORIZED,
-            Error::NoDefaultBucket => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::BucketNotFound => StatusCode::NOT_FOUND,
             Error::AlbumNotFound => StatusCode::NOT_FOUND,
             Error::PhotoNotFound => StatusCode::NOT_FOUND,
+            Error::ShareNotFound => StatusCode::NOT_FOUND,
+            Error::GuestUploadNotFound => StatusCode::NOT_FOUND,
+            Error::UploadNotFound => StatusCode::NOT_FOUND,
+            Error::UploadConflict(_) => StatusCode::CONFLICT,
+            Error::UploadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             Error::NoAuthCookie => StatusCode::UNAUTHORIZED,
             Error::InvalidCsrfToken => StatusCode::BAD_REQUEST,
             Error::JsonParseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -4,6 +4,8 @@ mod error;
 mod models;
 mod run;
 mod services;
+#[cfg(test)]
+mod tests;
 mod web;
 
 use clap::Parser;
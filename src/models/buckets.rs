@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Bucket {
+    pub id: String,
+    pub client_id: String,
+    pub name: String,
+    pub created_at: i64,
+}
@@ -0,0 +1,98 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// Camera details read from the upload, keyed by photo id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PhotoExif {
+    /// Capture time as written by the camera, which knows no time zone
+    #[serde(default)]
+    pub taken_at: Option<i64>,
+    #[serde(default)]
+    pub camera: Option<String>,
+    #[serde(default)]
+    pub lens: Option<String>,
+    /// In seconds
+    #[serde(default)]
+    pub exposure_time: Option<f64>,
+    #[serde(default)]
+    pub f_number: Option<f64>,
+    #[serde(default)]
+    pub iso: Option<u32>,
+    /// In millimeters
+    #[serde(default)]
+    pub focal_length: Option<f64>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+impl PhotoExif {
+    /// Nothing worth keeping
+    pub fn is_empty(&self) -> bool {
+        self.taken_at.is_none()
+            && self.camera.is_none()
+            && self.lens.is_none()
+            && self.exposure_time.is_none()
+            && self.f_number.is_none()
+            && self.iso.is_none()
+            && self.focal_length.is_none()
+            && self.location().is_none()
+    }
+
+    pub fn taken_at_label(&self) -> Option<String> {
+        let dt = DateTime::from_timestamp(self.taken_at?, 0)?;
+        Some(dt.format("%b %-d, %Y %H:%M").to_string())
+    }
+
+    /// Exposure settings in the usual `1/250 s · f/2.8 · ISO 100 · 35 mm` form
+    pub fn exposure_label(&self) -> Option<String> {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(time) = self.exposure_time.filter(|t| *t > 0.0) {
+            if time < 1.0 {
+                parts.push(format!("1/{} s", (1.0 / time).round()));
+            } else {
+                parts.push(format!("{} s", trim_number(time)));
+            }
+        }
+        if let Some(f_number) = self.f_number {
+            parts.push(format!("f/{}", trim_number(f_number)));
+        }
+        if let Some(iso) = self.iso {
+            parts.push(format!("ISO {}", iso));
+        }
+        if let Some(focal_length) = self.focal_length {
+            parts.push(format!("{} mm", trim_number(focal_length)));
+        }
+
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+
+    /// Latitude and longitude, only when both are known
+    pub fn location(&self) -> Option<(f64, f64)> {
+        Some((self.latitude?, self.longitude?))
+    }
+
+    pub fn location_label(&self) -> Option<String> {
+        let (lat, lon) = self.location()?;
+        Some(format!("{:.5}, {:.5}", lat, lon))
+    }
+
+    pub fn map_url(&self) -> Option<String> {
+        let (lat, lon) = self.location()?;
+        Some(format!(
+            "https://www.openstreetmap.org/?mlat={:.5}&mlon={:.5}#map=15/{:.5}/{:.5}",
+            lat, lon, lat, lon
+        ))
+    }
+}
+
+/// At most one decimal, without a trailing `.0`
+fn trim_number(value: f64) -> String {
+    let value = (value * 10.0).round() / 10.0;
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Photos a user starred, keyed by user id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UserFavorites {
+    pub photos: Vec<FavoritePhoto>,
+}
+
+/// files-rs needs the bucket and album to find a photo again
+#[derive(Clone, Deserialize, Serialize)]
+pub struct FavoritePhoto {
+    pub bucket_id: String,
+    pub album_id: String,
+    pub photo_id: String,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct FavoritePhotoForm {
+    pub token: String,
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// How long a new guest upload link may stay valid, in days
+pub const GUEST_UPLOAD_EXPIRY_DAYS: [i64; 4] = [1, 3, 7, 30];
+
+/// Largest upload cap a single link can have
+pub const GUEST_UPLOAD_MAX_PHOTOS: u32 = 1000;
+
+/// Lets anyone holding the link upload into an album, keyed by id
+#[derive(Clone, Deserialize, Serialize)]
+pub struct GuestUploadLink {
+    pub id: String,
+    pub bucket_id: String,
+    pub album_id: String,
+    pub created_by: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub max_uploads: u32,
+    pub uploads: u32,
+}
+
+impl GuestUploadLink {
+    pub fn uploads_left(&self) -> u32 {
+        self.max_uploads.saturating_sub(self.uploads)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GuestUploadParams {
+    pub guest_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct GuestUploadLinkParams {
+    pub link_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct GuestUploadQuery {
+    pub token: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct NewGuestUploadLinkForm {
+    pub expires_in: i64,
+    pub max_uploads: u32,
+    pub token: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RevokeGuestUploadLinkForm {
+    pub token: String,
+}
@@ -1,15 +1,35 @@
+mod buckets;
+mod exif;
+mod favorites;
+mod guest_uploads;
 mod login;
+mod moderation;
 mod pagination;
 mod params;
 mod photos;
 mod pref;
+mod shares;
+mod slideshow;
+mod tags;
 mod template;
+mod timeline;
+mod uploads;
 mod user;
 
+pub use buckets::*;
+pub use exif::*;
+pub use favorites::*;
+pub use guest_uploads::*;
 pub use login::*;
+pub use moderation::*;
 pub use pagination::*;
 pub use params::*;
 pub use photos::*;
 pub use pref::*;
+pub use shares::*;
+pub use slideshow::*;
+pub use tags::*;
 pub use template::*;
+pub use timeline::*;
+pub use uploads::*;
 pub use user::*;
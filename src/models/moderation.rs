@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+/// An upload held back until a reviewer approves it, keyed by id
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StagedUpload {
+    pub id: String,
+    pub bucket_id: String,
+    pub album_id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+    /// Username of the member or the name entered by a guest
+    pub uploaded_by: String,
+    pub guest: bool,
+    pub created_at: i64,
+}
+
+/// Image types a browser only ever draws, so reviewers can see them inline.
+/// SVG can carry scripts and is left out.
+const PREVIEW_CONTENT_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+impl StagedUpload {
+    /// The content type comes from the uploader and is not trusted otherwise
+    pub fn is_previewable(&self) -> bool {
+        PREVIEW_CONTENT_TYPES.contains(&self.content_type.to_lowercase().as_str())
+    }
+}
+
+pub struct NewStagedUpload {
+    pub bucket_id: String,
+    pub album_id: String,
+    pub uploaded_by: String,
+    pub guest: bool,
+}
+
+#[derive(Deserialize)]
+pub struct StagedUploadParams {
+    pub staged_id: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AlbumModerationForm {
+    pub moderated: Option<String>,
+    pub token: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModerationAction {
+    Approve,
+    Reject,
+}
+
+/// Approve or reject several staged uploads with a single album-scoped token
+pub struct ModerateUploadsForm {
+    pub staged_ids: Vec<String>,
+    pub action: Option<ModerationAction>,
+    pub token: String,
+}
+
+impl ModerateUploadsForm {
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut form = Self {
+            staged_ids: Vec::new(),
+            action: None,
+            token: "".to_string(),
+        };
+
+        for (key, value) in pairs {
+            match key.as_str() {
+                "staged_id" if !value.is_empty() && !form.staged_ids.contains(&value) => {
+                    form.staged_ids.push(value)
+                }
+                "action" => {
+                    form.action = match value.as_str() {
+                        "approve" => Some(ModerationAction::Approve),
+                        "reject" => Some(ModerationAction::Reject),
+                        _ => None,
+                    }
+                }
+                "token" => form.token = value,
+                _ => {}
+            }
+        }
+
+        form
+    }
+}
+
+/// Outcome of approving or rejecting a single staged upload
+pub struct ModerationResult {
+    pub staged_id: String,
+    pub filename: String,
+    pub error: Option<String>,
+}
@@ -14,6 +14,31 @@ pub struct Paginated<T> {
     pub data: Vec<T>,
 }
 
+impl<T> Paginated<T> {
+    /// Paginates items that were already fetched in full
+    pub fn from_items(items: Vec<T>, page: i64, per_page: i64) -> Self {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+        let total_records = items.len() as i64;
+        let total_pages = (total_records + per_page - 1) / per_page;
+        let data: Vec<T> = items
+            .into_iter()
+            .skip(((page - 1) * per_page) as usize)
+            .take(per_page as usize)
+            .collect();
+
+        Self {
+            meta: PaginatedMeta {
+                page,
+                per_page,
+                total_records,
+                total_pages,
+            },
+            data,
+        }
+    }
+}
+
 #[derive(Clone, Serialize)]
 pub struct PaginationLinks {
     pub prev: Option<PaginationLink>,
@@ -118,6 +143,7 @@ impl PaginationLinks {
         }
 
         // Inject the middle pages
+        #[allow(clippy::single_match)]
         match (mid_start, mid_end) {
             (Some(start), Some(end)) => {
                 if start != 2 {
@@ -541,4 +567,17 @@ mod tests {
         assert!(links.next.is_some());
         assert_eq!(links.items.len(), 6);
     }
+
+    #[test]
+    fn test_from_items() {
+        let items: Vec<i64> = (1..=25).collect();
+        let paginated = Paginated::from_items(items, 3, 10);
+        assert_eq!(paginated.meta.total_records, 25);
+        assert_eq!(paginated.meta.total_pages, 3);
+        assert_eq!(paginated.data, vec![21, 22, 23, 24, 25]);
+
+        let paginated = Paginated::from_items(Vec::<i64>::new(), 1, 10);
+        assert_eq!(paginated.meta.total_pages, 0);
+        assert!(paginated.data.is_empty());
+    }
 }
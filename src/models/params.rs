@@ -1,18 +1,54 @@
+use chrono::NaiveDate;
 use core::fmt;
+use std::cmp::Ordering;
 use urlencoding::encode;
 
 use serde::Deserialize;
 
+use super::{Album, ImgVersion, Photo};
+
+#[derive(Deserialize)]
+pub struct BucketParams {
+    pub bucket_id: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct AlbumParams {
     pub album_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct DownloadAlbumParams {
+    pub version: Option<ImgVersion>,
+}
+
 #[derive(Deserialize)]
 pub struct ListAlbumsParams {
     pub keyword: Option<String>,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
+    pub sort: Option<String>,
+    pub non_empty: Option<bool>,
+    pub updated_since: Option<String>,
+}
+
+/// Albums listed on a page unless asked otherwise, and the most files-rs is asked for
+pub const DEFAULT_ALBUM_PAGE_SIZE: u32 = 10;
+pub const MAX_ALBUM_PAGE_SIZE: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlbumSortField {
+    Label,
+    CreatedAt,
+    UpdatedAt,
+    FileCount,
+}
+
+/// Album sort order, written as `field` or `-field` for descending
+#[derive(Clone, Copy, PartialEq)]
+pub struct AlbumSort {
+    pub field: AlbumSortField,
+    pub desc: bool,
 }
 
 #[derive(Deserialize)]
@@ -23,34 +59,132 @@ pub struct UploadParams {
 #[derive(Deserialize)]
 pub struct ListPhotosParams {
     pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub sort: Option<String>,
+    pub keyword: Option<String>,
+    pub camera: Option<String>,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum PhotoSortField {
+    Name,
+    CreatedAt,
+    TakenAt,
+    Size,
+}
+
+/// Photo sort order, written as `field` or `-field` for descending
+#[derive(Clone, Copy, PartialEq)]
+pub struct PhotoSort {
+    pub field: PhotoSortField,
+    pub desc: bool,
+}
+
+/// Page sizes offered in the photo grid
+pub const PHOTO_PAGE_SIZES: [u32; 4] = [25, 50, 100, 200];
+pub const DEFAULT_PHOTO_PAGE_SIZE: u32 = 50;
+
 #[derive(Deserialize)]
 pub struct PhotoParams {
-    pub album_id: Option<String>,
     pub photo_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct ResumableUploadParams {
+    pub upload_id: Option<String>,
+}
+
 impl Default for ListAlbumsParams {
     fn default() -> Self {
         Self {
             keyword: None,
             page: Some(1),
-            per_page: Some(10),
+            per_page: Some(DEFAULT_ALBUM_PAGE_SIZE),
+            sort: None,
+            non_empty: None,
+            updated_since: None,
         }
     }
 }
 
+impl ListAlbumsParams {
+    /// Page size kept within what a single page can show
+    pub fn page_size(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_ALBUM_PAGE_SIZE)
+            .clamp(1, MAX_ALBUM_PAGE_SIZE)
+    }
+
+    pub fn sort_order(&self) -> Option<AlbumSort> {
+        self.sort.as_deref().and_then(AlbumSort::parse)
+    }
+
+    /// Start of the `updated_since` day as a timestamp
+    pub fn updated_since_ts(&self) -> Option<i64> {
+        let value = self.updated_since.as_deref()?;
+        let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+        Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+    }
+
+    /// Whether albums must be sorted or filtered on our side
+    pub fn is_custom(&self) -> bool {
+        self.sort_order().is_some()
+            || self.non_empty == Some(true)
+            || self.updated_since_ts().is_some()
+    }
+
+    /// Filters then sorts albums according to the params
+    pub fn apply(&self, albums: Vec<Album>) -> Vec<Album> {
+        let since = self.updated_since_ts();
+        let non_empty = self.non_empty == Some(true);
+
+        let mut albums: Vec<Album> = albums
+            .into_iter()
+            .filter(|album| !non_empty || album.file_count > 0)
+            .filter(|album| since.is_none_or(|ts| album.updated_at >= ts))
+            .collect();
+
+        if let Some(sort) = self.sort_order() {
+            albums.sort_by(|a, b| sort.compare(a, b));
+        }
+
+        albums
+    }
+
+    /// Query params other than pagination, to be carried over on pagination links
+    pub fn filter_query(&self) -> String {
+        let mut query = String::new();
+        if let Some(keyword) = &self.keyword {
+            query.push_str(&format!("&keyword={}", encode(keyword)));
+        }
+        if let Some(sort) = self.sort_order() {
+            query.push_str(&format!("&sort={}", sort));
+        }
+        if self.non_empty == Some(true) {
+            query.push_str("&non_empty=true");
+        }
+        if self.updated_since_ts().is_some() {
+            let since = self.updated_since.as_deref().unwrap_or("");
+            query.push_str(&format!("&updated_since={}", encode(since)));
+        }
+        query
+    }
+}
+
 impl fmt::Display for ListAlbumsParams {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Ideally, we want an empty string if all fields are None
-        if self.keyword.is_none() && self.page.is_none() && self.per_page.is_none() {
+        if self.keyword.is_none()
+            && self.page.is_none()
+            && self.per_page.is_none()
+            && !self.is_custom()
+        {
             return write!(f, "");
         }
 
         let keyword = self.keyword.as_deref().unwrap_or("");
         let page = self.page.unwrap_or(1);
-        let per_page = self.per_page.unwrap_or(10);
+        let per_page = self.page_size();
 
         write!(
             f,
@@ -58,6 +192,212 @@ impl fmt::Display for ListAlbumsParams {
             page,
             per_page,
             encode(keyword)
-        )
+        )?;
+
+        if let Some(sort) = self.sort_order() {
+            write!(f, "&sort={}", sort)?;
+        }
+        if self.non_empty == Some(true) {
+            write!(f, "&non_empty=true")?;
+        }
+        if self.updated_since_ts().is_some() {
+            let since = self.updated_since.as_deref().unwrap_or("");
+            write!(f, "&updated_since={}", encode(since))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ListPhotosParams {
+    fn default() -> Self {
+        Self {
+            page: Some(1),
+            per_page: None,
+            sort: None,
+            keyword: None,
+            camera: None,
+        }
+    }
+}
+
+impl ListPhotosParams {
+    /// Page size limited to the offered choices
+    pub fn page_size(&self) -> u32 {
+        match self.per_page {
+            Some(size) if PHOTO_PAGE_SIZES.contains(&size) => size,
+            _ => DEFAULT_PHOTO_PAGE_SIZE,
+        }
+    }
+
+    pub fn sort_order(&self) -> Option<PhotoSort> {
+        self.sort.as_deref().and_then(PhotoSort::parse)
+    }
+
+    pub fn keyword(&self) -> Option<&str> {
+        self.keyword
+            .as_deref()
+            .map(|k| k.trim())
+            .filter(|k| !k.is_empty())
+    }
+
+    pub fn camera(&self) -> Option<&str> {
+        self.camera
+            .as_deref()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+    }
+
+    /// Whether photos must be sorted or filtered on our side
+    pub fn is_custom(&self) -> bool {
+        self.sort_order().is_some() || self.keyword().is_some() || self.camera().is_some()
+    }
+
+    /// Filters by filename keyword and camera then sorts photos according to the params
+    pub fn apply(&self, photos: Vec<Photo>) -> Vec<Photo> {
+        let keyword = self.keyword().map(|k| k.to_lowercase());
+        let camera = self.camera().map(|c| c.to_lowercase());
+
+        let mut photos: Vec<Photo> = photos
+            .into_iter()
+            .filter(|photo| {
+                keyword.as_deref().is_none_or(|k| {
+                    photo.name.to_lowercase().contains(k)
+                        || photo.filename.to_lowercase().contains(k)
+                })
+            })
+            .filter(|photo| {
+                camera.as_deref().is_none_or(|c| {
+                    photo
+                        .exif
+                        .as_ref()
+                        .and_then(|exif| exif.camera.as_deref())
+                        .is_some_and(|camera| camera.to_lowercase().contains(c))
+                })
+            })
+            .collect();
+
+        if let Some(sort) = self.sort_order() {
+            photos.sort_by(|a, b| sort.compare(a, b));
+        }
+
+        photos
+    }
+
+    /// Query params other than the page, to be carried over on grid links
+    pub fn filter_query(&self) -> String {
+        let mut query = format!("&per_page={}", self.page_size());
+        if let Some(sort) = self.sort_order() {
+            query.push_str(&format!("&sort={}", sort));
+        }
+        if let Some(keyword) = self.keyword() {
+            query.push_str(&format!("&keyword={}", encode(keyword)));
+        }
+        if let Some(camera) = self.camera() {
+            query.push_str(&format!("&camera={}", encode(camera)));
+        }
+        query
+    }
+}
+
+impl AlbumSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (desc, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+
+        let field = match name {
+            "label" => AlbumSortField::Label,
+            "created_at" => AlbumSortField::CreatedAt,
+            "updated_at" => AlbumSortField::UpdatedAt,
+            "file_count" => AlbumSortField::FileCount,
+            _ => return None,
+        };
+
+        Some(Self { field, desc })
+    }
+
+    pub fn compare(&self, a: &Album, b: &Album) -> Ordering {
+        let ordering = match self.field {
+            AlbumSortField::Label => a.label.to_lowercase().cmp(&b.label.to_lowercase()),
+            AlbumSortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            AlbumSortField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            AlbumSortField::FileCount => a.file_count.cmp(&b.file_count),
+        };
+
+        if self.desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl fmt::Display for AlbumSort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.field {
+            AlbumSortField::Label => "label",
+            AlbumSortField::CreatedAt => "created_at",
+            AlbumSortField::UpdatedAt => "updated_at",
+            AlbumSortField::FileCount => "file_count",
+        };
+
+        if self.desc {
+            write!(f, "-{}", name)
+        } else {
+            write!(f, "{}", name)
+        }
+    }
+}
+
+impl PhotoSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (desc, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+
+        let field = match name {
+            "name" => PhotoSortField::Name,
+            "created_at" => PhotoSortField::CreatedAt,
+            "taken_at" => PhotoSortField::TakenAt,
+            "size" => PhotoSortField::Size,
+            _ => return None,
+        };
+
+        Some(Self { field, desc })
+    }
+
+    pub fn compare(&self, a: &Photo, b: &Photo) -> Ordering {
+        let ordering = match self.field {
+            PhotoSortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            PhotoSortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            PhotoSortField::TakenAt => a.taken_at().cmp(&b.taken_at()),
+            PhotoSortField::Size => a.size.cmp(&b.size),
+        };
+
+        if self.desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl fmt::Display for PhotoSort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.field {
+            PhotoSortField::Name => "name",
+            PhotoSortField::CreatedAt => "created_at",
+            PhotoSortField::TakenAt => "taken_at",
+            PhotoSortField::Size => "size",
+        };
+
+        if self.desc {
+            write!(f, "-{}", name)
+        } else {
+            write!(f, "{}", name)
+        }
     }
 }
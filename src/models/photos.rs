@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::PhotoExif;
 
 #[derive(Clone, Deserialize)]
 pub struct Album {
@@ -45,6 +48,152 @@ pub struct DeletePhotoForm {
     pub token: String,
 }
 
+#[derive(Clone, Deserialize, Serialize, Validate)]
+pub struct UpdatePhotoForm {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    #[validate(length(max = 500))]
+    pub caption: String,
+
+    pub token: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UpdatePhoto {
+    pub name: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+/// Copy or move photos into another album, photo ids may repeat in the form
+pub struct TransferPhotosForm {
+    pub photo_ids: Vec<String>,
+    pub target_album_id: String,
+    pub mode: TransferMode,
+    pub token: String,
+}
+
+/// Outcome of copying or moving a single photo
+pub struct TransferResult {
+    pub photo_id: String,
+    pub name: String,
+    pub error: Option<String>,
+    pub removed: bool,
+}
+
+/// Delete several photos with a single album-scoped token
+pub struct BulkDeletePhotosForm {
+    pub photo_ids: Vec<String>,
+    /// Number of photos loaded in the grid, used to refresh the counter
+    pub loaded: Option<i64>,
+    pub token: String,
+}
+
+/// Outcome of deleting a single photo
+pub struct BulkDeleteResult {
+    pub photo_id: String,
+    pub name: String,
+    pub error: Option<String>,
+}
+
+/// Photo details kept on the memo-rs side, keyed by photo id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PhotoMeta {
+    #[serde(default)]
+    pub caption: Option<String>,
+
+    /// Name entered by a guest uploader
+    #[serde(default)]
+    pub uploaded_by: Option<String>,
+}
+
+impl PhotoMeta {
+    /// Nothing worth keeping
+    pub fn is_empty(&self) -> bool {
+        self.caption.is_none() && self.uploaded_by.is_none()
+    }
+}
+
+/// Album details kept on the memo-rs side, keyed by album id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct AlbumMeta {
+    /// Either picked by the user or the first photo found
+    #[serde(default)]
+    pub cover_photo_id: Option<String>,
+
+    /// Lets visitors without an account view the album
+    #[serde(default)]
+    pub passphrase: Option<AlbumPassphrase>,
+
+    /// Uploads by guests and members who cannot edit photos wait for review
+    #[serde(default)]
+    pub moderated: bool,
+
+    /// Uploads are auto-rotated and stripped of location before files-rs
+    #[serde(default)]
+    pub normalize_uploads: bool,
+}
+
+impl AlbumMeta {
+    /// Nothing worth keeping
+    pub fn is_empty(&self) -> bool {
+        self.cover_photo_id.is_none()
+            && self.passphrase.is_none()
+            && !self.moderated
+            && !self.normalize_uploads
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AlbumPassphrase {
+    /// Visitors only know the album id
+    pub bucket_id: String,
+    pub hash: String,
+    /// Changes with every new passphrase so earlier unlocks stop working
+    pub version: String,
+    pub max_views: Option<u32>,
+    pub views: u32,
+    pub no_downloads: bool,
+}
+
+impl AlbumPassphrase {
+    pub fn views_left(&self) -> bool {
+        match self.max_views {
+            Some(max) => self.views < max,
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Validate)]
+pub struct AlbumPassphraseForm {
+    #[validate(length(min = 8, max = 100))]
+    pub passphrase: String,
+
+    /// Left empty for unlimited views
+    #[serde(default)]
+    pub max_views: String,
+
+    pub no_downloads: Option<String>,
+    pub token: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RemovePassphraseForm {
+    pub token: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct UnlockAlbumForm {
+    pub passphrase: String,
+    pub token: String,
+}
+
 #[derive(Clone, Deserialize)]
 pub struct FileObject {
     pub id: String,
@@ -66,6 +215,18 @@ pub struct FileObject {
     pub updated_at: i64,
 }
 
+/// How a file is shown in the album, only images have versions of their own
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    #[default]
+    Image,
+    Video,
+    File,
+    /// Image files-rs has not made the versions for yet
+    Processing,
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Photo {
     pub id: String,
@@ -74,13 +235,74 @@ pub struct Photo {
     pub filename: String,
     pub content_type: String,
     pub size: i64,
+    #[serde(default)]
+    pub kind: MediaKind,
     pub orig: PhotoVersionDto,
     pub preview: PhotoVersionDto,
     pub thumb: PhotoVersionDto,
     pub created_at: i64,
     pub updated_at: i64,
+
+    // Kept on the memo-rs side
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub uploaded_by: Option<String>,
+    /// Starred by the current user
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub exif: Option<PhotoExif>,
 }
 
+impl Photo {
+    pub fn is_image(&self) -> bool {
+        self.kind == MediaKind::Image
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.kind == MediaKind::Video
+    }
+
+    pub fn is_processing(&self) -> bool {
+        self.kind == MediaKind::Processing
+    }
+
+    /// Files still being processed or missing a url cannot be opened yet
+    pub fn has_url(&self) -> bool {
+        !self.orig.url.is_empty()
+    }
+
+    /// File size in the largest unit that keeps it above 1
+    pub fn size_label(&self) -> String {
+        let units = ["B", "KB", "MB", "GB"];
+        let mut value = self.size as f64;
+        let mut unit = 0;
+
+        while value >= 1024.0 && unit < units.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{} {}", self.size, units[0])
+        } else {
+            format!("{:.1} {}", value, units[unit])
+        }
+    }
+
+    /// Capture time when the camera recorded one, otherwise the upload time
+    pub fn taken_at(&self) -> i64 {
+        self.exif
+            .as_ref()
+            .and_then(|exif| exif.taken_at)
+            .unwrap_or(self.created_at)
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Clone, Deserialize, Serialize)]
 pub struct UploadResult {
     pub error_message: Option<String>,
@@ -88,24 +310,21 @@ pub struct UploadResult {
     pub next_token: String,
 }
 
-impl TryFrom<FileObject> for Photo {
-    type Error = String;
-
-    fn try_from(file: FileObject) -> Result<Self, Self::Error> {
+impl From<FileObject> for Photo {
+    fn from(mut file: FileObject) -> Self {
         if !file.is_image {
-            return Err("File is not an image".into());
+            return non_image_file(file);
         }
 
-        let Some(versions) = file.img_versions else {
-            return Err("Missing image versions".into());
-        };
-
-        let versions: Vec<PhotoVersionDto> = versions
+        let versions: Vec<PhotoVersionDto> = file
+            .img_versions
+            .take()
+            .unwrap_or_default()
             .into_iter()
             .filter_map(|v| match v.url {
                 None => None,
                 Some(url) => Some(PhotoVersionDto {
-                    version: v.version.as_str().try_into().unwrap(),
+                    version: v.version.as_str().try_into().ok()?,
                     dimension: v.dimension,
                     url,
                 }),
@@ -113,30 +332,79 @@ impl TryFrom<FileObject> for Photo {
             .collect();
 
         let orig = versions.iter().find(|v| v.version == ImgVersion::Original);
-        let mut preview = versions.iter().find(|v| v.version == ImgVersion::Preview);
+        let preview = versions
+            .iter()
+            .find(|v| v.version == ImgVersion::Preview)
+            .or(orig);
         let thumb = versions.iter().find(|v| v.version == ImgVersion::Thumbnail);
 
-        if preview.is_none() && orig.is_some() {
-            preview = orig.clone();
-        }
-
-        if orig.is_none() || preview.is_none() || thumb.is_none() {
-            return Err("Missing image versions".into());
-        }
+        let (Some(orig), Some(preview), Some(thumb)) = (orig, preview, thumb) else {
+            return media_file(file, MediaKind::Processing, "");
+        };
 
-        Ok(Photo {
+        Photo {
             id: file.id,
             dir_id: file.dir_id,
             name: file.name,
             filename: file.filename,
             content_type: file.content_type,
             size: file.size,
-            orig: orig.unwrap().clone(),
-            preview: preview.unwrap().clone(),
-            thumb: thumb.unwrap().clone(),
+            kind: MediaKind::Image,
+            orig: orig.clone(),
+            preview: preview.clone(),
+            thumb: thumb.clone(),
             created_at: file.created_at,
             updated_at: file.updated_at,
-        })
+            caption: None,
+            uploaded_by: None,
+            favorite: false,
+            tags: Vec::new(),
+            exif: None,
+        }
+    }
+}
+
+/// Videos and other files have a single url, files without one are still
+/// listed but cannot be opened
+fn non_image_file(file: FileObject) -> Photo {
+    let kind = if file.content_type.starts_with("video/") && file.url.is_some() {
+        MediaKind::Video
+    } else {
+        MediaKind::File
+    };
+    let url = file.url.clone().unwrap_or_default();
+    media_file(file, kind, &url)
+}
+
+/// Every version points to the same url
+fn media_file(file: FileObject, kind: MediaKind, url: &str) -> Photo {
+    let version = |version: ImgVersion| PhotoVersionDto {
+        version,
+        dimension: ImgDimension {
+            width: 0,
+            height: 0,
+        },
+        url: url.to_string(),
+    };
+
+    Photo {
+        id: file.id,
+        dir_id: file.dir_id,
+        name: file.name,
+        filename: file.filename,
+        content_type: file.content_type,
+        size: file.size,
+        kind,
+        orig: version(ImgVersion::Original),
+        preview: version(ImgVersion::Preview),
+        thumb: version(ImgVersion::Thumbnail),
+        created_at: file.created_at,
+        updated_at: file.updated_at,
+        caption: None,
+        uploaded_by: None,
+        favorite: false,
+        tags: Vec::new(),
+        exif: None,
     }
 }
 
@@ -172,13 +440,71 @@ pub struct ImgVersionDto {
     pub url: Option<String>,
 }
 
+impl TransferPhotosForm {
+    /// Builds the form from raw pairs since serde forms cannot collect repeated keys
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut form = Self {
+            photo_ids: Vec::new(),
+            target_album_id: "".to_string(),
+            mode: TransferMode::Copy,
+            token: "".to_string(),
+        };
+
+        for (key, value) in pairs {
+            match key.as_str() {
+                "photo_id" if !value.is_empty() && !form.photo_ids.contains(&value) => {
+                    form.photo_ids.push(value)
+                }
+                "target_album_id" => form.target_album_id = value,
+                "mode" if value == "move" => form.mode = TransferMode::Move,
+                "token" => form.token = value,
+                _ => {}
+            }
+        }
+
+        form
+    }
+}
+
+impl BulkDeletePhotosForm {
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut form = Self {
+            photo_ids: Vec::new(),
+            loaded: None,
+            token: "".to_string(),
+        };
+
+        for (key, value) in pairs {
+            match key.as_str() {
+                "photo_id" if !value.is_empty() && !form.photo_ids.contains(&value) => {
+                    form.photo_ids.push(value)
+                }
+                "loaded" => form.loaded = value.parse().ok(),
+                "token" => form.token = value,
+                _ => {}
+            }
+        }
+
+        form
+    }
+}
+
+impl core::fmt::Display for TransferMode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Copy => write!(f, "copy"),
+            Self::Move => write!(f, "move"),
+        }
+    }
+}
+
 /// Convert ImgVersion to String
 impl core::fmt::Display for ImgVersion {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
-            Self::Original => write!(f, "{}", "orig"),
-            Self::Preview => write!(f, "{}", "prev"),
-            Self::Thumbnail => write!(f, "{}", "thumb"),
+            Self::Original => write!(f, "orig"),
+            Self::Preview => write!(f, "prev"),
+            Self::Thumbnail => write!(f, "thumb"),
         }
     }
 }
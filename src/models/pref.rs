@@ -3,12 +3,17 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Pref {
     pub theme: String,
+    pub bucket_id: Option<String>,
+    /// Album listing layout, either "list" or "grid"
+    pub album_view: String,
 }
 
 impl Pref {
     pub fn new() -> Self {
         Self {
             theme: String::from("light"),
+            bucket_id: None,
+            album_view: String::from("list"),
         }
     }
 }
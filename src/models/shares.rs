@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// How long a new share link may stay valid, in days
+pub const SHARE_EXPIRY_DAYS: [i64; 4] = [1, 7, 30, 90];
+
+/// Read-only access to an album for anyone holding the link, keyed by id
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub bucket_id: String,
+    pub album_id: String,
+    pub created_by: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ShareParams {
+    pub share_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareLinkParams {
+    pub share_id: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct NewShareLinkForm {
+    pub expires_in: i64,
+    pub token: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RevokeShareLinkForm {
+    pub token: String,
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use super::Photo;
+
+/// Seconds each photo can stay on screen, offered in the slideshow settings
+pub const SLIDESHOW_INTERVALS: [u32; 6] = [3, 5, 10, 15, 30, 60];
+pub const DEFAULT_SLIDESHOW_INTERVAL: u32 = 5;
+
+#[derive(Deserialize)]
+pub struct SlideshowParams {
+    pub interval: Option<u32>,
+    pub shuffle: Option<bool>,
+
+    #[serde(rename = "loop")]
+    pub repeat: Option<bool>,
+}
+
+impl SlideshowParams {
+    /// Out of range values are pulled to the nearest offered interval
+    pub fn interval(&self) -> u32 {
+        let min = SLIDESHOW_INTERVALS[0];
+        let max = SLIDESHOW_INTERVALS[SLIDESHOW_INTERVALS.len() - 1];
+        self.interval
+            .unwrap_or(DEFAULT_SLIDESHOW_INTERVAL)
+            .clamp(min, max)
+    }
+
+    pub fn shuffle(&self) -> bool {
+        self.shuffle.unwrap_or(false)
+    }
+
+    pub fn repeat(&self) -> bool {
+        self.repeat.unwrap_or(false)
+    }
+
+    /// Query string that keeps the settings, used for the playlist url
+    pub fn to_query(&self) -> String {
+        format!(
+            "interval={}&shuffle={}&loop={}",
+            self.interval(),
+            self.shuffle(),
+            self.repeat()
+        )
+    }
+}
+
+/// Everything the slideshow needs to play an album, shuffling is left to the browser
+#[derive(Serialize)]
+pub struct Playlist {
+    pub interval: u32,
+    pub shuffle: bool,
+
+    #[serde(rename = "loop")]
+    pub repeat: bool,
+
+    pub slides: Vec<Slide>,
+}
+
+/// A single photo in the playlist, always the preview version
+#[derive(Serialize)]
+pub struct Slide {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<Photo> for Slide {
+    fn from(photo: Photo) -> Self {
+        Self {
+            id: photo.id,
+            name: photo.name,
+            url: photo.preview.url,
+            width: photo.preview.dimension.width,
+            height: photo.preview.dimension.height,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Keeps the tag input and the tags file reasonably small
+pub const MAX_PHOTO_TAGS: usize = 20;
+pub const MAX_TAG_LENGTH: usize = 30;
+
+/// Tags on a photo, keyed by photo id
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PhotoTags {
+    /// files-rs needs the bucket and album to find the photo again
+    pub bucket_id: String,
+    pub album_id: String,
+    pub tags: Vec<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PhotoTagsForm {
+    /// Comma separated
+    pub tags: String,
+    pub token: String,
+}
+
+/// A tag and the number of photos having it
+pub struct TagCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Splits the comma separated input into lowercase tags without duplicates.
+/// Spaces inside a tag become dashes so tags work as url segments.
+pub fn parse_tags(input: &str) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+    for item in input.split(',') {
+        let tag = item
+            .trim()
+            .trim_start_matches('#')
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("-")
+            .to_lowercase();
+
+        if tag.is_empty() || tags.contains(&tag) {
+            continue;
+        }
+
+        let valid = tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if !valid || tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(Error::ValidationError(format!(
+                "Tags must be at most {} letters, numbers, dashes or underscores.",
+                MAX_TAG_LENGTH
+            )));
+        }
+        tags.push(tag);
+    }
+
+    if tags.len() > MAX_PHOTO_TAGS {
+        return Err(Error::ValidationError(format!(
+            "A photo can have at most {} tags.",
+            MAX_PHOTO_TAGS
+        )));
+    }
+
+    Ok(tags)
+}
+
+#[derive(Deserialize)]
+pub struct TagParams {
+    pub tag: String,
+}
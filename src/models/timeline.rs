@@ -0,0 +1,68 @@
+use chrono::DateTime;
+use serde::Deserialize;
+
+use super::Photo;
+
+/// Photos per timeline page, each page is loaded on scroll
+pub const TIMELINE_PAGE_SIZE: u32 = 50;
+
+#[derive(Deserialize)]
+pub struct TimelineParams {
+    pub page: Option<u32>,
+    /// Month of the last photo already shown, written as `YYYY-MM`
+    pub month: Option<String>,
+}
+
+/// A photo and the album it lives in
+#[derive(Clone)]
+pub struct TimelinePhoto {
+    pub photo: Photo,
+    pub album_label: String,
+}
+
+impl TimelinePhoto {
+    /// Photos are placed by capture time when known, otherwise by upload time
+    pub fn timestamp(&self) -> i64 {
+        self.photo.taken_at()
+    }
+
+    pub fn month(&self) -> String {
+        format_month(self.timestamp(), "%Y-%m")
+    }
+}
+
+/// Timeline grid item, the header is set on the first photo of each month
+pub struct TimelineEntry {
+    pub header: Option<String>,
+    pub item: TimelinePhoto,
+}
+
+/// Newest first, ties keep a stable order across requests
+pub fn sort_timeline(items: &mut [TimelinePhoto]) {
+    items.sort_by(|a, b| {
+        b.timestamp()
+            .cmp(&a.timestamp())
+            .then_with(|| a.photo.id.cmp(&b.photo.id))
+    });
+}
+
+/// Adds month headers, skipping the one already shown on the previous page
+pub fn timeline_entries(items: Vec<TimelinePhoto>, prev_month: Option<&str>) -> Vec<TimelineEntry> {
+    let mut month: Option<String> = prev_month.map(|m| m.to_string());
+    items
+        .into_iter()
+        .map(|item| {
+            let item_month = item.month();
+            let header = (month.as_deref() != Some(item_month.as_str()))
+                .then(|| format_month(item.timestamp(), "%B %Y"));
+            month = Some(item_month);
+            TimelineEntry { header, item }
+        })
+        .collect()
+}
+
+fn format_month(ts: i64, fmt: &str) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .map(|dt| dt.format(fmt).to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// A resumable upload being assembled on disk before it is sent to files-rs
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResumableUpload {
+    pub id: String,
+    pub user_id: String,
+    pub bucket_id: String,
+    pub album_id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub length: u64,
+    pub expires_at: i64,
+
+    /// Bytes received so far, derived from the data file
+    #[serde(skip)]
+    pub offset: u64,
+}
+
+pub struct NewResumableUpload {
+    pub user_id: String,
+    pub bucket_id: String,
+    pub album_id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub length: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AlbumUploadSettingsForm {
+    pub normalize_uploads: Option<String>,
+    pub token: String,
+}
@@ -23,6 +23,7 @@ pub struct Actor {
 }
 
 impl Actor {
+    #[allow(clippy::ptr_arg)]
     pub fn has_permissions(&self, permissions: &Vec<Permission>) -> bool {
         permissions
             .iter()
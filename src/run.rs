@@ -1,4 +1,6 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::extract::FromRef;
 use axum::Router;
@@ -6,25 +8,122 @@ use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_cookies::CookieManagerLayer;
 use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::{info, Level};
+use tracing::{error, info, Level};
 
 use crate::config::Config;
+use crate::models::{AlbumMeta, GuestUploadLink, PhotoMeta, PhotoTags, ShareLink, UserFavorites};
+use crate::services::{
+    ActorCache, AttemptLimiter, ExifStore, FilesApi, FilesClient, JsonStore, PhotoListingCache,
+    ServiceAccount, StagingStore, TimelineCache, UploadStore,
+};
 use crate::web::{assets_routes, private_routes, public_routes, routes_fallback};
 use crate::Result;
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub config: Arc<Config>,
+    pub files: Arc<dyn FilesApi>,
+    pub actor_cache: Arc<ActorCache>,
+    pub timeline: Arc<TimelineCache>,
+    pub photo_listings: Arc<PhotoListingCache>,
+    pub uploads: Arc<UploadStore>,
+    pub photo_meta: Arc<JsonStore<PhotoMeta>>,
+    pub photo_exif: Arc<ExifStore>,
+    pub album_meta: Arc<JsonStore<AlbumMeta>>,
+    pub favorites: Arc<JsonStore<UserFavorites>>,
+    pub photo_tags: Arc<JsonStore<PhotoTags>>,
+    pub staging: Arc<StagingStore>,
+    pub share_links: Arc<JsonStore<ShareLink>>,
+    pub share_account: Arc<ServiceAccount>,
+    pub guest_upload_links: Arc<JsonStore<GuestUploadLink>>,
+    #[from_ref(skip)]
+    pub guest_upload_account: Arc<ServiceAccount>,
+    pub unlock_attempts: Arc<AttemptLimiter>,
 }
 
 pub async fn run(config: Config) -> Result<()> {
     let port = config.port;
-    let frontend_dir = config.frontend_dir.clone();
+    let actor_cache = Arc::new(ActorCache::default());
+    let files = FilesClient::new(&config.api_url, actor_cache.clone())?;
+    let uploads = Arc::new(UploadStore::new(config.upload_dir.clone())?);
+    let photo_meta = JsonStore::open(config.data_dir.join("photos.json"))?;
+    let photo_exif = ExifStore::open(config.data_dir.join("exif"))?;
+    let album_meta = JsonStore::open(config.data_dir.join("albums.json"))?;
+    let favorites = JsonStore::open(config.data_dir.join("favorites.json"))?;
+    let photo_tags = JsonStore::open(config.data_dir.join("tags.json"))?;
+    let staging = Arc::new(StagingStore::open(config.data_dir.join("staging"))?);
+    let share_links = JsonStore::open(config.data_dir.join("shares.json"))?;
+    let guest_upload_links = JsonStore::open(config.data_dir.join("guest_uploads.json"))?;
+    let share_account =
+        ServiceAccount::new(config.share_username.clone(), config.share_password.clone());
+    let guest_upload_account = ServiceAccount::new(
+        config.guest_upload_username.clone(),
+        config.guest_upload_password.clone(),
+    );
     let state = AppState {
         config: Arc::new(config),
+        files: Arc::new(files),
+        actor_cache: actor_cache.clone(),
+        timeline: Arc::new(TimelineCache::default()),
+        photo_listings: Arc::new(PhotoListingCache::default()),
+        uploads: uploads.clone(),
+        photo_meta: Arc::new(photo_meta),
+        photo_exif: Arc::new(photo_exif),
+        album_meta: Arc::new(album_meta),
+        favorites: Arc::new(favorites),
+        photo_tags: Arc::new(photo_tags),
+        staging: staging.clone(),
+        share_links: Arc::new(share_links),
+        share_account: Arc::new(share_account),
+        guest_upload_links: Arc::new(guest_upload_links),
+        guest_upload_account: Arc::new(guest_upload_account),
+        unlock_attempts: Arc::new(AttemptLimiter::default()),
     };
 
-    let routes_all = Router::new()
+    tokio::spawn(cleanup_uploads(uploads, staging));
+
+    let routes_all = build_routes(state);
+
+    // Setup the server
+    let ip = "127.0.0.1";
+    let addr = format!("{}:{}", ip, port);
+    info!("Listening on {}", addr);
+
+    let listener = TcpListener::bind(addr).await.unwrap();
+    // The peer address keys the unlock attempts when no trusted proxy sent one
+    axum::serve(
+        listener,
+        routes_all.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+
+    Ok(())
+}
+
+/// Periodically discards resumable uploads that were never completed and
+/// staged uploads that were never reviewed
+async fn cleanup_uploads(uploads: Arc<UploadStore>, staging: Arc<StagingStore>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        match uploads.remove_expired().await {
+            Ok(count) if count > 0 => info!("Removed {} expired uploads", count),
+            Ok(_) => {}
+            Err(e) => error!("Error: {}", e),
+        }
+        match staging.remove_expired().await {
+            Ok(count) if count > 0 => info!("Removed {} expired staged uploads", count),
+            Ok(_) => {}
+            Err(e) => error!("Error: {}", e),
+        }
+    }
+}
+
+pub fn build_routes(state: AppState) -> Router {
+    let frontend_dir = state.config.frontend_dir.clone();
+
+    Router::new()
         .merge(private_routes(state.clone()))
         .merge(public_routes(state.clone()))
         .merge(assets_routes(&frontend_dir))
@@ -36,17 +135,5 @@ pub async fn run(config: Config) -> Result<()> {
                     .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
                     .on_response(DefaultOnResponse::new().level(Level::INFO)),
             ),
-        );
-
-    // Setup the server
-    let ip = "127.0.0.1";
-    let addr = format!("{}:{}", ip, port);
-    info!("Listening on {}", addr);
-
-    let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, routes_all.into_make_service())
-        .await
-        .unwrap();
-
-    Ok(())
+        )
 }
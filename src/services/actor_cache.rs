@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::models::{Actor, Bucket};
+
+/// How long a validated actor is trusted before asking files-rs again
+pub const ACTOR_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Maximum number of cached actors
+pub const ACTOR_CACHE_MAX_ENTRIES: usize = 1000;
+
+struct CachedActor {
+    actor: Actor,
+    buckets: Option<Vec<Bucket>>,
+    expires_at: Instant,
+}
+
+/// Short-lived cache of validated actors, and the buckets they can access,
+/// keyed by the hash of their auth token
+pub struct ActorCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, CachedActor>>,
+}
+
+impl ActorCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<Actor> {
+        let key = hash_token(token);
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.actor.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, token: &str, actor: Actor) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let key = hash_token(token);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            // Still full, evict the entry closest to expiring
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(k, _)| k.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            key,
+            CachedActor {
+                actor,
+                buckets: None,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+
+    pub fn get_buckets(&self, token: &str) -> Option<Vec<Bucket>> {
+        let key = hash_token(token);
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .get(&key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .and_then(|entry| entry.buckets.clone())
+    }
+
+    /// Buckets are only kept alongside an already cached actor
+    pub fn insert_buckets(&self, token: &str, buckets: Vec<Bucket>) {
+        let key = hash_token(token);
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&key) {
+            entry.buckets = Some(buckets);
+        }
+    }
+
+    pub fn invalidate(&self, token: &str) {
+        let key = hash_token(token);
+        self.entries.lock().unwrap().remove(&key);
+    }
+}
+
+impl Default for ActorCache {
+    fn default() -> Self {
+        Self::new(ACTOR_CACHE_TTL, ACTOR_CACHE_MAX_ENTRIES)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::User;
+
+    fn actor(id: &str) -> Actor {
+        Actor {
+            id: id.to_string(),
+            client_id: "client".to_string(),
+            default_bucket_id: None,
+            scope: "auth vault".to_string(),
+            user: User {
+                id: id.to_string(),
+                client_id: "client".to_string(),
+                username: id.to_string(),
+                status: "active".to_string(),
+                roles: Vec::new(),
+                created_at: 0,
+                updated_at: 0,
+            },
+            roles: Vec::new(),
+            permissions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_get_and_invalidate() {
+        let cache = ActorCache::default();
+        assert!(cache.get("token-1").is_none());
+
+        cache.insert("token-1", actor("user-1"));
+        assert_eq!(cache.get("token-1").unwrap().id, "user-1");
+        assert!(cache.get("token-2").is_none());
+
+        cache.invalidate("token-1");
+        assert!(cache.get("token-1").is_none());
+    }
+
+    #[test]
+    fn test_buckets() {
+        let cache = ActorCache::default();
+        let bucket = Bucket {
+            id: "bucket-1".to_string(),
+            client_id: "client".to_string(),
+            name: "photos".to_string(),
+            created_at: 0,
+        };
+
+        // Not cached without the actor
+        cache.insert_buckets("token-1", vec![bucket.clone()]);
+        assert!(cache.get_buckets("token-1").is_none());
+
+        cache.insert("token-1", actor("user-1"));
+        assert!(cache.get_buckets("token-1").is_none());
+        cache.insert_buckets("token-1", vec![bucket]);
+        assert_eq!(cache.get_buckets("token-1").unwrap()[0].id, "bucket-1");
+
+        cache.invalidate("token-1");
+        assert!(cache.get_buckets("token-1").is_none());
+    }
+
+    #[test]
+    fn test_expired_entry() {
+        let cache = ActorCache::new(Duration::ZERO, 10);
+        cache.insert("token-1", actor("user-1"));
+        assert!(cache.get("token-1").is_none());
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let cache = ActorCache::new(ACTOR_CACHE_TTL, 2);
+        cache.insert("token-1", actor("user-1"));
+        cache.insert("token-2", actor("user-2"));
+        cache.insert("token-3", actor("user-3"));
+
+        let cached = ["token-1", "token-2", "token-3"]
+            .iter()
+            .filter(|token| cache.get(token).is_some())
+            .count();
+        assert_eq!(cached, 2);
+        assert!(cache.get("token-3").is_some());
+    }
+}
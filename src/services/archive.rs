@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+
+use axum::body::Bytes;
+use chrono::{DateTime, Datelike, Timelike};
+use crc32fast::Hasher;
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x08074b50;
+const CENTRAL_HEADER_SIG: u32 = 0x02014b50;
+const ZIP64_END_SIG: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x07064b50;
+const END_SIG: u32 = 0x06054b50;
+
+/// Sizes and crc come after the data, names are UTF-8
+const FLAGS: u16 = 0x0008 | 0x0800;
+const VERSION: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+
+/// Writes a ZIP archive one piece at a time so it can be streamed.
+///
+/// Entries are stored without compression since photos are already
+/// compressed. Each entry must stay under 4 GiB but the archive itself
+/// switches to ZIP64 records once it grows past that.
+pub struct ZipArchive {
+    offset: u64,
+    entries: Vec<ZipEntry>,
+    names: HashSet<String>,
+    current: Option<CurrentEntry>,
+}
+
+struct ZipEntry {
+    name: String,
+    dos_time: u16,
+    dos_date: u16,
+    crc: u32,
+    size: u32,
+    offset: u64,
+}
+
+struct CurrentEntry {
+    entry: ZipEntry,
+    hasher: Hasher,
+    size: u64,
+}
+
+impl Default for ZipArchive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZipArchive {
+    pub fn new() -> Self {
+        Self {
+            offset: 0,
+            entries: Vec::new(),
+            names: HashSet::new(),
+            current: None,
+        }
+    }
+
+    /// Starts a new entry and returns its local header.
+    /// Repeated names get a numbered suffix so nothing is overwritten on extract.
+    pub fn start_entry(&mut self, name: &str, timestamp: i64) -> Bytes {
+        if self.current.is_some() {
+            self.finish_entry();
+        }
+
+        let name = self.unique_name(name);
+        let (dos_time, dos_date) = dos_datetime(timestamp);
+
+        let mut buf: Vec<u8> = Vec::with_capacity(30 + name.len());
+        put_u32(&mut buf, LOCAL_HEADER_SIG);
+        put_u16(&mut buf, VERSION);
+        put_u16(&mut buf, FLAGS);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, dos_time);
+        put_u16(&mut buf, dos_date);
+        put_u32(&mut buf, 0);
+        put_u32(&mut buf, 0);
+        put_u32(&mut buf, 0);
+        put_u16(&mut buf, name.len() as u16);
+        put_u16(&mut buf, 0);
+        buf.extend_from_slice(name.as_bytes());
+
+        self.current = Some(CurrentEntry {
+            entry: ZipEntry {
+                name,
+                dos_time,
+                dos_date,
+                crc: 0,
+                size: 0,
+                offset: self.offset,
+            },
+            hasher: Hasher::new(),
+            size: 0,
+        });
+        self.offset += buf.len() as u64;
+
+        Bytes::from(buf)
+    }
+
+    /// Accounts for a chunk of the current entry, the chunk itself is sent as is.
+    /// Nothing changes when the chunk is refused.
+    pub fn add_data(&mut self, chunk: &[u8]) -> crate::Result<()> {
+        let Some(current) = self.current.as_mut() else {
+            return Err("No archive entry started.".into());
+        };
+
+        let size = current.size + chunk.len() as u64;
+        if size > u32::MAX as u64 {
+            return Err("File is too large for the archive.".into());
+        }
+
+        current.size = size;
+        current.hasher.update(chunk);
+        self.offset += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Closes the current entry and returns its data descriptor
+    pub fn finish_entry(&mut self) -> Bytes {
+        let Some(current) = self.current.take() else {
+            return Bytes::new();
+        };
+
+        let mut entry = current.entry;
+        entry.crc = current.hasher.finalize();
+        entry.size = current.size as u32;
+
+        let mut buf: Vec<u8> = Vec::with_capacity(16);
+        put_u32(&mut buf, DATA_DESCRIPTOR_SIG);
+        put_u32(&mut buf, entry.crc);
+        put_u32(&mut buf, entry.size);
+        put_u32(&mut buf, entry.size);
+
+        self.offset += buf.len() as u64;
+        self.entries.push(entry);
+
+        Bytes::from(buf)
+    }
+
+    /// Writes the central directory, nothing can be added afterwards
+    pub fn finish(mut self) -> Bytes {
+        let descriptor = self.finish_entry();
+        let mut buf = descriptor.to_vec();
+        let cd_offset = self.offset;
+
+        for entry in self.entries.iter() {
+            let zip64 = entry.offset >= u32::MAX as u64;
+            put_u32(&mut buf, CENTRAL_HEADER_SIG);
+            put_u16(&mut buf, if zip64 { VERSION_ZIP64 } else { VERSION });
+            put_u16(&mut buf, if zip64 { VERSION_ZIP64 } else { VERSION });
+            put_u16(&mut buf, FLAGS);
+            put_u16(&mut buf, 0);
+            put_u16(&mut buf, entry.dos_time);
+            put_u16(&mut buf, entry.dos_date);
+            put_u32(&mut buf, entry.crc);
+            put_u32(&mut buf, entry.size);
+            put_u32(&mut buf, entry.size);
+            put_u16(&mut buf, entry.name.len() as u16);
+            put_u16(&mut buf, if zip64 { 12 } else { 0 });
+            put_u16(&mut buf, 0);
+            put_u16(&mut buf, 0);
+            put_u16(&mut buf, 0);
+            put_u32(&mut buf, 0);
+            put_u32(&mut buf, if zip64 { u32::MAX } else { entry.offset as u32 });
+            buf.extend_from_slice(entry.name.as_bytes());
+            if zip64 {
+                put_u16(&mut buf, 0x0001);
+                put_u16(&mut buf, 8);
+                put_u64(&mut buf, entry.offset);
+            }
+        }
+
+        let cd_size = (buf.len() - descriptor.len()) as u64;
+        let count = self.entries.len() as u64;
+        let zip64 =
+            count >= u16::MAX as u64 || cd_offset >= u32::MAX as u64 || cd_size >= u32::MAX as u64;
+
+        if zip64 {
+            let zip64_offset = cd_offset + cd_size;
+            put_u32(&mut buf, ZIP64_END_SIG);
+            put_u64(&mut buf, 44);
+            put_u16(&mut buf, VERSION_ZIP64);
+            put_u16(&mut buf, VERSION_ZIP64);
+            put_u32(&mut buf, 0);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, cd_size);
+            put_u64(&mut buf, cd_offset);
+
+            put_u32(&mut buf, ZIP64_LOCATOR_SIG);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, zip64_offset);
+            put_u32(&mut buf, 1);
+        }
+
+        put_u32(&mut buf, END_SIG);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, count.min(u16::MAX as u64) as u16);
+        put_u16(&mut buf, count.min(u16::MAX as u64) as u16);
+        put_u32(&mut buf, cd_size.min(u32::MAX as u64) as u32);
+        put_u32(&mut buf, cd_offset.min(u32::MAX as u64) as u32);
+        put_u16(&mut buf, 0);
+
+        Bytes::from(buf)
+    }
+
+    fn unique_name(&mut self, name: &str) -> String {
+        // Keep paths flat and avoid anything that escapes the extract dir
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        let name = match name.trim_start_matches('.') {
+            "" => "photo".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+
+        let mut unique = name.clone();
+        let mut counter = 1;
+        while self.names.contains(&unique) {
+            unique = match name.rsplit_once('.') {
+                Some((stem, ext)) if !stem.is_empty() => format!("{} ({}).{}", stem, counter, ext),
+                _ => format!("{} ({})", name, counter),
+            };
+            counter += 1;
+        }
+
+        self.names.insert(unique.clone());
+        unique
+    }
+}
+
+/// MS-DOS date and time, which cannot go below 1980
+fn dos_datetime(timestamp: i64) -> (u16, u16) {
+    let dt = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+    if dt.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+
+    let time = (dt.hour() << 11) | (dt.minute() << 5) | (dt.second() / 2);
+    let date = (((dt.year() - 1980) as u32) << 9) | (dt.month() << 5) | dt.day();
+    (time as u16, date as u16)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(buf: &[u8], pos: usize) -> u16 {
+        u16::from_le_bytes([buf[pos], buf[pos + 1]])
+    }
+
+    fn u32_at(buf: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_archive_layout() {
+        let mut zip = ZipArchive::new();
+        let mut buf: Vec<u8> = Vec::new();
+
+        buf.extend_from_slice(&zip.start_entry("beach.jpg", 1_700_000_000));
+        zip.add_data(b"hello ").unwrap();
+        buf.extend_from_slice(b"hello ");
+        zip.add_data(b"world").unwrap();
+        buf.extend_from_slice(b"world");
+        buf.extend_from_slice(&zip.finish_entry());
+        buf.extend_from_slice(&zip.start_entry("beach.jpg", 0));
+        buf.extend_from_slice(&zip.finish());
+
+        assert_eq!(u32_at(&buf, 0), LOCAL_HEADER_SIG);
+
+        let end = buf.len() - 22;
+        assert_eq!(u32_at(&buf, end), END_SIG);
+        assert_eq!(u16_at(&buf, end + 10), 2);
+
+        let cd_offset = u32_at(&buf, end + 16) as usize;
+        assert_eq!(u32_at(&buf, cd_offset), CENTRAL_HEADER_SIG);
+        assert_eq!(
+            u32_at(&buf, cd_offset + 16),
+            crc32fast::hash(b"hello world")
+        );
+        assert_eq!(u32_at(&buf, cd_offset + 20), 11);
+        assert_eq!(&buf[cd_offset + 46..cd_offset + 55], b"beach.jpg");
+
+        let cd_size = u32_at(&buf, end + 12) as usize;
+        assert_eq!(cd_offset + cd_size, end);
+
+        let second = cd_offset + 46 + 9;
+        assert_eq!(u32_at(&buf, second + 20), 0);
+        assert_eq!(&buf[second + 46..second + 59], b"beach (1).jpg");
+    }
+
+    #[test]
+    fn test_refuse_entries_over_4gib() {
+        let mut zip = ZipArchive::new();
+        let header = zip.start_entry("huge.mov", 0);
+        zip.add_data(b"ab").unwrap();
+        zip.current.as_mut().unwrap().size = u32::MAX as u64 - 1;
+
+        assert!(zip.add_data(b"cd").is_err());
+        assert_eq!(zip.current.as_ref().unwrap().size, u32::MAX as u64 - 1);
+        assert_eq!(zip.offset, header.len() as u64 + 2);
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let mut zip = ZipArchive::new();
+        assert_eq!(zip.unique_name("a.jpg"), "a.jpg");
+        assert_eq!(zip.unique_name("a.jpg"), "a (1).jpg");
+        assert_eq!(zip.unique_name("a.jpg"), "a (2).jpg");
+        assert_eq!(zip.unique_name("../etc/passwd"), "_etc_passwd");
+        assert_eq!(zip.unique_name(".."), "photo");
+        assert_eq!(zip.unique_name("README"), "README");
+        assert_eq!(zip.unique_name("README"), "README (1)");
+    }
+}
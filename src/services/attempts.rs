@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Wrong passphrases allowed before the visitor has to wait
+pub const MAX_UNLOCK_ATTEMPTS: u32 = 5;
+
+/// How long failed attempts are remembered
+pub const UNLOCK_ATTEMPTS_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Entries kept, the one closest to expiring goes first once full
+const ATTEMPTS_MAX_ENTRIES: usize = 1000;
+
+struct Attempts {
+    count: u32,
+    expires_at: Instant,
+}
+
+/// Counts attempts per key, like an album and client address pair
+pub struct AttemptLimiter {
+    max_attempts: u32,
+    window: Duration,
+    entries: Mutex<HashMap<String, Attempts>>,
+}
+
+impl AttemptLimiter {
+    pub fn new(max_attempts: u32, window: Duration) -> Self {
+        Self {
+            max_attempts,
+            window,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts the attempt before it is checked so concurrent attempts cannot
+    /// slip past the limit. Returns false when the key has none left.
+    /// The window starts over with the first attempt after it ends.
+    pub fn try_attempt(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if !entries.contains_key(key) && entries.len() >= ATTEMPTS_MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            // Still full, evict the entry closest to expiring
+            if entries.len() >= ATTEMPTS_MAX_ENTRIES {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(k, _)| k.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        let entry = entries.entry(key.to_string()).or_insert(Attempts {
+            count: 0,
+            expires_at: now + self.window,
+        });
+        if entry.expires_at <= now {
+            entry.count = 0;
+            entry.expires_at = now + self.window;
+        }
+        if entry.count >= self.max_attempts {
+            return false;
+        }
+        entry.count += 1;
+        true
+    }
+
+    /// Forgets the attempts of a key after one succeeded
+    pub fn reset(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for AttemptLimiter {
+    fn default() -> Self {
+        Self::new(MAX_UNLOCK_ATTEMPTS, UNLOCK_ATTEMPTS_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_after_max_attempts() {
+        let limiter = AttemptLimiter::new(2, UNLOCK_ATTEMPTS_WINDOW);
+        assert!(limiter.try_attempt("a"));
+        assert!(limiter.try_attempt("a"));
+        assert!(!limiter.try_attempt("a"));
+        assert!(limiter.try_attempt("b"));
+
+        limiter.reset("a");
+        assert!(limiter.try_attempt("a"));
+    }
+
+    #[test]
+    fn test_window_expires() {
+        let limiter = AttemptLimiter::new(1, Duration::ZERO);
+        assert!(limiter.try_attempt("a"));
+        assert!(limiter.try_attempt("a"));
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let limiter = AttemptLimiter::new(1, UNLOCK_ATTEMPTS_WINDOW);
+        for i in 0..ATTEMPTS_MAX_ENTRIES + 10 {
+            assert!(limiter.try_attempt(&format!("key-{}", i)));
+        }
+        assert_eq!(limiter.entries.lock().unwrap().len(), ATTEMPTS_MAX_ENTRIES);
+
+        // The newest keys are still limited
+        let newest = format!("key-{}", ATTEMPTS_MAX_ENTRIES + 9);
+        assert!(!limiter.try_attempt(&newest));
+    }
+}
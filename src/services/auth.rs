@@ -1,10 +1,12 @@
-use reqwest::{Client, StatusCode};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::error;
 
 use crate::{models::Actor, Error, Result};
 
+use super::FilesClient;
+
 #[derive(Serialize)]
 pub struct AuthPayload {
     pub username: String,
@@ -16,13 +18,13 @@ pub struct AuthResponse {
     pub token: String,
 }
 
-pub async fn authenticate(api_url: &str, data: AuthPayload) -> Result<AuthResponse> {
+pub(super) async fn authenticate(api: &FilesClient, data: AuthPayload) -> Result<AuthResponse> {
     let mut body = HashMap::new();
     body.insert("username", data.username);
     body.insert("password", data.password);
 
-    let url = format!("{}/v1/auth/token", api_url);
-    let result = Client::new().post(url.as_str()).json(&body).send().await;
+    let url = format!("{}/v1/auth/token", &api.api_url);
+    let result = api.client.post(url.as_str()).json(&body).send().await;
     let Ok(response) = result else {
         return Err("Unable to process login information. Try again later.".into());
     };
@@ -47,9 +49,10 @@ pub async fn authenticate(api_url: &str, data: AuthPayload) -> Result<AuthRespon
     }
 }
 
-pub async fn authenticate_token(api_url: &str, token: &str) -> Result<Actor> {
-    let url = format!("{}/v1/user/authz", api_url);
-    let result = Client::new()
+pub(super) async fn authenticate_token(api: &FilesClient, token: &str) -> Result<Actor> {
+    let url = format!("{}/v1/user/authz", &api.api_url);
+    let result = api
+        .client
         .get(url.as_str())
         .header("Authorization", format!("Bearer {}", token))
         .send()
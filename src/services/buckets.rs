@@ -0,0 +1,35 @@
+use reqwest::StatusCode;
+use tracing::error;
+
+use crate::models::Bucket;
+use crate::{Error, Result};
+
+use super::FilesClient;
+
+pub(super) async fn list_buckets(api: &FilesClient, token: &str) -> Result<Vec<Bucket>> {
+    let url = format!("{}/v1/buckets", &api.api_url);
+    let result = api.client.get(url).bearer_auth(token).send().await;
+
+    let Ok(response) = result else {
+        return Err("Unable to list buckets. Try again later.".into());
+    };
+
+    match response.status() {
+        StatusCode::OK => match response.json::<Vec<Bucket>>().await {
+            Ok(buckets) => Ok(buckets),
+            Err(e) => {
+                error!("Error: {}", e);
+                Err(Error::JsonParseError(
+                    "Unable to parse buckets.".to_string(),
+                ))
+            }
+        },
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
+        StatusCode::FORBIDDEN => Err(Error::Forbidden(
+            "You have no permissions to view buckets".to_string(),
+        )),
+        _ => Err(Error::ServiceError(
+            "Unable to list buckets. Try again later.".to_string(),
+        )),
+    }
+}
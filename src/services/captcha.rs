@@ -3,17 +3,15 @@ use serde::Deserialize;
 
 use crate::{Error, Result};
 
-const VERIFY_URL: &str = "https://www.google.com/recaptcha/api/siteverify";
-
 #[derive(Deserialize)]
 struct CaptchaResponse {
     success: bool,
 }
 
-pub async fn validate_catpcha(secret: &str, response: &str) -> Result<()> {
+pub async fn validate_catpcha(verify_url: &str, secret: &str, response: &str) -> Result<()> {
     let post_body = [("secret", secret), ("response", response)];
 
-    let result = Client::new().post(VERIFY_URL).form(&post_body).send().await;
+    let result = Client::new().post(verify_url).form(&post_body).send().await;
     let Ok(response) = result else {
         return Err("Unable to validate captcha. Try again later.".into());
     };
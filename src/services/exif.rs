@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use axum::body::{Body, Bytes};
+use chrono::NaiveDate;
+use exif::{In, Reader, Tag, Value};
+use futures_util::{stream, StreamExt};
+use tracing::error;
+
+use crate::models::{Photo, PhotoExif};
+use crate::{Error, Result};
+
+/// Bytes kept from the start of an upload, EXIF sits near the start of the file
+pub const EXIF_PROBE_LIMIT: usize = 256 * 1024;
+
+/// Copies the start of an upload aside while it streams through to files-rs
+#[derive(Clone, Default)]
+pub struct ExifProbe {
+    head: Arc<Mutex<Vec<u8>>>,
+}
+
+impl ExifProbe {
+    /// Passes the body through unchanged
+    pub fn tap(&self, body: Body) -> Body {
+        let head = self.head.clone();
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                let mut head = head.lock().unwrap();
+                let room = EXIF_PROBE_LIMIT.saturating_sub(head.len());
+                head.extend_from_slice(&bytes[..room.min(bytes.len())]);
+            }
+            chunk
+        });
+        Body::from_stream(stream)
+    }
+
+    /// Reads EXIF from the `file` field of the copied multipart head
+    pub async fn read(&self, content_type: &str) -> Option<PhotoExif> {
+        let boundary = multer::parse_boundary(content_type).ok()?;
+        let mut head = self.head.lock().unwrap().clone();
+        // Close the head so the file field ends where the copy stopped
+        head.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        let body = stream::once(async move { Ok::<Bytes, std::io::Error>(Bytes::from(head)) });
+
+        let mut multipart = multer::Multipart::new(body, boundary);
+        while let Ok(Some(mut field)) = multipart.next_field().await {
+            if field.name() != Some("file") {
+                continue;
+            }
+
+            let mut data: Vec<u8> = Vec::new();
+            while let Ok(Some(chunk)) = field.chunk().await {
+                data.extend_from_slice(&chunk);
+            }
+            return read_exif(&data);
+        }
+        None
+    }
+}
+
+/// Number of EXIF files read at once when filling a listing
+const EXIF_READ_CONCURRENCY: usize = 16;
+
+/// EXIF of uploaded photos, one small JSON file per photo so that keeping
+/// the details of a new upload never rewrites those of every other photo
+pub struct ExifStore {
+    dir: PathBuf,
+}
+
+impl ExifStore {
+    /// Entries kept in a single `exif.json` next to the dir are moved into it
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            error!("Error: {}", e);
+            return Err("Unable to create EXIF dir.".into());
+        }
+
+        let store = Self { dir };
+        store.import(&store.dir.with_extension("json"))?;
+        Ok(store)
+    }
+
+    pub async fn get(&self, photo_id: &str) -> Option<PhotoExif> {
+        let path = self.path(photo_id)?;
+        let contents = tokio::fs::read(path).await.ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    pub async fn set(&self, photo_id: &str, exif: &PhotoExif) -> Result<()> {
+        let Some(path) = self.path(photo_id) else {
+            return Err(Error::PhotoNotFound);
+        };
+        let Ok(contents) = serde_json::to_vec(exif) else {
+            return Err("Unable to serialize data.".into());
+        };
+
+        match tokio::task::spawn_blocking(move || write_entry(&path, &contents)).await {
+            Ok(result) => result,
+            Err(_) => Err("Unable to save data.".into()),
+        }
+    }
+
+    pub async fn remove(&self, photo_id: &str) -> Result<()> {
+        let Some(path) = self.path(photo_id) else {
+            return Ok(());
+        };
+        match tokio::fs::remove_file(path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                error!("Error: {}", e);
+                Err("Unable to remove data.".into())
+            }
+        }
+    }
+
+    /// Copies the camera details onto photos from files-rs
+    pub async fn fill(&self, photos: &mut [Photo]) {
+        let ids: Vec<String> = photos.iter().map(|photo| photo.id.clone()).collect();
+        let found: Vec<Option<PhotoExif>> = stream::iter(ids)
+            .map(|id| async move { self.get(&id).await })
+            .buffered(EXIF_READ_CONCURRENCY)
+            .collect()
+            .await;
+        for (photo, exif) in photos.iter_mut().zip(found) {
+            photo.exif = exif;
+        }
+    }
+
+    fn import(&self, legacy: &Path) -> Result<()> {
+        let Ok(contents) = fs::read_to_string(legacy) else {
+            return Ok(());
+        };
+        let Ok(entries) = serde_json::from_str::<HashMap<String, PhotoExif>>(&contents) else {
+            return Err(Error::AnyError(format!(
+                "Unable to parse {}.",
+                legacy.display()
+            )));
+        };
+
+        for (photo_id, exif) in entries {
+            let (Some(path), Ok(contents)) = (self.path(&photo_id), serde_json::to_vec(&exif))
+            else {
+                continue;
+            };
+            write_entry(&path, &contents)?;
+        }
+        if let Err(e) = fs::remove_file(legacy) {
+            error!("Error: {}", e);
+            return Err("Unable to remove old EXIF index.".into());
+        }
+        Ok(())
+    }
+
+    /// Photo ids also come from urls, anything but an id stays out of the dir
+    fn path(&self, photo_id: &str) -> Option<PathBuf> {
+        let valid = !photo_id.is_empty()
+            && photo_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.dir.join(format!("{}.json", photo_id)))
+    }
+}
+
+/// Writes to a temporary file first so a crash never leaves a partial file
+fn write_entry(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    if let Err(e) = fs::write(&tmp_path, contents).and_then(|_| fs::rename(&tmp_path, path)) {
+        error!("Error: {}", e);
+        return Err("Unable to save data.".into());
+    }
+    Ok(())
+}
+
+/// Capture date, camera, exposure and location from an image, if it has any
+pub fn read_exif(data: &[u8]) -> Option<PhotoExif> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()?;
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+
+    let make = field(Tag::Make).and_then(ascii);
+    let model = field(Tag::Model).and_then(ascii);
+    let camera = match (make, model) {
+        // Most cameras repeat the make in the model
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => model.or(make),
+    };
+
+    let latitude = coordinate(
+        field(Tag::GPSLatitude),
+        field(Tag::GPSLatitudeRef).and_then(ascii),
+        "S",
+    );
+    let longitude = coordinate(
+        field(Tag::GPSLongitude),
+        field(Tag::GPSLongitudeRef).and_then(ascii),
+        "W",
+    );
+
+    let result = PhotoExif {
+        taken_at: field(Tag::DateTimeOriginal)
+            .or(field(Tag::DateTime))
+            .and_then(timestamp),
+        camera,
+        lens: field(Tag::LensModel).and_then(ascii),
+        exposure_time: field(Tag::ExposureTime).and_then(rational),
+        f_number: field(Tag::FNumber).and_then(rational),
+        iso: field(Tag::PhotographicSensitivity).and_then(|v| v.get_uint(0)),
+        focal_length: field(Tag::FocalLength).and_then(rational),
+        latitude,
+        longitude,
+    };
+
+    (!result.is_empty()).then_some(result)
+}
+
+fn ascii(value: &Value) -> Option<String> {
+    let Value::Ascii(items) = value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(items.first()?);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn rational(value: &Value) -> Option<f64> {
+    let Value::Rational(items) = value else {
+        return None;
+    };
+    items.first().filter(|r| r.denom != 0).map(|r| r.to_f64())
+}
+
+fn timestamp(value: &Value) -> Option<i64> {
+    let Value::Ascii(items) = value else {
+        return None;
+    };
+    let dt = exif::DateTime::from_ascii(items.first()?).ok()?;
+    let date = NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?;
+    let time = date.and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)?;
+    Some(time.and_utc().timestamp())
+}
+
+/// Degrees, minutes and seconds into signed decimal degrees
+fn coordinate(value: Option<&Value>, reference: Option<String>, negative: &str) -> Option<f64> {
+    let Some(Value::Rational(items)) = value else {
+        return None;
+    };
+    if items.len() < 3 || items.iter().any(|r| r.denom == 0) {
+        return None;
+    }
+
+    let degrees = items[0].to_f64() + items[1].to_f64() / 60.0 + items[2].to_f64() / 3600.0;
+    if reference.as_deref() == Some(negative) {
+        Some(-degrees)
+    } else {
+        Some(degrees)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{exif_jpeg, multipart_body};
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("memo-rs-exif-{}", Uuid::new_v4()))
+            .join("exif")
+    }
+
+    #[test]
+    fn test_read_exif() {
+        let exif = read_exif(&exif_jpeg()).unwrap();
+        assert_eq!(exif.taken_at, Some(1718454600));
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS R6"));
+        assert_eq!(exif.lens.as_deref(), Some("RF24-105mm F4 L IS USM"));
+        assert_eq!(
+            exif.exposure_label().as_deref(),
+            Some("1/250 s · f/4 · ISO 200 · 50 mm")
+        );
+        assert_eq!(
+            exif.location_label().as_deref(),
+            Some("14.58333, -120.97500")
+        );
+    }
+
+    #[test]
+    fn test_read_exif_without_exif() {
+        assert!(read_exif(b"not an image").is_none());
+        assert!(read_exif(&[0xFF, 0xD8, 0xFF, 0xD9]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        // EXIF at the start of a file larger than the probe
+        let mut data = exif_jpeg();
+        data.extend(vec![0u8; EXIF_PROBE_LIMIT * 2]);
+        let (content_type, original) = multipart_body("photo.jpg", "image/jpeg", &data);
+
+        let probe = ExifProbe::default();
+        let body = probe.tap(Body::from(original.clone()));
+        let forwarded = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(forwarded.to_vec(), original);
+
+        let exif = probe.read(&content_type).await.unwrap();
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS R6"));
+    }
+
+    #[tokio::test]
+    async fn test_exif_store() {
+        let store = ExifStore::open(temp_dir()).unwrap();
+        let exif = read_exif(&exif_jpeg()).unwrap();
+        store.set("file-1", &exif).await.unwrap();
+        assert!(store.set("../file-2", &exif).await.is_err());
+
+        let found = store.get("file-1").await.unwrap();
+        assert_eq!(found.camera.as_deref(), Some("Canon EOS R6"));
+        assert!(store.get("file-2").await.is_none());
+
+        store.remove("file-1").await.unwrap();
+        store.remove("file-1").await.unwrap();
+        assert!(store.get("file-1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exif_store_import() {
+        let dir = temp_dir();
+        let exif = read_exif(&exif_jpeg()).unwrap();
+        let entries = HashMap::from([("file-1".to_string(), exif)]);
+        fs::create_dir_all(dir.parent().unwrap()).unwrap();
+        fs::write(
+            dir.with_extension("json"),
+            serde_json::to_string(&entries).unwrap(),
+        )
+        .unwrap();
+
+        let store = ExifStore::open(dir.clone()).unwrap();
+        assert!(!dir.with_extension("json").exists());
+        assert_eq!(
+            store.get("file-1").await.unwrap().taken_at,
+            Some(1718454600)
+        );
+    }
+}
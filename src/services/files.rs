@@ -0,0 +1,322 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::{Body, Bytes};
+use futures_util::Stream;
+use reqwest::Client;
+
+use crate::models::{
+    Actor, Album, Bucket, ListAlbumsParams, ListPhotosParams, NewAlbum, Paginated, Photo,
+    UpdateAlbum, UpdatePhoto, DEFAULT_PHOTO_PAGE_SIZE,
+};
+use crate::{Error, Result};
+
+use super::{auth, buckets, photos, ActorCache, AuthPayload, AuthResponse};
+
+/// Contents of a downloaded file
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+/// Page size used when walking through every album
+const ALBUMS_BATCH_SIZE: u32 = 50;
+
+/// Operations memo-rs needs from the files backend
+#[async_trait]
+pub trait FilesApi: Send + Sync {
+    async fn authenticate(&self, data: AuthPayload) -> Result<AuthResponse>;
+
+    async fn authenticate_token(&self, token: &str) -> Result<Actor>;
+
+    async fn list_buckets(&self, token: &str) -> Result<Vec<Bucket>>;
+
+    async fn list_albums(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        params: &ListAlbumsParams,
+    ) -> Result<Paginated<Album>>;
+
+    /// Fetches every page of albums in the bucket
+    async fn list_all_albums(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        keyword: Option<String>,
+    ) -> Result<Vec<Album>> {
+        let mut albums: Vec<Album> = Vec::new();
+        let mut page: u32 = 1;
+
+        loop {
+            let params = ListAlbumsParams {
+                keyword: keyword.clone(),
+                page: Some(page),
+                per_page: Some(ALBUMS_BATCH_SIZE),
+                ..Default::default()
+            };
+            let listing = self.list_albums(token, bucket_id, &params).await?;
+            albums.extend(listing.data);
+
+            if page as i64 >= listing.meta.total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(albums)
+    }
+
+    async fn create_album(&self, token: &str, bucket_id: &str, data: &NewAlbum) -> Result<Album>;
+
+    async fn get_album(&self, token: &str, bucket_id: &str, album_id: &str) -> Result<Album>;
+
+    async fn update_album(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        data: &UpdateAlbum,
+    ) -> Result<Album>;
+
+    async fn delete_album(&self, token: &str, bucket_id: &str, album_id: &str) -> Result<()>;
+
+    async fn list_photos(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        params: &ListPhotosParams,
+    ) -> Result<Paginated<Photo>>;
+
+    /// Fetches every page of photos in the album
+    async fn list_all_photos(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+    ) -> Result<Vec<Photo>> {
+        let mut photos: Vec<Photo> = Vec::new();
+        let mut page: u32 = 1;
+
+        loop {
+            let params = ListPhotosParams {
+                page: Some(page),
+                per_page: Some(DEFAULT_PHOTO_PAGE_SIZE),
+                ..Default::default()
+            };
+            let listing = self
+                .list_photos(token, bucket_id, album_id, &params)
+                .await?;
+            photos.extend(listing.data);
+
+            if page as i64 >= listing.meta.total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(photos)
+    }
+
+    async fn get_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+    ) -> Result<Photo>;
+
+    async fn upload_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        content_type: &str,
+        content_length: Option<u64>,
+        body: Body,
+    ) -> Result<Photo>;
+
+    async fn update_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+        data: &UpdatePhoto,
+    ) -> Result<Photo>;
+
+    /// Copies a photo into another album of the bucket
+    async fn copy_photo(
+        &self,
+        token: &str,
+        photo: &Photo,
+        bucket_id: &str,
+        album_id: &str,
+    ) -> Result<Photo>;
+
+    /// Streams one of the photo versions
+    async fn download_photo(&self, url: &str) -> Result<ByteStream>;
+
+    async fn delete_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+    ) -> Result<()>;
+}
+
+/// files-rs HTTP client sharing one connection pool across requests
+pub struct FilesClient {
+    pub api_url: String,
+    pub client: Client,
+    /// Actors trusted with a token, dropped once files-rs rejects it
+    actor_cache: Arc<ActorCache>,
+}
+
+impl FilesClient {
+    pub fn new(api_url: &str, actor_cache: Arc<ActorCache>) -> Result<Self> {
+        let Ok(client) = Client::builder()
+            .pool_idle_timeout(Duration::from_secs(90))
+            .pool_max_idle_per_host(16)
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()
+        else {
+            return Err("Unable to initialize files API client.".into());
+        };
+
+        Ok(Self {
+            api_url: api_url.to_string(),
+            client,
+            actor_cache,
+        })
+    }
+
+    /// files-rs rejected the token, it must be validated again on the next request
+    pub(super) fn login_required(&self, token: &str) -> Error {
+        self.actor_cache.invalidate(token);
+        Error::LoginRequired("Login first".to_string())
+    }
+}
+
+#[async_trait]
+impl FilesApi for FilesClient {
+    async fn authenticate(&self, data: AuthPayload) -> Result<AuthResponse> {
+        auth::authenticate(self, data).await
+    }
+
+    async fn authenticate_token(&self, token: &str) -> Result<Actor> {
+        auth::authenticate_token(self, token).await
+    }
+
+    async fn list_buckets(&self, token: &str) -> Result<Vec<Bucket>> {
+        buckets::list_buckets(self, token).await
+    }
+
+    async fn list_albums(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        params: &ListAlbumsParams,
+    ) -> Result<Paginated<Album>> {
+        photos::list_albums(self, token, bucket_id, params).await
+    }
+
+    async fn create_album(&self, token: &str, bucket_id: &str, data: &NewAlbum) -> Result<Album> {
+        photos::create_album(self, token, bucket_id, data).await
+    }
+
+    async fn get_album(&self, token: &str, bucket_id: &str, album_id: &str) -> Result<Album> {
+        photos::get_album(self, token, bucket_id, album_id).await
+    }
+
+    async fn update_album(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        data: &UpdateAlbum,
+    ) -> Result<Album> {
+        photos::update_album(self, token, bucket_id, album_id, data).await
+    }
+
+    async fn delete_album(&self, token: &str, bucket_id: &str, album_id: &str) -> Result<()> {
+        photos::delete_album(self, token, bucket_id, album_id).await
+    }
+
+    async fn list_photos(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        params: &ListPhotosParams,
+    ) -> Result<Paginated<Photo>> {
+        photos::list_photos(self, token, bucket_id, album_id, params).await
+    }
+
+    async fn get_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+    ) -> Result<Photo> {
+        photos::get_photo(self, token, bucket_id, album_id, photo_id).await
+    }
+
+    async fn upload_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        content_type: &str,
+        content_length: Option<u64>,
+        body: Body,
+    ) -> Result<Photo> {
+        photos::upload_photo(
+            self,
+            token,
+            bucket_id,
+            album_id,
+            content_type,
+            content_length,
+            body,
+        )
+        .await
+    }
+
+    async fn update_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+        data: &UpdatePhoto,
+    ) -> Result<Photo> {
+        photos::update_photo(self, token, bucket_id, album_id, photo_id, data).await
+    }
+
+    async fn copy_photo(
+        &self,
+        token: &str,
+        photo: &Photo,
+        bucket_id: &str,
+        album_id: &str,
+    ) -> Result<Photo> {
+        photos::copy_photo(self, token, photo, bucket_id, album_id).await
+    }
+
+    async fn download_photo(&self, url: &str) -> Result<ByteStream> {
+        photos::download_photo(self, url).await
+    }
+
+    async fn delete_photo(
+        &self,
+        token: &str,
+        bucket_id: &str,
+        album_id: &str,
+        photo_id: &str,
+    ) -> Result<()> {
+        photos::delete_photo(self, token, bucket_id, album_id, photo_id).await
+    }
+}
@@ -1,9 +1,35 @@
+mod actor_cache;
+mod archive;
+mod attempts;
 mod auth;
+mod buckets;
 mod captcha;
+mod exif;
+mod files;
+mod normalize;
+mod passphrase;
+mod photo_listing;
 mod photos;
+mod service_account;
+mod staging;
+mod store;
+mod timeline;
 mod token;
+mod uploads;
 
+pub use actor_cache::*;
+pub use archive::*;
+pub use attempts::*;
 pub use auth::*;
 pub use captcha::*;
-pub use photos::*;
+pub use exif::*;
+pub use files::*;
+pub use normalize::*;
+pub use passphrase::*;
+pub use photo_listing::*;
+pub use service_account::*;
+pub use staging::*;
+pub use store::*;
+pub use timeline::*;
 pub use token::*;
+pub use uploads::*;
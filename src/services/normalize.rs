@@ -0,0 +1,327 @@
+use std::io::Cursor;
+
+use axum::body::{Body, Bytes};
+use exif::experimental::Writer;
+use exif::{Context, Field, In, Reader, Tag};
+use futures_util::{stream, StreamExt};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, Limits};
+
+use crate::{Error, Result};
+
+use super::MultipartEnvelope;
+
+/// Quality used when rotated pixels are encoded again
+const JPEG_QUALITY: u8 = 90;
+
+/// Uploads larger than this are sent on as they are, the whole photo is held
+/// in memory while it is normalized
+pub const NORMALIZE_MAX_SIZE: usize = 50 * 1024 * 1024;
+
+/// Photos wider or taller than this are not rotated
+const MAX_DECODE_DIMENSION: u32 = 16_384;
+
+/// Memory the decoder may take for the pixels, enough for about 85 megapixels
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Reads the `file` field of a multipart upload and builds a new body holding
+/// the normalized photo, as files-rs expects
+pub async fn normalize_upload(
+    content_type: &str,
+    content_length: Option<u64>,
+    body: Body,
+) -> Result<(String, Option<u64>, Body)> {
+    if content_length.is_some_and(|length| length > NORMALIZE_MAX_SIZE as u64) {
+        return Ok((content_type.to_string(), content_length, body));
+    }
+
+    let Ok(boundary) = multer::parse_boundary(content_type) else {
+        return Err(Error::BadRequest(
+            "Invalid Content-Type header.".to_string(),
+        ));
+    };
+
+    let mut multipart = multer::Multipart::new(body.into_data_stream(), boundary);
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => return Err(Error::BadRequest("No file uploaded.".to_string())),
+            Err(_) => return Err(Error::BadRequest("Invalid upload.".to_string())),
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let filename = field.file_name().unwrap_or("upload").to_string();
+        let file_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or("application/octet-stream".to_string());
+
+        let envelope = MultipartEnvelope::new(&filename, &file_type);
+        let content_type = envelope.content_type.clone();
+
+        let mut data: Vec<u8> = Vec::new();
+        while data.len() <= NORMALIZE_MAX_SIZE {
+            match field.chunk().await {
+                Ok(Some(chunk)) => data.extend_from_slice(&chunk),
+                Ok(None) => {
+                    let data = normalize_in_background(data).await?;
+                    let length = envelope.framing_len() + data.len() as u64;
+                    let file =
+                        stream::once(async move { Ok::<Bytes, std::io::Error>(Bytes::from(data)) });
+                    return Ok((content_type, Some(length), envelope.wrap(file)));
+                }
+                Err(_) => return Err(Error::BadRequest("Invalid upload.".to_string())),
+            }
+        }
+
+        // Too large to hold on to, what was read goes first and the rest follows
+        let head = stream::once(async move { Ok::<Bytes, multer::Error>(Bytes::from(data)) });
+        return Ok((content_type, None, envelope.wrap(head.chain(field))));
+    }
+}
+
+/// Decoding and encoding pixels would hold up other requests
+async fn normalize_in_background(data: Vec<u8>) -> Result<Vec<u8>> {
+    match tokio::task::spawn_blocking(move || normalize_photo(&data).unwrap_or(data)).await {
+        Ok(data) => Ok(data),
+        Err(_) => Err("Unable to process photo.".into()),
+    }
+}
+
+/// Applies the EXIF orientation to the pixels of a JPEG and drops location,
+/// serial numbers and other tags that identify the owner. Returns nothing
+/// when there is nothing to change.
+pub fn normalize_photo(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()?;
+
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .unwrap_or(Orientation::NoTransforms);
+
+    // Keep the original pixels and orientation when the photo cannot be decoded
+    let rotated = match orientation {
+        Orientation::NoTransforms => None,
+        _ => rotate_jpeg(data, orientation),
+    };
+
+    let fields: Vec<&Field> = exif
+        .fields()
+        .filter(|field| field.ifd_num == In::PRIMARY && !is_private(field.tag))
+        .filter(|field| rotated.is_none() || field.tag != Tag::Orientation)
+        .collect();
+    let segment = exif_segment(&fields, exif.little_endian());
+
+    Some(replace_metadata(
+        rotated.as_deref().unwrap_or(data),
+        segment.as_deref(),
+    ))
+}
+
+/// Tags that tell where a photo was taken or who took it
+fn is_private(tag: Tag) -> bool {
+    tag.context() == Context::Gps
+        || matches!(
+            tag,
+            Tag::MakerNote
+                | Tag::ImageUniqueID
+                | Tag::CameraOwnerName
+                | Tag::BodySerialNumber
+                | Tag::LensSerialNumber
+        )
+}
+
+/// Photos declaring dimensions beyond the decoder limits are left as they are
+fn rotate_jpeg(data: &[u8], orientation: Orientation) -> Option<Vec<u8>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), ImageFormat::Jpeg);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().ok()?;
+    let icc_profile = decoder.icc_profile().ok().flatten();
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+
+    // JPEG has no alpha or 16 bit channels
+    let image = if image.color().has_color() {
+        DynamicImage::ImageRgb8(image.into_rgb8())
+    } else {
+        DynamicImage::ImageLuma8(image.into_luma8())
+    };
+
+    let mut output: Vec<u8> = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut output, JPEG_QUALITY);
+    if let Some(icc_profile) = icc_profile {
+        let _ = encoder.set_icc_profile(icc_profile);
+    }
+    encoder
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            image.color().into(),
+        )
+        .ok()?;
+    Some(output)
+}
+
+/// APP1 segment holding the fields, nothing when no field is left
+fn exif_segment(fields: &[&Field], little_endian: bool) -> Option<Vec<u8>> {
+    if fields.is_empty() {
+        return None;
+    }
+
+    let mut writer = Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, little_endian).ok()?;
+    let tiff = tiff.into_inner();
+
+    // Segment length counts itself and the `Exif` header
+    let length = u16::try_from(tiff.len() + 8).ok()?;
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend(length.to_be_bytes());
+    segment.extend(b"Exif\0\0");
+    segment.extend(tiff);
+    Some(segment)
+}
+
+/// Drops the EXIF, XMP and IPTC segments of a JPEG and puts the new EXIF
+/// segment after the JFIF header, if any
+fn replace_metadata(jpeg: &[u8], exif: Option<&[u8]>) -> Vec<u8> {
+    let mut output: Vec<u8> = jpeg[..2].to_vec();
+    let mut exif = exif;
+    let mut pos = 2;
+
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xFF {
+        let marker = jpeg[pos + 1];
+        // Image data follows the scan header, copy everything from there
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(jpeg.len());
+
+        if marker != 0xE0 {
+            if let Some(segment) = exif.take() {
+                output.extend_from_slice(segment);
+            }
+        }
+        if marker != 0xE1 && marker != 0xED {
+            output.extend_from_slice(&jpeg[pos..end]);
+        }
+        pos = end;
+    }
+
+    if let Some(segment) = exif {
+        output.extend_from_slice(segment);
+    }
+    output.extend_from_slice(&jpeg[pos.min(jpeg.len())..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::read_exif;
+    use crate::tests::{exif_jpeg, oriented_jpeg};
+    use exif::Value;
+
+    fn orientation(data: &[u8]) -> Option<u32> {
+        let exif = Reader::new()
+            .read_from_container(&mut Cursor::new(data))
+            .ok()?;
+        let field = exif.get_field(Tag::Orientation, In::PRIMARY)?;
+        field.value.get_uint(0)
+    }
+
+    fn serial_number(data: &[u8]) -> Option<String> {
+        let exif = Reader::new()
+            .read_from_container(&mut Cursor::new(data))
+            .ok()?;
+        match &exif.get_field(Tag::BodySerialNumber, In::PRIMARY)?.value {
+            Value::Ascii(items) => Some(String::from_utf8_lossy(items.first()?).to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_strip_private_tags() {
+        let original = exif_jpeg();
+        assert!(serial_number(&original).is_some());
+
+        let data = normalize_photo(&original).unwrap();
+        let exif = read_exif(&data).unwrap();
+        assert!(exif.location().is_none());
+        assert!(serial_number(&data).is_none());
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS R6"));
+        assert_eq!(exif.taken_at, Some(1718454600));
+        assert_eq!(exif.lens.as_deref(), Some("RF24-105mm F4 L IS USM"));
+    }
+
+    #[test]
+    fn test_rotate_pixels() {
+        // Taken sideways, shown upright once rotated a quarter turn clockwise
+        let original = oriented_jpeg(16, 8, 6);
+        let data = normalize_photo(&original).unwrap();
+
+        let image = image::load_from_memory(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 16));
+        // The red left half ends up on top
+        let top = image.to_rgb8().get_pixel(4, 2).0;
+        assert!(top[0] > 200 && top[2] < 50);
+        assert!(orientation(&data).is_none());
+        assert!(serial_number(&data).is_none());
+
+        let exif = read_exif(&data).unwrap();
+        assert!(exif.location().is_none());
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS R6"));
+    }
+
+    #[test]
+    fn test_keep_upright_pixels() {
+        let original = oriented_jpeg(16, 8, 1);
+        let data = normalize_photo(&original).unwrap();
+
+        let image = image::load_from_memory(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+        assert_eq!(orientation(&data), Some(1));
+        // Only the metadata changed
+        assert_eq!(data[data.len() - 100..], original[original.len() - 100..]);
+    }
+
+    #[test]
+    fn test_skip_rotating_huge_photos() {
+        // Claims to be 20000 pixels wide in its frame header
+        let mut original = oriented_jpeg(16, 8, 6);
+        let sof = original
+            .windows(2)
+            .position(|marker| marker == [0xFF, 0xC0])
+            .unwrap();
+        original[sof + 7..sof + 9].copy_from_slice(&20000u16.to_be_bytes());
+
+        let data = normalize_photo(&original).unwrap();
+        assert_eq!(orientation(&data), Some(6));
+        assert!(serial_number(&data).is_none());
+    }
+
+    #[test]
+    fn test_nothing_to_normalize() {
+        assert!(normalize_photo(b"not an image").is_none());
+        assert!(normalize_photo(&[0xFF, 0xD8, 0xFF, 0xD9]).is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use tracing::error;
+
+use crate::Result;
+
+pub fn hash_passphrase(passphrase: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    match Argon2::default().hash_password(passphrase.as_bytes(), &salt) {
+        Ok(hash) => Ok(hash.to_string()),
+        Err(e) => {
+            error!("Error: {}", e);
+            Err("Unable to save the passphrase.".into())
+        }
+    }
+}
+
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return false;
+    };
+    Argon2::default()
+        .verify_password(passphrase.as_bytes(), &parsed)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase() {
+        let hash = hash_passphrase("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_passphrase("correct horse", &hash));
+        assert!(!verify_passphrase("wrong horse", &hash));
+        assert!(!verify_passphrase("correct horse", "not a hash"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::{Album, Photo};
+
+/// How long a full album listing is reused for sorting, filtering and paging
+pub const PHOTO_LISTING_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Maximum number of cached album listings
+pub const PHOTO_LISTING_CACHE_MAX_ENTRIES: usize = 20;
+
+struct CachedListing {
+    photos: Vec<Photo>,
+    file_count: i64,
+    expires_at: Instant,
+}
+
+/// Every photo of an album as files-rs lists them, keyed by album. files-rs
+/// cannot sort or filter files, so custom grids and photo navigation work
+/// from a full listing that is loaded once and shared by later requests.
+pub struct PhotoListingCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, CachedListing>>,
+}
+
+impl PhotoListingCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Photos are added or removed once the album count no longer matches
+    pub fn get(&self, album: &Album) -> Option<Vec<Photo>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(&album.id) {
+            Some(entry)
+                if entry.expires_at > Instant::now() && entry.file_count == album.file_count =>
+            {
+                Some(entry.photos.clone())
+            }
+            Some(_) => {
+                entries.remove(&album.id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, album: &Album, photos: Vec<Photo>) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if !entries.contains_key(&album.id) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            // Still full, evict the entry closest to expiring
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(k, _)| k.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            album.id.clone(),
+            CachedListing {
+                photos,
+                file_count: album.file_count,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+
+    /// Drops the listing once photos are added, removed or renamed.
+    /// A delete followed by an upload leaves the photo count as it was.
+    pub fn invalidate(&self, album_id: &str) {
+        self.entries.lock().unwrap().remove(album_id);
+    }
+}
+
+impl Default for PhotoListingCache {
+    fn default() -> Self {
+        Self::new(PHOTO_LISTING_CACHE_TTL, PHOTO_LISTING_CACHE_MAX_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album(id: &str, file_count: i64) -> Album {
+        Album {
+            id: id.to_string(),
+            bucket_id: "bucket-1".to_string(),
+            name: id.to_string(),
+            label: id.to_string(),
+            file_count,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_keyed_by_album_and_count() {
+        let cache = PhotoListingCache::default();
+        assert!(cache.get(&album("album-1", 0)).is_none());
+
+        cache.insert(&album("album-1", 0), Vec::new());
+        assert!(cache.get(&album("album-1", 0)).is_some());
+        assert!(cache.get(&album("album-2", 0)).is_none());
+
+        // A photo was uploaded since
+        assert!(cache.get(&album("album-1", 1)).is_none());
+        assert!(cache.get(&album("album-1", 0)).is_none());
+    }
+
+    #[test]
+    fn test_expired_and_invalidated() {
+        let cache = PhotoListingCache::new(Duration::ZERO, 10);
+        cache.insert(&album("album-1", 0), Vec::new());
+        assert!(cache.get(&album("album-1", 0)).is_none());
+
+        let cache = PhotoListingCache::default();
+        cache.insert(&album("album-1", 0), Vec::new());
+        cache.invalidate("album-1");
+        assert!(cache.get(&album("album-1", 0)).is_none());
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let cache = PhotoListingCache::new(PHOTO_LISTING_CACHE_TTL, 2);
+        cache.insert(&album("album-1", 0), Vec::new());
+        cache.insert(&album("album-2", 0), Vec::new());
+        cache.insert(&album("album-3", 0), Vec::new());
+
+        let cached = ["album-1", "album-2", "album-3"]
+            .iter()
+            .filter(|id| cache.get(&album(id, 0)).is_some())
+            .count();
+        assert_eq!(cached, 2);
+        assert!(cache.get(&album("album-3", 0)).is_some());
+    }
+}
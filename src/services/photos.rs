@@ -1,39 +1,37 @@
-use axum::body::Bytes;
-use axum::http::HeaderMap;
-use reqwest::{Client, StatusCode};
+use axum::body::Body;
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use sync_wrapper::SyncStream;
 use tracing::error;
 
-use crate::config::Config;
 use crate::error::ErrorResponse;
 use crate::models::{
-    Album, FileObject, ListAlbumsParams, ListPhotosParams, NewAlbum, NewAlbumForm, Paginated,
-    Photo, UpdateAlbum, UpdateAlbumForm,
+    Album, FileObject, ListAlbumsParams, ListPhotosParams, NewAlbum, Paginated, Photo, UpdateAlbum,
+    UpdatePhoto,
 };
 use crate::{Error, Result};
 
-use super::verify_csrf_token;
+use super::{ByteStream, FilesClient, MultipartEnvelope};
 
-pub async fn list_albums(
-    api_url: &str,
+pub(super) async fn list_albums(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     params: &ListAlbumsParams,
 ) -> Result<Paginated<Album>> {
-    let url = format!("{}/v1/buckets/{}/dirs", api_url, bucket_id);
+    let url = format!("{}/v1/buckets/{}/dirs", &api.api_url, bucket_id);
     let mut page = "1".to_string();
-    let mut per_page = "10".to_string();
+    let per_page = params.page_size().to_string();
 
     if let Some(p) = params.page {
         page = p.to_string();
     }
-    if let Some(pp) = params.per_page {
-        per_page = pp.to_string();
-    }
     let mut query: Vec<(&str, &str)> = vec![("page", &page), ("per_page", &per_page)];
     if let Some(keyword) = &params.keyword {
         query.push(("keyword", keyword));
     }
-    let result = Client::new()
+    let result = api
+        .client
         .get(url)
         .bearer_auth(token)
         .query(&query)
@@ -48,14 +46,14 @@ pub async fn list_albums(
         StatusCode::OK => {
             let json_res = response.json::<Paginated<Album>>().await;
             match json_res {
-                Ok(albums) => return Ok(albums),
+                Ok(albums) => Ok(albums),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::JsonParseError("Unable to parse albums.".to_string()));
+                    Err(Error::JsonParseError("Unable to parse albums.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permissions to view albums".to_string(),
         )),
@@ -66,26 +64,18 @@ pub async fn list_albums(
     }
 }
 
-pub async fn create_album(
-    config: &Config,
+pub(super) async fn create_album(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
-    form: NewAlbumForm,
+    data: &NewAlbum,
 ) -> Result<Album> {
-    let csrf_result = verify_csrf_token(&form.token, &config.jwt_secret)?;
-    if csrf_result != "new_album" {
-        return Err(Error::InvalidCsrfToken);
-    }
-    let url = format!("{}/v1/buckets/{}/dirs", &config.api_url, bucket_id);
-
-    let data = NewAlbum {
-        name: form.name,
-        label: form.label,
-    };
-    let result = Client::new()
+    let url = format!("{}/v1/buckets/{}/dirs", &api.api_url, bucket_id);
+    let result = api
+        .client
         .post(url)
         .bearer_auth(token)
-        .json(&data)
+        .json(data)
         .send()
         .await;
 
@@ -97,29 +87,27 @@ pub async fn create_album(
         StatusCode::CREATED => {
             let json_res = response.json::<Album>().await;
             match json_res {
-                Ok(album) => return Ok(album),
+                Ok(album) => Ok(album),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::JsonParseError(
+                    Err(Error::JsonParseError(
                         "Unable to parse album information.".to_string(),
-                    ));
+                    ))
                 }
             }
         }
         StatusCode::BAD_REQUEST => {
             let json_res = response.json::<ErrorResponse>().await;
             match json_res {
-                Ok(json) => {
-                    return Err(Error::ValidationError(json.message));
-                }
+                Ok(json) => Err(Error::ValidationError(json.message)),
                 Err(e) => {
                     // Most likely a bad request not handled by the API
                     error!("Error: {}", e);
-                    return Err(Error::BadRequest("Bad Request.".to_string()));
+                    Err(Error::BadRequest("Bad Request.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to create new albums.".to_string(),
         )),
@@ -129,14 +117,17 @@ pub async fn create_album(
     }
 }
 
-pub async fn get_album(
-    api_url: &str,
+pub(super) async fn get_album(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
 ) -> Result<Album> {
-    let url = format!("{}/v1/buckets/{}/dirs/{}", api_url, bucket_id, album_id);
-    let result = Client::new().get(url).bearer_auth(token).send().await;
+    let url = format!(
+        "{}/v1/buckets/{}/dirs/{}",
+        &api.api_url, bucket_id, album_id
+    );
+    let result = api.client.get(url).bearer_auth(token).send().await;
 
     let Ok(response) = result else {
         return Err("Unable to get album. Try again later.".into());
@@ -146,14 +137,14 @@ pub async fn get_album(
         StatusCode::OK => {
             let json_res = response.json::<Album>().await;
             match json_res {
-                Ok(album) => return Ok(album),
+                Ok(album) => Ok(album),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::JsonParseError("Unable to parse album.".to_string()));
+                    Err(Error::JsonParseError("Unable to parse album.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to read this album.".to_string(),
         )),
@@ -164,28 +155,22 @@ pub async fn get_album(
     }
 }
 
-pub async fn update_album(
-    config: &Config,
+pub(super) async fn update_album(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
-    form: &UpdateAlbumForm,
+    data: &UpdateAlbum,
 ) -> Result<Album> {
-    let csrf_result = verify_csrf_token(&form.token, &config.jwt_secret)?;
-    if csrf_result != album_id {
-        return Err(Error::InvalidCsrfToken);
-    }
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}",
-        &config.api_url, bucket_id, album_id
+        &api.api_url, bucket_id, album_id
     );
-    let data = UpdateAlbum {
-        label: form.label.clone(),
-    };
-    let result = Client::new()
+    let result = api
+        .client
         .patch(url)
         .bearer_auth(token)
-        .json(&data)
+        .json(data)
         .send()
         .await;
 
@@ -197,28 +182,26 @@ pub async fn update_album(
         StatusCode::OK => {
             let json_res = response.json::<Album>().await;
             match json_res {
-                Ok(album) => return Ok(album),
+                Ok(album) => Ok(album),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::JsonParseError(
+                    Err(Error::JsonParseError(
                         "Unable to parse album information.".to_string(),
-                    ));
+                    ))
                 }
             }
         }
         StatusCode::BAD_REQUEST => {
             let json_res = response.json::<ErrorResponse>().await;
             match json_res {
-                Ok(json) => {
-                    return Err(Error::ValidationError(json.message));
-                }
+                Ok(json) => Err(Error::ValidationError(json.message)),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::BadRequest("Bad Request.".to_string()));
+                    Err(Error::BadRequest("Bad Request.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::ServiceError(
             "You have no permission to update this album.".to_string(),
         )),
@@ -228,22 +211,17 @@ pub async fn update_album(
     }
 }
 
-pub async fn delete_album(
-    config: &Config,
+pub(super) async fn delete_album(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
-    csrf_token: &str,
 ) -> Result<()> {
-    let csrf_result = verify_csrf_token(&csrf_token, &config.jwt_secret)?;
-    if csrf_result != album_id {
-        return Err(Error::InvalidCsrfToken);
-    }
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}",
-        &config.api_url, bucket_id, album_id
+        &api.api_url, bucket_id, album_id
     );
-    let result = Client::new().delete(url).bearer_auth(token).send().await;
+    let result = api.client.delete(url).bearer_auth(token).send().await;
 
     let Ok(response) = result else {
         return Err("Unable to delete album. Try again later.".into());
@@ -254,16 +232,14 @@ pub async fn delete_album(
         StatusCode::BAD_REQUEST => {
             let json_res = response.json::<ErrorResponse>().await;
             match json_res {
-                Ok(json) => {
-                    return Err(Error::ValidationError(json.message));
-                }
+                Ok(json) => Err(Error::ValidationError(json.message)),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::BadRequest("Bad Request.".to_string()));
+                    Err(Error::BadRequest("Bad Request.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to delete this album.".to_string(),
         )),
@@ -273,8 +249,8 @@ pub async fn delete_album(
     }
 }
 
-pub async fn list_photos(
-    api_url: &str,
+pub(super) async fn list_photos(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
@@ -282,16 +258,17 @@ pub async fn list_photos(
 ) -> Result<Paginated<Photo>> {
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}/files",
-        api_url, bucket_id, album_id
+        &api.api_url, bucket_id, album_id
     );
     let mut page = "1".to_string();
-    let per_page = "50".to_string();
+    let per_page = params.page_size().to_string();
 
     if let Some(p) = params.page {
         page = p.to_string();
     }
     let query: Vec<(&str, &str)> = vec![("page", &page), ("per_page", &per_page)];
-    let result = Client::new()
+    let result = api
+        .client
         .get(url)
         .bearer_auth(token)
         .query(&query)
@@ -307,11 +284,8 @@ pub async fn list_photos(
             let json_res = response.json::<Paginated<FileObject>>().await;
             match json_res {
                 Ok(listing) => {
-                    let items: Vec<Photo> = listing
-                        .data
-                        .into_iter()
-                        .filter_map(|file| file.try_into().ok())
-                        .collect();
+                    // Every file gets a tile so the counts match files-rs
+                    let items: Vec<Photo> = listing.data.into_iter().map(Photo::from).collect();
                     Ok(Paginated {
                         meta: listing.meta,
                         data: items,
@@ -323,7 +297,7 @@ pub async fn list_photos(
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permissions to view photos".to_string(),
         )),
@@ -334,38 +308,34 @@ pub async fn list_photos(
     }
 }
 
-pub async fn upload_photo(
-    config: &Config,
+pub(super) async fn upload_photo(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
-    headers: &HeaderMap,
-    csrf_token: Option<String>,
-    body: Bytes,
+    content_type: &str,
+    content_length: Option<u64>,
+    body: Body,
 ) -> Result<Photo> {
-    // We need the content type header
-    let Some(content_type) = headers.get("Content-Type") else {
-        return Err("Content-Type header is required.".into());
-    };
-    let Ok(content_type) = content_type.to_str() else {
-        return Err("Invalid Content-Type header.".into());
-    };
-    let csrf_token = csrf_token.unwrap_or("".to_string());
-    let csrf_result = verify_csrf_token(&csrf_token, &config.jwt_secret)?;
-    if csrf_result != album_id {
-        return Err(Error::InvalidCsrfToken);
-    }
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}/files",
-        &config.api_url, bucket_id, album_id
+        &api.api_url, bucket_id, album_id
     );
 
-    let result = Client::new()
+    let mut request = api
+        .client
         .post(url)
         .header("Content-Type", content_type)
-        .header("Content-Length", body.len().to_string())
-        .bearer_auth(token)
-        .body(body)
+        .bearer_auth(token);
+
+    if let Some(length) = content_length {
+        request = request.header("Content-Length", length.to_string());
+    }
+
+    // Stream the body through as it arrives instead of buffering it
+    let stream = SyncStream::new(body.into_data_stream());
+    let result = request
+        .body(reqwest::Body::wrap_stream(stream))
         .send()
         .await;
 
@@ -377,12 +347,12 @@ pub async fn upload_photo(
         StatusCode::CREATED => {
             let json_res = response.json::<FileObject>().await;
             match json_res {
-                Ok(file) => Ok(Photo::try_from(file)?),
+                Ok(file) => Ok(Photo::from(file)),
                 Err(e) => {
                     error!("Error: {}", e);
-                    return Err(Error::JsonParseError(
+                    Err(Error::JsonParseError(
                         "Unable to parse photo information.".to_string(),
-                    ));
+                    ))
                 }
             }
         }
@@ -396,7 +366,7 @@ pub async fn upload_photo(
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to upload photos.".to_string(),
         )),
@@ -406,8 +376,8 @@ pub async fn upload_photo(
     }
 }
 
-pub async fn get_photo(
-    api_url: &str,
+pub(super) async fn get_photo(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
@@ -415,9 +385,9 @@ pub async fn get_photo(
 ) -> Result<Photo> {
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}/files/{}",
-        api_url, bucket_id, album_id, photo_id
+        &api.api_url, bucket_id, album_id, photo_id
     );
-    let result = Client::new().get(url).bearer_auth(token).send().await;
+    let result = api.client.get(url).bearer_auth(token).send().await;
 
     let Ok(response) = result else {
         return Err("Unable to get photo. Try again later.".into());
@@ -427,14 +397,14 @@ pub async fn get_photo(
         StatusCode::OK => {
             let json_res = response.json::<FileObject>().await;
             match json_res {
-                Ok(file) => Ok(Photo::try_from(file)?),
+                Ok(file) => Ok(Photo::from(file)),
                 Err(e) => {
                     error!("Error: {}", e);
                     Err(Error::JsonParseError("Unable to parse photo.".to_string()))
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to read this photo.".to_string(),
         )),
@@ -445,23 +415,134 @@ pub async fn get_photo(
     }
 }
 
-pub async fn delete_photo(
-    config: &Config,
+pub(super) async fn update_photo(
+    api: &FilesClient,
     token: &str,
     bucket_id: &str,
     album_id: &str,
     photo_id: &str,
-    csrf_token: &str,
-) -> Result<()> {
-    let csrf_result = verify_csrf_token(&csrf_token, &config.jwt_secret)?;
-    if csrf_result != photo_id {
-        return Err(Error::InvalidCsrfToken);
+    data: &UpdatePhoto,
+) -> Result<Photo> {
+    let url = format!(
+        "{}/v1/buckets/{}/dirs/{}/files/{}",
+        &api.api_url, bucket_id, album_id, photo_id
+    );
+    let result = api
+        .client
+        .patch(url)
+        .bearer_auth(token)
+        .json(data)
+        .send()
+        .await;
+
+    let Ok(response) = result else {
+        return Err("Unable to update photo. Try again later.".into());
+    };
+
+    match response.status() {
+        StatusCode::OK => {
+            let json_res = response.json::<FileObject>().await;
+            match json_res {
+                Ok(file) => Ok(Photo::from(file)),
+                Err(e) => {
+                    error!("Error: {}", e);
+                    Err(Error::JsonParseError(
+                        "Unable to parse photo information.".to_string(),
+                    ))
+                }
+            }
+        }
+        StatusCode::BAD_REQUEST => {
+            let json_res = response.json::<ErrorResponse>().await;
+            match json_res {
+                Ok(json) => Err(Error::ValidationError(json.message)),
+                Err(e) => {
+                    error!("Error: {}", e);
+                    Err(Error::BadRequest("Bad Request.".to_string()))
+                }
+            }
+        }
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
+        StatusCode::FORBIDDEN => Err(Error::Forbidden(
+            "You have no permission to edit photos.".to_string(),
+        )),
+        StatusCode::NOT_FOUND => Err(Error::PhotoNotFound),
+        _ => Err(Error::ServiceError(
+            "Unable to update photo. Try again later.".to_string(),
+        )),
+    }
+}
+
+/// Uploads a copy of the original photo into another album
+pub(super) async fn copy_photo(
+    api: &FilesClient,
+    token: &str,
+    photo: &Photo,
+    bucket_id: &str,
+    album_id: &str,
+) -> Result<Photo> {
+    let result = api.client.get(&photo.orig.url).send().await;
+    let Ok(response) = result else {
+        return Err("Unable to download photo. Try again later.".into());
+    };
+    if !response.status().is_success() {
+        return Err(Error::ServiceError(
+            "Unable to download photo. Try again later.".to_string(),
+        ));
     }
+
+    let envelope = MultipartEnvelope::new(&photo.filename, &photo.content_type);
+    let content_length = response
+        .content_length()
+        .map(|length| length + envelope.framing_len());
+    let content_type = envelope.content_type.clone();
+    let body = envelope.wrap(response.bytes_stream());
+
+    upload_photo(
+        api,
+        token,
+        bucket_id,
+        album_id,
+        &content_type,
+        content_length,
+        body,
+    )
+    .await
+}
+
+/// Photo urls are pre-signed, no need for the token
+pub(super) async fn download_photo(api: &FilesClient, url: &str) -> Result<ByteStream> {
+    let result = api.client.get(url).send().await;
+    let Ok(response) = result else {
+        return Err("Unable to download photo. Try again later.".into());
+    };
+    if !response.status().is_success() {
+        return Err(Error::ServiceError(
+            "Unable to download photo. Try again later.".to_string(),
+        ));
+    }
+
+    let stream = response.bytes_stream().map(|chunk| {
+        chunk.map_err(|e| {
+            error!("Error: {}", e);
+            Error::ServiceError("Unable to download photo. Try again later.".to_string())
+        })
+    });
+    Ok(Box::pin(stream))
+}
+
+pub(super) async fn delete_photo(
+    api: &FilesClient,
+    token: &str,
+    bucket_id: &str,
+    album_id: &str,
+    photo_id: &str,
+) -> Result<()> {
     let url = format!(
         "{}/v1/buckets/{}/dirs/{}/files/{}",
-        &config.api_url, bucket_id, album_id, photo_id
+        &api.api_url, bucket_id, album_id, photo_id
     );
-    let result = Client::new().delete(url).bearer_auth(token).send().await;
+    let result = api.client.delete(url).bearer_auth(token).send().await;
 
     let Ok(response) = result else {
         return Err("Unable to delete photo. Try again later.".into());
@@ -479,7 +560,7 @@ pub async fn delete_photo(
                 }
             }
         }
-        StatusCode::UNAUTHORIZED => Err(Error::LoginRequired("Login first".to_string())),
+        StatusCode::UNAUTHORIZED => Err(api.login_required(token)),
         StatusCode::FORBIDDEN => Err(Error::Forbidden(
             "You have no permission to delete this photo.".to_string(),
         )),
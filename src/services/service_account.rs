@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+
+use tracing::error;
+
+use crate::{models::Album, Error, Result};
+
+use super::{AuthPayload, FilesApi};
+
+/// files-rs account configured on the memo-rs side, used on behalf of
+/// visitors without a login. Its token is kept until files-rs rejects it.
+pub struct ServiceAccount {
+    credentials: Option<(String, String)>,
+    token: Mutex<Option<String>>,
+}
+
+impl ServiceAccount {
+    pub fn new(username: Option<String>, password: Option<String>) -> Self {
+        let credentials = match (username, password) {
+            (Some(username), Some(password)) => Some((username, password)),
+            _ => None,
+        };
+
+        Self {
+            credentials,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Features backed by the account need it to be configured
+    pub fn is_enabled(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    pub async fn token(&self, files: &dyn FilesApi) -> Result<String> {
+        if let Some(token) = self.token.lock().unwrap().clone() {
+            return Ok(token);
+        }
+
+        let Some((username, password)) = self.credentials.clone() else {
+            return Err(Error::ServiceError(
+                "This service account is not set up.".to_string(),
+            ));
+        };
+
+        let result = files.authenticate(AuthPayload { username, password }).await;
+        let auth = match result {
+            Ok(auth) => auth,
+            Err(err) => {
+                // Visitors only need to know that it did not work
+                error!("Service account login failed: {}", err);
+                return Err(Error::ServiceError(
+                    "This page is unavailable right now. Try again later.".to_string(),
+                ));
+            }
+        };
+        *self.token.lock().unwrap() = Some(auth.token.clone());
+        Ok(auth.token)
+    }
+
+    /// Loads the album along with the token used, logging in again once
+    /// when the current token has expired
+    pub async fn get_album(
+        &self,
+        files: &dyn FilesApi,
+        bucket_id: &str,
+        album_id: &str,
+    ) -> Result<(String, Album)> {
+        let token = self.token(files).await?;
+        match files.get_album(&token, bucket_id, album_id).await {
+            Err(Error::LoginRequired(_)) => {
+                self.invalidate(&token);
+                let token = self.token(files).await?;
+                let album = files.get_album(&token, bucket_id, album_id).await?;
+                Ok((token, album))
+            }
+            result => Ok((token, result?)),
+        }
+    }
+
+    /// Forgets the token so the next request logs in again
+    pub fn invalidate(&self, token: &str) {
+        let mut current = self.token.lock().unwrap();
+        if current.as_deref() == Some(token) {
+            *current = None;
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use axum::body::Body;
+use chrono::Utc;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::models::{NewStagedUpload, StagedUpload};
+use crate::{Error, Result};
+
+use super::{sanitize_filename, JsonStore, MultipartEnvelope};
+
+/// Staged uploads nobody reviewed are discarded after this many days
+pub const STAGED_UPLOAD_EXPIRY_DAYS: i64 = 30;
+
+/// Uploads an album can have waiting for review
+pub const MAX_STAGED_UPLOADS_PER_ALBUM: usize = 500;
+
+/// Bytes an album can have waiting for review, 5 GB
+pub const MAX_STAGED_BYTES_PER_ALBUM: u64 = 5_000_000_000;
+
+/// Holds uploads to moderated albums on disk until they are reviewed
+pub struct StagingStore {
+    dir: PathBuf,
+    entries: JsonStore<StagedUpload>,
+    /// Uploads still being written, by album
+    pending: Mutex<HashMap<String, PendingUploads>>,
+}
+
+#[derive(Default)]
+struct PendingUploads {
+    count: usize,
+    bytes: u64,
+}
+
+/// Keeps a place within the album limits while an upload is written,
+/// given back when dropped, even when the client goes away mid upload
+struct Reservation<'a> {
+    store: &'a StagingStore,
+    album_id: String,
+    bytes: u64,
+}
+
+impl Reservation<'_> {
+    /// Claims more bytes for the upload, fails when the album would go over its limit
+    fn grow(&mut self, bytes: u64) -> Result<()> {
+        let mut pending = self.store.pending.lock().unwrap();
+        let (_, staged) = self.store.entries.album_usage(&self.album_id);
+        let album = pending.entry(self.album_id.clone()).or_default();
+        if staged + album.bytes + bytes > MAX_STAGED_BYTES_PER_ALBUM {
+            return Err(album_full());
+        }
+        album.bytes += bytes;
+        self.bytes += bytes;
+        Ok(())
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut pending = self.store.pending.lock().unwrap();
+        if let Some(album) = pending.get_mut(&self.album_id) {
+            album.count -= 1;
+            album.bytes -= self.bytes;
+            if album.count == 0 {
+                pending.remove(&self.album_id);
+            }
+        }
+    }
+}
+
+impl StagingStore {
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        let entries = JsonStore::open(dir.join("staged.json"))?;
+        Ok(Self {
+            dir,
+            entries,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Keeps the `file` field of a multipart upload, the same body files-rs accepts
+    pub async fn stage(
+        &self,
+        data: NewStagedUpload,
+        content_type: &str,
+        body: Body,
+    ) -> Result<StagedUpload> {
+        let Ok(boundary) = multer::parse_boundary(content_type) else {
+            return Err(Error::BadRequest(
+                "Invalid Content-Type header.".to_string(),
+            ));
+        };
+
+        let mut multipart = multer::Multipart::new(body.into_data_stream(), boundary);
+        loop {
+            let field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => return Err(Error::BadRequest("No file uploaded.".to_string())),
+                Err(_) => return Err(Error::BadRequest("Invalid upload.".to_string())),
+            };
+            if field.name() != Some("file") {
+                continue;
+            }
+
+            let mut reservation = self.reserve(&data.album_id)?;
+            let staged = StagedUpload {
+                id: Uuid::new_v4().to_string(),
+                bucket_id: data.bucket_id,
+                album_id: data.album_id,
+                filename: sanitize_filename(field.file_name().unwrap_or("upload")),
+                content_type: field
+                    .content_type()
+                    .map(|mime| mime.to_string())
+                    .unwrap_or("application/octet-stream".to_string()),
+                size: 0,
+                uploaded_by: data.uploaded_by,
+                guest: data.guest,
+                created_at: Utc::now().timestamp(),
+            };
+
+            return self.save_field(staged, field, &mut reservation).await;
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<StagedUpload> {
+        self.entries.get(id)
+    }
+
+    /// Staged uploads of the album, oldest first
+    pub fn album_uploads(&self, album_id: &str) -> Vec<StagedUpload> {
+        self.entries.album_uploads(album_id)
+    }
+
+    /// Streams the staged file for reviewers
+    pub async fn file_body(&self, staged: &StagedUpload) -> Result<Body> {
+        let Ok(file) = File::open(self.data_path(&staged.id)).await else {
+            return Err("Unable to open staged upload.".into());
+        };
+        Ok(Body::from_stream(ReaderStream::new(file)))
+    }
+
+    /// Builds a multipart body holding the staged file, as files-rs expects
+    pub async fn multipart_body(&self, staged: &StagedUpload) -> Result<(String, u64, Body)> {
+        let Ok(file) = File::open(self.data_path(&staged.id)).await else {
+            return Err("Unable to open staged upload.".into());
+        };
+
+        let envelope = MultipartEnvelope::new(&staged.filename, &staged.content_type);
+        let length = envelope.framing_len() + staged.size;
+        let content_type = envelope.content_type.clone();
+
+        Ok((content_type, length, envelope.wrap(ReaderStream::new(file))))
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        self.entries.remove(id).await?;
+        // The file may already be gone, nothing to do then
+        let _ = fs::remove_file(self.data_path(id)).await;
+        Ok(())
+    }
+
+    /// Removes everything waiting for a deleted album
+    pub async fn remove_album(&self, album_id: &str) -> Result<()> {
+        for staged in self.entries.album_uploads(album_id) {
+            self.remove(&staged.id).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes uploads that were not reviewed in time
+    pub async fn remove_expired(&self) -> Result<usize> {
+        let before = Utc::now().timestamp() - STAGED_UPLOAD_EXPIRY_DAYS * 24 * 3600;
+        let expired = self.entries.created_before(before);
+        for id in expired.iter() {
+            self.remove(id).await?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Counts the upload against the album limits before anything is written.
+    /// Uploads still being written count too, concurrent ones cannot overshoot.
+    fn reserve(&self, album_id: &str) -> Result<Reservation<'_>> {
+        let mut pending = self.pending.lock().unwrap();
+        let (count, bytes) = self.entries.album_usage(album_id);
+        let album = pending.entry(album_id.to_string()).or_default();
+        if count + album.count >= MAX_STAGED_UPLOADS_PER_ALBUM
+            || bytes + album.bytes >= MAX_STAGED_BYTES_PER_ALBUM
+        {
+            if album.count == 0 {
+                pending.remove(album_id);
+            }
+            return Err(album_full());
+        }
+        album.count += 1;
+
+        Ok(Reservation {
+            store: self,
+            album_id: album_id.to_string(),
+            bytes: 0,
+        })
+    }
+
+    /// Stops writing once the album goes over its limit
+    async fn save_field(
+        &self,
+        mut staged: StagedUpload,
+        mut field: multer::Field<'_>,
+        reservation: &mut Reservation<'_>,
+    ) -> Result<StagedUpload> {
+        let path = self.data_path(&staged.id);
+        let Ok(mut file) = File::create(&path).await else {
+            return Err("Unable to create staged upload.".into());
+        };
+
+        let mut result = Ok(());
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    if let Err(err) = reservation.grow(chunk.len() as u64) {
+                        result = Err(err);
+                        break;
+                    }
+                    if let Err(e) = file.write_all(&chunk).await {
+                        error!("Error: {}", e);
+                        result = Err("Unable to write staged upload.".into());
+                        break;
+                    }
+                    staged.size += chunk.len() as u64;
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    result = Err(Error::BadRequest("Upload interrupted.".to_string()));
+                    break;
+                }
+            }
+        }
+        if result.is_ok() {
+            if let Err(e) = file.flush().await {
+                error!("Error: {}", e);
+                result = Err("Unable to write staged upload.".into());
+            }
+        }
+
+        if result.is_ok() {
+            result = self.entries.set(&staged.id, staged.clone()).await;
+        }
+        if let Err(err) = result {
+            let _ = fs::remove_file(&path).await;
+            return Err(err);
+        }
+
+        Ok(staged)
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", id))
+    }
+}
+
+fn album_full() -> Error {
+    Error::TooManyRequests(
+        "Too many uploads are waiting for review in this album. Try again later.".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::multipart_body;
+
+    fn temp_store() -> StagingStore {
+        let dir = std::env::temp_dir().join(format!("memo-rs-staging-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        StagingStore::open(dir).unwrap()
+    }
+
+    fn new_upload(album_id: &str) -> NewStagedUpload {
+        NewStagedUpload {
+            bucket_id: "bucket-1".to_string(),
+            album_id: album_id.to_string(),
+            uploaded_by: "guest".to_string(),
+            guest: true,
+        }
+    }
+
+    async fn stage(store: &StagingStore, album_id: &str) -> Result<StagedUpload> {
+        let (content_type, body) = multipart_body("beach.jpg", "image/jpeg", b"jpeg data");
+        store
+            .stage(new_upload(album_id), &content_type, Body::from(body))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_remove_expired() {
+        let store = temp_store();
+        let old = stage(&store, "album-1").await.unwrap();
+        let fresh = stage(&store, "album-1").await.unwrap();
+
+        let created_at = Utc::now().timestamp() - (STAGED_UPLOAD_EXPIRY_DAYS * 24 + 1) * 3600;
+        let expired = StagedUpload { created_at, ..old };
+        store
+            .entries
+            .set(&expired.id, expired.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.remove_expired().await.unwrap(), 1);
+        assert!(store.get(&expired.id).is_none());
+        assert!(!store.data_path(&expired.id).exists());
+        assert!(store.get(&fresh.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_album_limit() {
+        let store = temp_store();
+        let staged = stage(&store, "album-1").await.unwrap();
+        let full = StagedUpload {
+            size: MAX_STAGED_BYTES_PER_ALBUM,
+            ..staged
+        };
+        store.entries.set(&full.id.clone(), full).await.unwrap();
+
+        let result = stage(&store, "album-1").await;
+        assert!(matches!(result, Err(Error::TooManyRequests(_))));
+        assert!(stage(&store, "album-2").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_album_limit_counts_pending_uploads() {
+        let store = temp_store();
+        let mut reservation = store.reserve("album-1").unwrap();
+        reservation.grow(MAX_STAGED_BYTES_PER_ALBUM - 4).unwrap();
+
+        // Not enough room left for the file
+        let result = stage(&store, "album-1").await;
+        assert!(matches!(result, Err(Error::TooManyRequests(_))));
+        assert!(stage(&store, "album-2").await.is_ok());
+
+        drop(reservation);
+        assert!(stage(&store, "album-1").await.is_ok());
+        assert!(store.pending.lock().unwrap().is_empty());
+    }
+}
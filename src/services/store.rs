@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::error;
+
+use crate::models::{
+    AlbumMeta, FavoritePhoto, GuestUploadLink, Photo, PhotoMeta, PhotoTags, ShareLink,
+    StagedUpload, TagCount, UserFavorites,
+};
+use crate::{Error, Result};
+
+/// Small JSON file backed map for data files-rs has no place for.
+/// Every write rewrites the whole file, keep the values small.
+/// Reads are served from memory and the file is written off the runtime.
+pub struct JsonStore<V> {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, V>>,
+    /// Bumped with every change, under the entries lock
+    version: AtomicU64,
+    /// Last version written, an older snapshot never overwrites a newer one
+    saved: tokio::sync::Mutex<u64>,
+}
+
+/// Contents of the store at a given version, ready to be written
+struct Snapshot {
+    version: u64,
+    contents: String,
+}
+
+impl<V> JsonStore<V>
+where
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Loads the file if it exists, otherwise starts empty
+    pub fn open(path: PathBuf) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                error!("Error: {}", e);
+                return Err("Unable to create data dir.".into());
+            }
+        }
+
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<HashMap<String, V>>(&contents) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    return Err(Error::AnyError(format!(
+                        "Unable to parse {}.",
+                        path.display()
+                    )));
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+            version: AtomicU64::new(0),
+            saved: tokio::sync::Mutex::new(0),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub async fn set(&self, key: &str, value: V) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.insert(key.to_string(), value);
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<Option<V>> {
+        let (value, snapshot) = {
+            let mut entries = self.entries.lock().unwrap();
+            let value = entries.remove(key);
+            let snapshot = match value {
+                Some(_) => Some(self.snapshot(&entries)?),
+                None => None,
+            };
+            (value, snapshot)
+        };
+        if let Some(snapshot) = snapshot {
+            self.save(snapshot).await?;
+        }
+        Ok(value)
+    }
+
+    /// Removes every entry matching the predicate, like those of a deleted album
+    pub async fn remove_where(&self, predicate: impl Fn(&V) -> bool) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let count = entries.len();
+            entries.retain(|_, value| !predicate(value));
+            if entries.len() == count {
+                return Ok(());
+            }
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+
+    /// Serializes the entries while the lock is still held
+    fn snapshot(&self, entries: &HashMap<String, V>) -> Result<Snapshot> {
+        let Ok(contents) = serde_json::to_string(entries) else {
+            return Err("Unable to serialize data.".into());
+        };
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(Snapshot { version, contents })
+    }
+
+    /// Writes the snapshot unless a newer one is already on disk
+    async fn save(&self, snapshot: Snapshot) -> Result<()> {
+        let mut saved = self.saved.lock().await;
+        if *saved >= snapshot.version {
+            return Ok(());
+        }
+
+        let path = self.path.clone();
+        let contents = snapshot.contents;
+        match tokio::task::spawn_blocking(move || write_file(&path, &contents)).await {
+            Ok(Ok(_)) => {
+                *saved = snapshot.version;
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                error!("Error: {}", e);
+                Err("Unable to save data.".into())
+            }
+            Err(_) => Err("Unable to save data.".into()),
+        }
+    }
+}
+
+/// Writes to a temporary file first so a crash never leaves a partial file
+fn write_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+impl JsonStore<PhotoMeta> {
+    /// Copies the locally kept details onto photos from files-rs
+    pub fn fill(&self, photos: &mut [Photo]) {
+        let entries = self.entries.lock().unwrap();
+        for photo in photos.iter_mut() {
+            if let Some(meta) = entries.get(&photo.id) {
+                photo.caption = meta.caption.clone();
+                photo.uploaded_by = meta.uploaded_by.clone();
+            }
+        }
+    }
+}
+
+impl JsonStore<AlbumMeta> {
+    /// Forgets the cover once its photo is gone, a new one is picked on the next request
+    pub async fn clear_cover(&self, album_id: &str, photo_id: &str) -> Result<()> {
+        let Some(mut meta) = self.get(album_id) else {
+            return Ok(());
+        };
+        if meta.cover_photo_id.as_deref() != Some(photo_id) {
+            return Ok(());
+        }
+
+        meta.cover_photo_id = None;
+        if meta.is_empty() {
+            self.remove(album_id).await?;
+        } else {
+            self.set(album_id, meta).await?;
+        }
+        Ok(())
+    }
+
+    /// Counts a visit to a passphrase protected album.
+    /// Returns false once the album has used up its views.
+    pub async fn add_passphrase_view(&self, album_id: &str) -> Result<bool> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let Some(passphrase) = entries
+                .get_mut(album_id)
+                .and_then(|meta| meta.passphrase.as_mut())
+            else {
+                return Ok(false);
+            };
+            if !passphrase.views_left() {
+                return Ok(false);
+            }
+
+            passphrase.views += 1;
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await?;
+        Ok(true)
+    }
+}
+
+impl JsonStore<UserFavorites> {
+    /// Stars the photo or takes the star off again.
+    /// Returns whether the photo is now a favorite.
+    pub async fn toggle(&self, user_id: &str, favorite: FavoritePhoto) -> Result<bool> {
+        let (starred, snapshot) = {
+            let mut entries = self.entries.lock().unwrap();
+            let favorites = entries.entry(user_id.to_string()).or_default();
+
+            let count = favorites.photos.len();
+            favorites
+                .photos
+                .retain(|photo| photo.photo_id != favorite.photo_id);
+            let starred = favorites.photos.len() == count;
+            if starred {
+                favorites.photos.push(favorite);
+            }
+            if favorites.photos.is_empty() {
+                entries.remove(user_id);
+            }
+            (starred, self.snapshot(&entries)?)
+        };
+
+        self.save(snapshot).await?;
+        Ok(starred)
+    }
+
+    /// Takes the star off a photo that can no longer be found
+    pub async fn unstar(&self, user_id: &str, photo_id: &str) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let Some(favorites) = entries.get_mut(user_id) else {
+                return Ok(());
+            };
+            favorites.photos.retain(|photo| photo.photo_id != photo_id);
+            if favorites.photos.is_empty() {
+                entries.remove(user_id);
+            }
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+
+    /// Drops the photos of a deleted album from everyone's favorites
+    pub async fn forget_album(&self, album_id: &str) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let mut changed = false;
+            for favorites in entries.values_mut() {
+                let count = favorites.photos.len();
+                favorites.photos.retain(|photo| photo.album_id != album_id);
+                changed = changed || favorites.photos.len() != count;
+            }
+            if !changed {
+                return Ok(());
+            }
+
+            entries.retain(|_, favorites| !favorites.photos.is_empty());
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+
+    /// Favorites of the user within the bucket, newest first
+    pub fn bucket_favorites(&self, user_id: &str, bucket_id: &str) -> Vec<FavoritePhoto> {
+        let entries = self.entries.lock().unwrap();
+        let mut photos: Vec<FavoritePhoto> = entries
+            .get(user_id)
+            .map(|favorites| {
+                favorites
+                    .photos
+                    .iter()
+                    .filter(|photo| photo.bucket_id == bucket_id)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        photos.sort_by_key(|photo| std::cmp::Reverse(photo.created_at));
+        photos
+    }
+
+    /// Marks the photos the user starred
+    pub fn fill(&self, user_id: &str, photos: &mut [Photo]) {
+        let entries = self.entries.lock().unwrap();
+        let Some(favorites) = entries.get(user_id) else {
+            return;
+        };
+        for photo in photos.iter_mut() {
+            photo.favorite = favorites.photos.iter().any(|f| f.photo_id == photo.id);
+        }
+    }
+
+    /// Drops a deleted photo from everyone's favorites
+    pub async fn forget_photo(&self, photo_id: &str) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let mut changed = false;
+            for favorites in entries.values_mut() {
+                let count = favorites.photos.len();
+                favorites.photos.retain(|photo| photo.photo_id != photo_id);
+                changed = changed || favorites.photos.len() != count;
+            }
+            if !changed {
+                return Ok(());
+            }
+
+            entries.retain(|_, favorites| !favorites.photos.is_empty());
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+}
+
+impl JsonStore<PhotoTags> {
+    /// Copies the tags onto photos from files-rs
+    pub fn fill(&self, photos: &mut [Photo]) {
+        let entries = self.entries.lock().unwrap();
+        for photo in photos.iter_mut() {
+            if let Some(entry) = entries.get(&photo.id) {
+                photo.tags = entry.tags.clone();
+            }
+        }
+    }
+
+    /// Tags used in the bucket with their photo counts, sorted by name
+    pub fn bucket_tags(&self, bucket_id: &str) -> Vec<TagCount> {
+        let entries = self.entries.lock().unwrap();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for entry in entries.values().filter(|e| e.bucket_id == bucket_id) {
+            for tag in entry.tags.iter() {
+                *counts.entry(tag.as_str()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(name, count)| TagCount {
+                name: name.to_string(),
+                count,
+            })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        tags
+    }
+
+    /// Photo ids in the bucket having the tag, most recently tagged first
+    pub fn tagged_photos(&self, bucket_id: &str, tag: &str) -> Vec<(String, PhotoTags)> {
+        let entries = self.entries.lock().unwrap();
+        let mut photos: Vec<(String, PhotoTags)> = entries
+            .iter()
+            .filter(|(_, e)| e.bucket_id == bucket_id && e.tags.iter().any(|t| t == tag))
+            .map(|(id, e)| (id.clone(), e.clone()))
+            .collect();
+        photos.sort_by(|a, b| b.1.updated_at.cmp(&a.1.updated_at).then(a.0.cmp(&b.0)));
+        photos
+    }
+}
+
+impl JsonStore<StagedUpload> {
+    /// Staged uploads of the album, oldest first
+    pub fn album_uploads(&self, album_id: &str) -> Vec<StagedUpload> {
+        let entries = self.entries.lock().unwrap();
+        let mut uploads: Vec<StagedUpload> = entries
+            .values()
+            .filter(|upload| upload.album_id == album_id)
+            .cloned()
+            .collect();
+        uploads.sort_by_key(|upload| upload.created_at);
+        uploads
+    }
+
+    /// Number and total size of the uploads staged for the album
+    pub fn album_usage(&self, album_id: &str) -> (usize, u64) {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .values()
+            .filter(|upload| upload.album_id == album_id)
+            .fold((0, 0), |(count, size), upload| {
+                (count + 1, size + upload.size)
+            })
+    }
+
+    /// Ids of uploads staged before the timestamp
+    pub fn created_before(&self, timestamp: i64) -> Vec<String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|(_, upload)| upload.created_at < timestamp)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+impl JsonStore<ShareLink> {
+    /// Links for the album that are still valid, newest first.
+    /// Expired links are dropped along the way.
+    pub async fn album_links(&self, album_id: &str, now: i64) -> Result<Vec<ShareLink>> {
+        let (mut links, snapshot) = {
+            let mut entries = self.entries.lock().unwrap();
+            let count = entries.len();
+            entries.retain(|_, link| link.expires_at > now);
+            let snapshot = if entries.len() != count {
+                Some(self.snapshot(&entries)?)
+            } else {
+                None
+            };
+
+            let links: Vec<ShareLink> = entries
+                .values()
+                .filter(|link| link.album_id == album_id)
+                .cloned()
+                .collect();
+            (links, snapshot)
+        };
+        if let Some(snapshot) = snapshot {
+            self.save(snapshot).await?;
+        }
+
+        links.sort_by_key(|link| std::cmp::Reverse(link.created_at));
+        Ok(links)
+    }
+}
+
+impl JsonStore<GuestUploadLink> {
+    /// Links for the album that are still valid, newest first.
+    /// Expired links are dropped along the way.
+    pub async fn album_links(&self, album_id: &str, now: i64) -> Result<Vec<GuestUploadLink>> {
+        let (mut links, snapshot) = {
+            let mut entries = self.entries.lock().unwrap();
+            let count = entries.len();
+            entries.retain(|_, link| link.expires_at > now);
+            let snapshot = if entries.len() != count {
+                Some(self.snapshot(&entries)?)
+            } else {
+                None
+            };
+
+            let links: Vec<GuestUploadLink> = entries
+                .values()
+                .filter(|link| link.album_id == album_id)
+                .cloned()
+                .collect();
+            (links, snapshot)
+        };
+        if let Some(snapshot) = snapshot {
+            self.save(snapshot).await?;
+        }
+
+        links.sort_by_key(|link| std::cmp::Reverse(link.created_at));
+        Ok(links)
+    }
+
+    /// Takes one upload off the link before the photo is sent to files-rs.
+    /// Returns false when the link is expired or has no uploads left.
+    pub async fn reserve_upload(&self, link_id: &str, now: i64) -> Result<bool> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let Some(link) = entries.get_mut(link_id) else {
+                return Ok(false);
+            };
+            if link.expires_at <= now || link.uploads_left() == 0 {
+                return Ok(false);
+            }
+
+            link.uploads += 1;
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await?;
+        Ok(true)
+    }
+
+    /// Gives back an upload that did not make it to files-rs
+    pub async fn release_upload(&self, link_id: &str) -> Result<()> {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let Some(link) = entries.get_mut(link_id) else {
+                return Ok(());
+            };
+
+            link.uploads = link.uploads.saturating_sub(1);
+            self.snapshot(&entries)?
+        };
+        self.save(snapshot).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("memo-rs-store-{}", Uuid::new_v4()))
+            .join("data.json")
+    }
+
+    #[tokio::test]
+    async fn test_set_and_reopen() {
+        let path = temp_path();
+        let store: JsonStore<String> = JsonStore::open(path.clone()).unwrap();
+        assert!(store.get("a").is_none());
+
+        store.set("a", "one".to_string()).await.unwrap();
+        store.set("b", "two".to_string()).await.unwrap();
+        assert_eq!(store.remove("b").await.unwrap(), Some("two".to_string()));
+
+        let store: JsonStore<String> = JsonStore::open(path).unwrap();
+        assert_eq!(store.get("a"), Some("one".to_string()));
+        assert!(store.get("b").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes() {
+        let path = temp_path();
+        let store: Arc<JsonStore<String>> = Arc::new(JsonStore::open(path.clone()).unwrap());
+        let writes = (0..20).map(|i| {
+            let store = store.clone();
+            tokio::spawn(async move { store.set(&format!("k{}", i), i.to_string()).await })
+        });
+        for write in futures_util::future::join_all(writes).await {
+            write.unwrap().unwrap();
+        }
+
+        // The newest snapshot is the one left on disk
+        let store: JsonStore<String> = JsonStore::open(path).unwrap();
+        for i in 0..20 {
+            assert_eq!(store.get(&format!("k{}", i)), Some(i.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let path = temp_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+
+        let result: Result<JsonStore<String>> = JsonStore::open(path);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_album_links() {
+        let store: JsonStore<ShareLink> = JsonStore::open(temp_path()).unwrap();
+        let link = |id: &str, album_id: &str, created_at: i64, expires_at: i64| ShareLink {
+            id: id.to_string(),
+            bucket_id: "bucket-1".to_string(),
+            album_id: album_id.to_string(),
+            created_by: "admin".to_string(),
+            created_at,
+            expires_at,
+        };
+        store.set("a", link("a", "album-1", 10, 200)).await.unwrap();
+        store.set("b", link("b", "album-1", 20, 200)).await.unwrap();
+        store.set("c", link("c", "album-2", 30, 200)).await.unwrap();
+        store.set("d", link("d", "album-1", 40, 100)).await.unwrap();
+
+        let links = store.album_links("album-1", 150).await.unwrap();
+        let ids: Vec<&str> = links.iter().map(|l| l.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+        assert!(store.get("d").is_none());
+        assert!(store.get("c").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reserve_upload() {
+        let store: JsonStore<GuestUploadLink> = JsonStore::open(temp_path()).unwrap();
+        let link = GuestUploadLink {
+            id: "a".to_string(),
+            bucket_id: "bucket-1".to_string(),
+            album_id: "album-1".to_string(),
+            created_by: "admin".to_string(),
+            created_at: 10,
+            expires_at: 200,
+            max_uploads: 2,
+            uploads: 0,
+        };
+        store.set("a", link).await.unwrap();
+
+        assert!(store.reserve_upload("a", 100).await.unwrap());
+        assert!(store.reserve_upload("a", 100).await.unwrap());
+        assert!(!store.reserve_upload("a", 100).await.unwrap());
+
+        store.release_upload("a").await.unwrap();
+        assert_eq!(store.get("a").unwrap().uploads, 1);
+        assert!(!store.reserve_upload("a", 300).await.unwrap());
+        assert!(!store.reserve_upload("b", 100).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_favorites() {
+        let store: JsonStore<UserFavorites> = JsonStore::open(temp_path()).unwrap();
+        let favorite = |photo_id: &str, created_at: i64| FavoritePhoto {
+            bucket_id: "bucket-1".to_string(),
+            album_id: "album-1".to_string(),
+            photo_id: photo_id.to_string(),
+            created_at,
+        };
+
+        assert!(store.toggle("user-1", favorite("a", 1)).await.unwrap());
+        assert!(store.toggle("user-1", favorite("b", 2)).await.unwrap());
+        assert!(store.toggle("user-2", favorite("a", 3)).await.unwrap());
+
+        let ids: Vec<String> = store
+            .bucket_favorites("user-1", "bucket-1")
+            .into_iter()
+            .map(|photo| photo.photo_id)
+            .collect();
+        assert_eq!(ids, vec!["b".to_string(), "a".to_string()]);
+        assert!(store.bucket_favorites("user-1", "bucket-2").is_empty());
+
+        assert!(!store.toggle("user-1", favorite("b", 4)).await.unwrap());
+        assert_eq!(store.bucket_favorites("user-1", "bucket-1").len(), 1);
+
+        store.forget_photo("a").await.unwrap();
+        assert!(store.get("user-1").is_none());
+        assert!(store.get("user-2").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bucket_tags() {
+        let store: JsonStore<PhotoTags> = JsonStore::open(temp_path()).unwrap();
+        let entry = |bucket_id: &str, tags: &[&str], updated_at: i64| PhotoTags {
+            bucket_id: bucket_id.to_string(),
+            album_id: "album-1".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            updated_at,
+        };
+        store
+            .set("a", entry("bucket-1", &["beach", "family"], 1))
+            .await
+            .unwrap();
+        store
+            .set("b", entry("bucket-1", &["beach"], 2))
+            .await
+            .unwrap();
+        store
+            .set("c", entry("bucket-2", &["beach"], 3))
+            .await
+            .unwrap();
+
+        let tags: Vec<(String, usize)> = store
+            .bucket_tags("bucket-1")
+            .into_iter()
+            .map(|tag| (tag.name, tag.count))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![("beach".to_string(), 2), ("family".to_string(), 1)]
+        );
+
+        let ids: Vec<String> = store
+            .tagged_photos("bucket-1", "beach")
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec!["b".to_string(), "a".to_string()]);
+        assert!(store.tagged_photos("bucket-2", "family").is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::TimelinePhoto;
+
+/// How long a merged timeline is reused while scrolling
+pub const TIMELINE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Maximum number of cached timelines
+pub const TIMELINE_CACHE_MAX_ENTRIES: usize = 100;
+
+struct CachedTimeline {
+    items: Vec<TimelinePhoto>,
+    expires_at: Instant,
+}
+
+/// Photos of every album in a bucket merged into a single list, keyed by
+/// user and bucket. Walking every album is expensive, so later pages of the
+/// same timeline are served from here.
+pub struct TimelineCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, CachedTimeline>>,
+}
+
+impl TimelineCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, user_id: &str, bucket_id: &str) -> Option<Vec<TimelinePhoto>> {
+        let key = cache_key(user_id, bucket_id);
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.items.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, user_id: &str, bucket_id: &str, items: Vec<TimelinePhoto>) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let key = cache_key(user_id, bucket_id);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            // Still full, evict the entry closest to expiring
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(k, _)| k.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            key,
+            CachedTimeline {
+                items,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+}
+
+impl Default for TimelineCache {
+    fn default() -> Self {
+        Self::new(TIMELINE_CACHE_TTL, TIMELINE_CACHE_MAX_ENTRIES)
+    }
+}
+
+fn cache_key(user_id: &str, bucket_id: &str) -> String {
+    format!("{}/{}", user_id, bucket_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyed_by_user_and_bucket() {
+        let cache = TimelineCache::default();
+        assert!(cache.get("user-1", "bucket-1").is_none());
+
+        cache.insert("user-1", "bucket-1", Vec::new());
+        assert!(cache.get("user-1", "bucket-1").is_some());
+        assert!(cache.get("user-1", "bucket-2").is_none());
+        assert!(cache.get("user-2", "bucket-1").is_none());
+    }
+
+    #[test]
+    fn test_expired_entry() {
+        let cache = TimelineCache::new(Duration::ZERO, 10);
+        cache.insert("user-1", "bucket-1", Vec::new());
+        assert!(cache.get("user-1", "bucket-1").is_none());
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let cache = TimelineCache::new(TIMELINE_CACHE_TTL, 2);
+        cache.insert("user-1", "bucket-1", Vec::new());
+        cache.insert("user-2", "bucket-1", Vec::new());
+        cache.insert("user-3", "bucket-1", Vec::new());
+
+        let cached = ["user-1", "user-2", "user-3"]
+            .iter()
+            .filter(|user_id| cache.get(user_id, "bucket-1").is_some())
+            .count();
+        assert_eq!(cached, 2);
+        assert!(cache.get("user-3", "bucket-1").is_some());
+    }
+}
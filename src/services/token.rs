@@ -4,12 +4,24 @@ use serde::{Deserialize, Serialize};
 
 use crate::{Error, Result};
 
+/// Keeps link and unlock tokens from passing as CSRF tokens or as each other
+const SHARE_AUDIENCE: &str = "share";
+const UNLOCK_AUDIENCE: &str = "unlock";
+const GUEST_UPLOAD_AUDIENCE: &str = "guest-upload";
+
 #[derive(Debug, Deserialize, Serialize)]
 struct Claims {
     sub: String,
     exp: usize,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct AudienceClaims {
+    sub: String,
+    aud: String,
+    exp: usize,
+}
+
 pub fn create_csrf_token(subject: &str, secret: &str) -> Result<String> {
     // Limit up to 1 hour only
     let exp = Utc::now() + Duration::hours(1);
@@ -39,18 +51,100 @@ pub fn verify_csrf_token(token: &str, secret: &str) -> Result<String> {
         return Err(Error::InvalidCsrfToken);
     };
 
-    if decoded.claims.sub.len() == 0 {
+    if decoded.claims.sub.is_empty() {
         return Err(Error::InvalidCsrfToken);
     }
 
     Ok(decoded.claims.sub)
 }
 
+/// Verifies the CSRF token and ensures it was issued for the given subject
+pub fn verify_csrf_subject(token: &str, subject: &str, secret: &str) -> Result<()> {
+    let csrf_result = verify_csrf_token(token, secret)?;
+    if csrf_result != subject {
+        return Err(Error::InvalidCsrfToken);
+    }
+    Ok(())
+}
+
+/// Signs a share link id, the token stops working at the given timestamp
+pub fn create_share_token(link_id: &str, expires_at: i64, secret: &str) -> Result<String> {
+    create_audience_token(link_id, SHARE_AUDIENCE, expires_at, secret)
+}
+
+/// Returns the share link id of a valid share token
+pub fn verify_share_token(token: &str, secret: &str) -> Result<String> {
+    verify_audience_token(token, SHARE_AUDIENCE, secret).ok_or(Error::ShareNotFound)
+}
+
+/// Signs a guest upload link id, the token stops working at the given timestamp
+pub fn create_guest_upload_token(link_id: &str, expires_at: i64, secret: &str) -> Result<String> {
+    create_audience_token(link_id, GUEST_UPLOAD_AUDIENCE, expires_at, secret)
+}
+
+/// Returns the guest upload link id of a valid guest upload token
+pub fn verify_guest_upload_token(token: &str, secret: &str) -> Result<String> {
+    verify_audience_token(token, GUEST_UPLOAD_AUDIENCE, secret).ok_or(Error::GuestUploadNotFound)
+}
+
+/// Proves that the visitor knows the album passphrase, valid for 12 hours
+pub fn create_unlock_token(subject: &str, secret: &str) -> Result<String> {
+    let exp = Utc::now() + Duration::hours(12);
+    create_audience_token(subject, UNLOCK_AUDIENCE, exp.timestamp(), secret)
+}
+
+/// Returns the subject of a valid unlock token
+pub fn verify_unlock_token(token: &str, secret: &str) -> Option<String> {
+    verify_audience_token(token, UNLOCK_AUDIENCE, secret)
+}
+
+fn create_audience_token(
+    subject: &str,
+    aud: &str,
+    expires_at: i64,
+    secret: &str,
+) -> Result<String> {
+    let claims = AudienceClaims {
+        sub: subject.to_string(),
+        aud: aud.to_string(),
+        exp: expires_at as usize,
+    };
+
+    let Ok(token) = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    ) else {
+        return Err("Error creating JWT token".into());
+    };
+
+    Ok(token)
+}
+
+fn verify_audience_token(token: &str, aud: &str, secret: &str) -> Option<String> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[aud]);
+
+    let decoded = decode::<AudienceClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .ok()?;
+
+    if decoded.claims.sub.is_empty() {
+        return None;
+    }
+
+    Some(decoded.claims.sub)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_jwt_token() {
         // Generate token
         let token = create_csrf_token("example", "secret").unwrap();
@@ -68,4 +162,47 @@ mod tests {
         let result = verify_csrf_token(&token, "secret");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_share_token() {
+        let exp = (Utc::now() + Duration::days(1)).timestamp();
+        let token = create_share_token("link-1", exp, "secret").unwrap();
+        assert_eq!(verify_share_token(&token, "secret").unwrap(), "link-1");
+        assert!(verify_share_token(&token, "other").is_err());
+
+        // Neither token works in place of the other
+        assert!(verify_csrf_token(&token, "secret").is_err());
+        let csrf = create_csrf_token("link-1", "secret").unwrap();
+        assert!(verify_share_token(&csrf, "secret").is_err());
+
+        let exp = (Utc::now() - Duration::days(1)).timestamp();
+        let token = create_share_token("link-1", exp, "secret").unwrap();
+        assert!(verify_share_token(&token, "secret").is_err());
+    }
+
+    #[test]
+    fn test_unlock_token() {
+        let token = create_unlock_token("album-1:v1", "secret").unwrap();
+        assert_eq!(
+            verify_unlock_token(&token, "secret"),
+            Some("album-1:v1".to_string())
+        );
+        assert!(verify_share_token(&token, "secret").is_err());
+        assert!(verify_csrf_token(&token, "secret").is_err());
+    }
+
+    #[test]
+    fn test_guest_upload_token() {
+        let exp = (Utc::now() + Duration::days(1)).timestamp();
+        let token = create_guest_upload_token("link-1", exp, "secret").unwrap();
+        assert_eq!(
+            verify_guest_upload_token(&token, "secret").unwrap(),
+            "link-1"
+        );
+
+        // Share links cannot be used to upload
+        let share = create_share_token("link-1", exp, "secret").unwrap();
+        assert!(verify_guest_upload_token(&share, "secret").is_err());
+        assert!(verify_share_token(&token, "secret").is_err());
+    }
 }
@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use axum::body::{Body, Bytes};
+use chrono::{Duration, Utc};
+use futures_util::{stream, Stream, StreamExt};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::models::{NewResumableUpload, ResumableUpload};
+use crate::{Error, Result};
+
+/// Unfinished resumable uploads are discarded after this many hours
+pub const UPLOAD_EXPIRY_HOURS: i64 = 24;
+
+/// Stores resumable uploads on disk while their chunks arrive
+pub struct UploadStore {
+    dir: PathBuf,
+    active: Mutex<HashSet<String>>,
+}
+
+/// Marks an upload as being written to until dropped
+struct ActiveUpload<'a> {
+    id: String,
+    active: &'a Mutex<HashSet<String>>,
+}
+
+impl Drop for ActiveUpload<'_> {
+    fn drop(&mut self) {
+        self.active.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Keeps only the base name, without characters that would break the multipart
+/// header. Falls back to `upload` when nothing is left.
+pub fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let base: String = base
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect();
+    match base.trim() {
+        "" => "upload".to_string(),
+        base => base.to_string(),
+    }
+}
+
+/// Multipart framing around a single streamed `file` field
+pub struct MultipartEnvelope {
+    pub content_type: String,
+    head: String,
+    tail: String,
+}
+
+impl MultipartEnvelope {
+    pub fn new(filename: &str, content_type: &str) -> Self {
+        // Quotes and line breaks would break the part header
+        let filename = sanitize_filename(filename);
+
+        let boundary = format!("memo-rs-{}", Uuid::new_v4().simple());
+        let head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, filename, content_type
+        );
+        let tail = format!("\r\n--{}--\r\n", boundary);
+
+        Self {
+            content_type: format!("multipart/form-data; boundary={}", boundary),
+            head,
+            tail,
+        }
+    }
+
+    /// Size of the framing alone, add the file size to get the body size
+    pub fn framing_len(&self) -> u64 {
+        (self.head.len() + self.tail.len()) as u64
+    }
+
+    pub fn wrap<S, E>(self, file: S) -> Body
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+        E: Into<axum::BoxError> + 'static,
+    {
+        let head = self.head;
+        let tail = self.tail;
+        let body = stream::once(async move { Ok::<Bytes, axum::BoxError>(Bytes::from(head)) })
+            .chain(file.map(|chunk| chunk.map_err(Into::into)))
+            .chain(stream::once(async move { Ok(Bytes::from(tail)) }));
+
+        Body::from_stream(body)
+    }
+}
+
+impl UploadStore {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            error!("Error: {}", e);
+            return Err("Unable to create upload dir.".into());
+        }
+
+        Ok(Self {
+            dir,
+            active: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub async fn create(&self, data: NewResumableUpload) -> Result<ResumableUpload> {
+        let expires_at = Utc::now() + Duration::hours(UPLOAD_EXPIRY_HOURS);
+        let upload = ResumableUpload {
+            id: Uuid::new_v4().to_string(),
+            user_id: data.user_id,
+            bucket_id: data.bucket_id,
+            album_id: data.album_id,
+            filename: data.filename,
+            content_type: data.content_type,
+            length: data.length,
+            expires_at: expires_at.timestamp(),
+            offset: 0,
+        };
+
+        let Ok(contents) = serde_json::to_string(&upload) else {
+            return Err("Unable to save upload information.".into());
+        };
+
+        let data_res = File::create(self.data_path(&upload.id)).await;
+        let info_res = fs::write(self.info_path(&upload.id), contents).await;
+        if let Err(e) = data_res.and(info_res) {
+            error!("Error: {}", e);
+            return Err("Unable to create upload.".into());
+        }
+
+        Ok(upload)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<ResumableUpload> {
+        // Ids end up in file paths, only accept what we generate
+        if Uuid::parse_str(id).is_err() {
+            return Err(Error::UploadNotFound);
+        }
+
+        let Ok(contents) = fs::read_to_string(self.info_path(id)).await else {
+            return Err(Error::UploadNotFound);
+        };
+        let Ok(mut upload) = serde_json::from_str::<ResumableUpload>(&contents) else {
+            return Err(Error::UploadNotFound);
+        };
+
+        if upload.expires_at <= Utc::now().timestamp() {
+            self.remove(id).await?;
+            return Err(Error::UploadNotFound);
+        }
+
+        let Ok(meta) = fs::metadata(self.data_path(id)).await else {
+            return Err(Error::UploadNotFound);
+        };
+        upload.offset = meta.len();
+
+        Ok(upload)
+    }
+
+    /// Appends a chunk starting at the given offset and returns the new offset
+    pub async fn append(&self, upload: &ResumableUpload, offset: u64, body: Body) -> Result<u64> {
+        let _guard = self.lock(&upload.id)?;
+
+        let current = self.get(&upload.id).await?;
+        if current.offset != offset {
+            return Err(Error::UploadConflict(
+                "Upload offset does not match.".to_string(),
+            ));
+        }
+
+        let open_res = OpenOptions::new()
+            .append(true)
+            .open(self.data_path(&upload.id))
+            .await;
+        let Ok(mut file) = open_res else {
+            return Err("Unable to open upload.".into());
+        };
+
+        let mut written = offset;
+        let mut chunks = body.into_data_stream();
+        let mut result = Ok(());
+
+        while let Some(chunk) = chunks.next().await {
+            let Ok(chunk) = chunk else {
+                // Keep what we got so far, the client can resume from there
+                result = Err(Error::BadRequest("Upload interrupted.".to_string()));
+                break;
+            };
+
+            if written + chunk.len() as u64 > upload.length {
+                result = Err(Error::BadRequest(
+                    "Upload exceeds the declared length.".to_string(),
+                ));
+                break;
+            }
+
+            if let Err(e) = file.write_all(&chunk).await {
+                error!("Error: {}", e);
+                result = Err("Unable to write upload.".into());
+                break;
+            }
+
+            written += chunk.len() as u64;
+        }
+
+        if let Err(e) = file.flush().await {
+            error!("Error: {}", e);
+            return Err("Unable to write upload.".into());
+        }
+
+        result.map(|_| written)
+    }
+
+    /// Builds a multipart body holding the assembled file, as files-rs expects
+    pub async fn multipart_body(&self, upload: &ResumableUpload) -> Result<(String, u64, Body)> {
+        let Ok(file) = File::open(self.data_path(&upload.id)).await else {
+            return Err("Unable to open upload.".into());
+        };
+
+        let envelope = MultipartEnvelope::new(&upload.filename, &upload.content_type);
+        let length = envelope.framing_len() + upload.length;
+        let content_type = envelope.content_type.clone();
+
+        Ok((content_type, length, envelope.wrap(ReaderStream::new(file))))
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        // Files may already be gone, nothing to do then
+        let _ = fs::remove_file(self.data_path(id)).await;
+        let _ = fs::remove_file(self.info_path(id)).await;
+        Ok(())
+    }
+
+    /// Removes uploads that were not completed in time
+    pub async fn remove_expired(&self) -> Result<usize> {
+        let Ok(mut entries) = fs::read_dir(&self.dir).await else {
+            return Err("Unable to read upload dir.".into());
+        };
+
+        let now = Utc::now().timestamp();
+        let mut count: usize = 0;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let Ok(contents) = fs::read_to_string(&path).await else {
+                continue;
+            };
+            let Ok(upload) = serde_json::from_str::<ResumableUpload>(&contents) else {
+                continue;
+            };
+
+            if upload.expires_at <= now && !self.active.lock().unwrap().contains(&upload.id) {
+                self.remove(&upload.id).await?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    fn lock(&self, id: &str) -> Result<ActiveUpload<'_>> {
+        let mut active = self.active.lock().unwrap();
+        if !active.insert(id.to_string()) {
+            return Err(Error::UploadConflict(
+                "Upload is already in progress.".to_string(),
+            ));
+        }
+
+        Ok(ActiveUpload {
+            id: id.to_string(),
+            active: &self.active,
+        })
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", id))
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
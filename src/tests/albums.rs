@@ -0,0 +1,895 @@
+use reqwest::{Method, StatusCode};
+
+use crate::models::{
+    AlbumMeta, FavoritePhoto, GuestUploadLink, NewStagedUpload, PhotoTags, ShareLink,
+};
+
+use super::{multipart_body, TestApp, ADMIN_TOKEN, BUCKET_ID, VIEWER_TOKEN};
+
+#[tokio::test]
+async fn test_album_listing() {
+    let app = TestApp::spawn().await;
+    app.fake.add_dir("summer", "Summer Trip");
+    app.fake.add_dir("winter", "Winter Trip");
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("Summer Trip"));
+    assert!(body.contains("Winter Trip"));
+    assert!(body.contains("Create new album"));
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing?keyword=summer", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Summer Trip"));
+    assert!(!body.contains("Winter Trip"));
+}
+
+#[tokio::test]
+async fn test_album_listing_viewer() {
+    let app = TestApp::spawn().await;
+    app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Summer Trip"));
+    assert!(!body.contains("Create new album"));
+}
+
+#[tokio::test]
+async fn test_album_listing_sort() {
+    let app = TestApp::spawn().await;
+    let bravo = app.fake.add_dir("bravo", "Bravo Trip");
+    app.fake.add_dir("alpha", "Alpha Trip");
+    app.fake.add_dir("charlie", "Charlie Trip");
+    app.fake.add_file(&bravo.id, "a.jpg", "image/jpeg", b"a");
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing?sort=label", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    let alpha = body.find("Alpha Trip").unwrap();
+    let bravo_pos = body.find("Bravo Trip").unwrap();
+    let charlie = body.find("Charlie Trip").unwrap();
+    assert!(alpha < bravo_pos && bravo_pos < charlie);
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing?sort=-file_count", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.find("Bravo Trip").unwrap() < body.find("Alpha Trip").unwrap());
+}
+
+#[tokio::test]
+async fn test_album_listing_filters() {
+    let app = TestApp::spawn().await;
+    let full = app.fake.add_dir("full", "Full Trip");
+    let old = app.fake.add_dir("old", "Old Trip");
+    app.fake.add_dir("empty", "Empty Trip");
+    app.fake.add_file(&full.id, "a.jpg", "image/jpeg", b"a");
+    app.fake.add_file(&old.id, "b.jpg", "image/jpeg", b"b");
+    {
+        let mut state = app.fake.state();
+        let dir = state.dirs.iter_mut().find(|d| d.id == old.id).unwrap();
+        // 2020-01-01
+        dir.updated_at = 1_577_836_800;
+    }
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing?non_empty=true", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Full Trip"));
+    assert!(body.contains("Old Trip"));
+    assert!(!body.contains("Empty Trip"));
+
+    let res = app
+        .htmx_request(
+            Method::GET,
+            "/albums/listing?non_empty=true&updated_since=2021-06-01",
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Full Trip"));
+    assert!(!body.contains("Old Trip"));
+    assert!(!body.contains("Empty Trip"));
+}
+
+#[tokio::test]
+async fn test_album_listing_sort_pagination() {
+    let app = TestApp::spawn().await;
+    for i in 0..12 {
+        let label = format!("Trip {:02}", i);
+        app.fake.add_dir(&format!("trip-{}", i), &label);
+    }
+
+    let res = app
+        .htmx_request(
+            Method::GET,
+            "/albums/listing?sort=-label&non_empty=false&per_page=5",
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Trip 11"));
+    assert!(body.contains("Trip 07"));
+    assert!(!body.contains("Trip 06"));
+    assert!(body.contains("?page=2&amp;per_page=5&amp;sort=-label"));
+
+    let res = app
+        .htmx_request(
+            Method::GET,
+            "/albums/listing?page=3&per_page=5&sort=-label",
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Trip 01"));
+    assert!(body.contains("Trip 00"));
+    assert!(!body.contains("Trip 02"));
+}
+
+#[tokio::test]
+async fn test_index_keeps_album_filters() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            "/?sort=-updated_at&non_empty=true&updated_since=2024-01-31",
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains(r#"<option value="-updated_at" selected>"#));
+    assert!(body.contains(r#"value="2024-01-31""#));
+    assert!(body.contains(r#"value="true" checked"#));
+    assert!(body.contains("sort=-updated_at&amp;non_empty=true&amp;updated_since=2024-01-31"));
+}
+
+#[tokio::test]
+async fn test_create_album() {
+    let app = TestApp::spawn().await;
+    let token = app.csrf_token("/albums/new", ADMIN_TOKEN).await;
+
+    let res = app
+        .htmx_request(Method::POST, "/albums/new", ADMIN_TOKEN)
+        .form(&[
+            ("name", "summer"),
+            ("label", "Summer Trip"),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let album_id = app.fake.state().dirs[0].id.clone();
+    let redirect = res.headers().get("HX-Redirect").unwrap();
+    assert_eq!(
+        redirect.to_str().unwrap(),
+        format!("/buckets/{}/albums/{}", BUCKET_ID, album_id)
+    );
+
+    let res = app
+        .auth_request(Method::GET, &format!("/albums/{}", album_id), ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("Summer Trip"));
+}
+
+#[tokio::test]
+async fn test_create_album_validation_error() {
+    let app = TestApp::spawn().await;
+    app.fake.add_dir("summer", "Summer Trip");
+    let token = app.csrf_token("/albums/new", ADMIN_TOKEN).await;
+
+    let res = app
+        .htmx_request(Method::POST, "/albums/new", ADMIN_TOKEN)
+        .form(&[("name", "summer"), ("label", "Another"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Directory already exists"));
+    assert!(body.contains("value=\"Another\""));
+    assert_eq!(app.fake.state().dirs.len(), 1);
+}
+
+#[tokio::test]
+async fn test_create_album_invalid_token() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .htmx_request(Method::POST, "/albums/new", ADMIN_TOKEN)
+        .form(&[("name", "summer"), ("label", "Summer"), ("token", "stale")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Stale form data"));
+    assert!(app.fake.state().dirs.is_empty());
+}
+
+#[tokio::test]
+async fn test_create_album_forbidden() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .auth_request(Method::GET, "/albums/new", VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("You do not have permission to create albums."));
+}
+
+#[tokio::test]
+async fn test_edit_album() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/edit", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("label", "Summer 2024"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("hx-swap-oob=\"true\""));
+    assert!(body.contains("Summer 2024"));
+    assert_eq!(app.fake.state().dirs[0].label, "Summer 2024");
+}
+
+#[tokio::test]
+async fn test_delete_album() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/delete", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let redirect = res.headers().get("HX-Redirect").unwrap();
+    assert_eq!(
+        redirect.to_str().unwrap(),
+        format!("/buckets/{}", BUCKET_ID)
+    );
+    assert!(app.fake.state().dirs.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_album_forgets_local_data() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let other = app.fake.add_dir("winter", "Winter Trip");
+    let state = &app.state;
+
+    for album_id in [&dir.id, &other.id] {
+        let meta = AlbumMeta {
+            moderated: true,
+            ..Default::default()
+        };
+        state.album_meta.set(album_id, meta).await.unwrap();
+
+        let share = ShareLink {
+            id: format!("share-{}", album_id),
+            bucket_id: BUCKET_ID.to_string(),
+            album_id: album_id.to_string(),
+            created_by: "admin".to_string(),
+            created_at: 0,
+            expires_at: i64::MAX,
+        };
+        state
+            .share_links
+            .set(&share.id, share.clone())
+            .await
+            .unwrap();
+
+        let link = GuestUploadLink {
+            id: format!("guest-{}", album_id),
+            bucket_id: BUCKET_ID.to_string(),
+            album_id: album_id.to_string(),
+            created_by: "admin".to_string(),
+            created_at: 0,
+            expires_at: i64::MAX,
+            max_uploads: 10,
+            uploads: 0,
+        };
+        state
+            .guest_upload_links
+            .set(&link.id, link.clone())
+            .await
+            .unwrap();
+
+        // Photos removed from files-rs some other way
+        let photo_id = format!("photo-{}", album_id);
+        let tags = PhotoTags {
+            bucket_id: BUCKET_ID.to_string(),
+            album_id: album_id.to_string(),
+            tags: vec!["beach".to_string()],
+            updated_at: 0,
+        };
+        state.photo_tags.set(&photo_id, tags).await.unwrap();
+        let favorite = FavoritePhoto {
+            bucket_id: BUCKET_ID.to_string(),
+            album_id: album_id.to_string(),
+            photo_id,
+            created_at: 0,
+        };
+        state.favorites.toggle("user-1", favorite).await.unwrap();
+
+        let (content_type, body) = multipart_body("beach.jpg", "image/jpeg", b"jpeg");
+        let data = NewStagedUpload {
+            bucket_id: BUCKET_ID.to_string(),
+            album_id: album_id.to_string(),
+            uploaded_by: "guest".to_string(),
+            guest: true,
+        };
+        state
+            .staging
+            .stage(data, &content_type, body.into())
+            .await
+            .unwrap();
+    }
+
+    let path = format!("/albums/{}/delete", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert!(state.album_meta.get(&dir.id).is_none());
+    assert!(state
+        .share_links
+        .get(&format!("share-{}", dir.id))
+        .is_none());
+    assert!(state
+        .guest_upload_links
+        .get(&format!("guest-{}", dir.id))
+        .is_none());
+    assert!(state.photo_tags.get(&format!("photo-{}", dir.id)).is_none());
+    assert!(state.staging.album_uploads(&dir.id).is_empty());
+    let favorites = state.favorites.get("user-1").unwrap();
+    assert_eq!(favorites.photos.len(), 1);
+    assert_eq!(favorites.photos[0].album_id, other.id);
+
+    // Other albums keep theirs
+    assert!(state.album_meta.get(&other.id).is_some());
+    assert!(state
+        .share_links
+        .get(&format!("share-{}", other.id))
+        .is_some());
+    assert!(state
+        .guest_upload_links
+        .get(&format!("guest-{}", other.id))
+        .is_some());
+    assert!(state
+        .photo_tags
+        .get(&format!("photo-{}", other.id))
+        .is_some());
+    assert_eq!(state.staging.album_uploads(&other.id).len(), 1);
+}
+
+#[tokio::test]
+async fn test_delete_album_not_empty() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let path = format!("/albums/{}/delete", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Directory is not empty"));
+    assert_eq!(app.fake.state().dirs.len(), 1);
+}
+
+#[tokio::test]
+async fn test_album_not_found() {
+    let app = TestApp::spawn().await;
+
+    // Full page error on regular requests
+    let res = app
+        .auth_request(Method::GET, "/albums/missing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("Album not found"));
+
+    // Error fragment on htmx requests
+    let res = app
+        .htmx_request(Method::GET, "/albums/missing/photo-grid", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("Album not found"));
+}
+
+/// Reads the stored entries of a ZIP archive through its central directory
+fn read_zip(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+    let end = data.len() - 22;
+    assert_eq!(u32_at(end), 0x06054b50);
+    let count = u16_at(end + 10);
+    let mut pos = u32_at(end + 16) as usize;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(pos), 0x02014b50);
+        let crc = u32_at(pos + 16);
+        let size = u32_at(pos + 20) as usize;
+        let name_len = u16_at(pos + 28);
+        let extra_len = u16_at(pos + 30);
+        let offset = u32_at(pos + 42) as usize;
+        let name = String::from_utf8(data[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+
+        assert_eq!(u32_at(offset), 0x04034b50);
+        let start = offset + 30 + u16_at(offset + 26) + u16_at(offset + 28);
+        let contents = data[start..start + size].to_vec();
+        assert_eq!(crc32fast::hash(&contents), crc);
+
+        entries.push((name, contents));
+        pos += 46 + name_len + extra_len;
+    }
+    entries
+}
+
+#[tokio::test]
+async fn test_download_album() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip!");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"first");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"second");
+    app.fake
+        .add_file(&dir.id, "sunset.jpg", "image/jpeg", b"third");
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/download", dir.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("Content-Type").unwrap(),
+        "application/zip"
+    );
+    assert_eq!(
+        res.headers().get("Content-Disposition").unwrap(),
+        "attachment; filename=\"Summer Trip_.zip\""
+    );
+
+    let entries = read_zip(&res.bytes().await.unwrap());
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names.len(), 3);
+    assert!(names.contains(&"beach.jpg"));
+    assert!(names.contains(&"beach (1).jpg"));
+    assert!(names.contains(&"sunset.jpg"));
+    let mut contents: Vec<&[u8]> = entries.iter().map(|(_, data)| data.as_slice()).collect();
+    contents.sort();
+    assert_eq!(contents, vec![&b"first"[..], &b"second"[..], &b"third"[..]]);
+}
+
+#[tokio::test]
+async fn test_download_album_previews() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"first");
+
+    let path = format!("/albums/{}/download?version=prev", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("Content-Disposition").unwrap(),
+        "attachment; filename=\"Summer Trip-previews.zip\""
+    );
+    let entries = read_zip(&res.bytes().await.unwrap());
+    assert_eq!(
+        entries,
+        vec![("beach.jpg".to_string(), b"prev:first".to_vec())]
+    );
+
+    let path = format!("/albums/{}/download?version=thumb", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_download_album_partial_failure() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"first");
+    let broken = app
+        .fake
+        .add_file(&dir.id, "sunset.jpg", "image/jpeg", b"second");
+    app.fake.state().broken_raw.push(broken.id.clone());
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/download", dir.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let entries = read_zip(&res.bytes().await.unwrap());
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], ("beach.jpg".to_string(), b"first".to_vec()));
+    assert_eq!(entries[1].0, "download-errors.txt");
+    let errors = String::from_utf8(entries[1].1.clone()).unwrap();
+    assert!(errors.contains("sunset.jpg: "));
+}
+
+#[tokio::test]
+async fn test_download_album_aborts_on_truncated_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let truncated = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"first photo");
+    app.fake
+        .add_file(&dir.id, "sunset.jpg", "image/jpeg", b"second");
+    app.fake.state().truncated_raw.push(truncated.id.clone());
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/download", dir.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The download fails instead of finishing with half a photo
+    assert!(res.bytes().await.is_err());
+}
+
+#[tokio::test]
+async fn test_album_listing_grid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let empty = app.fake.add_dir("winter", "Winter Trip");
+    app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    app.fake.add_file(&dir.id, "b.jpg", "image/jpeg", b"b");
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("album-card"));
+
+    let res = app
+        .htmx_request(Method::POST, "/prefs/album-view/grid", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("HX-Trigger").unwrap(),
+        "AlbumViewSetEvent"
+    );
+    let cookie = res.headers().get("Set-Cookie").unwrap().to_str().unwrap();
+    assert!(cookie.starts_with("album_view=grid"));
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .header(
+            "Cookie",
+            format!("auth_token={}; album_view=grid", ADMIN_TOKEN),
+        )
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("album-card"));
+    assert!(body.contains(&format!(
+        r#"<img src="/buckets/{}/albums/{}/cover""#,
+        BUCKET_ID, dir.id
+    )));
+    assert!(body.contains("2 photos"));
+    assert!(!body.contains(&format!("/albums/{}/cover", empty.id)));
+    assert!(body.contains("0 photos"));
+}
+
+#[tokio::test]
+async fn test_album_cover() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let first = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    let second = app.fake.add_file(&dir.id, "b.jpg", "image/jpeg", b"b");
+    let path = format!("/albums/{}/cover", dir.id);
+
+    // Defaults to the first photo
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FOUND);
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.ends_with(&format!("/raw/{}/thumb", first.id)));
+
+    // Pick the second one from its detail page
+    let photo_path = format!("/albums/{}/photos/{}", dir.id, second.id);
+    let res = app
+        .auth_request(Method::GET, &photo_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Set as cover"));
+
+    let token = app.csrf_token(&photo_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &format!("{}/cover", photo_path), ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("Album cover"));
+
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.ends_with(&format!("/raw/{}/thumb", second.id)));
+
+    // Deleting the cover falls back to the first photo again
+    let delete_path = format!("{}/delete", photo_path);
+    let token = app.csrf_token(&delete_path, ADMIN_TOKEN).await;
+    app.htmx_request(Method::POST, &delete_path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.ends_with(&format!("/raw/{}/thumb", first.id)));
+}
+
+#[tokio::test]
+async fn test_album_cover_empty() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/cover", dir.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_set_album_cover_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    let photo_path = format!("/albums/{}/photos/{}", dir.id, file.id);
+
+    let res = app
+        .auth_request(Method::GET, &photo_path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(!res.text().await.unwrap().contains("album-cover-control"));
+
+    let res = app
+        .htmx_request(Method::POST, &format!("{}/cover", photo_path), VIEWER_TOKEN)
+        .form(&[("token", "any")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_slideshow() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/slideshow?interval=10&loop=true", dir.id),
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("slideshow-test.js"));
+    assert!(body.contains(&format!(
+        "/buckets/{}/albums/{}/slideshow/playlist?interval=10&amp;shuffle=false&amp;loop=true",
+        BUCKET_ID, dir.id
+    )));
+    assert!(body.contains(r#"<option value="10" selected>"#));
+    assert!(body.contains(r#"name="loop" value="true" checked"#));
+    assert!(!body.contains(r#"name="shuffle" value="true" checked"#));
+}
+
+#[tokio::test]
+async fn test_slideshow_playlist() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    // More than a single page of photos
+    for i in 0..60 {
+        app.fake.add_file(
+            &dir.id,
+            &format!("photo-{:02}.jpg", i),
+            "image/jpeg",
+            b"jpeg data",
+        );
+    }
+    let first_id = app.fake.state().files[0].id.clone();
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!(
+                "/albums/{}/slideshow/playlist?interval=1&shuffle=true",
+                dir.id
+            ),
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("Content-Type").unwrap(),
+        "application/json"
+    );
+
+    let playlist: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(playlist["interval"], 3);
+    assert_eq!(playlist["shuffle"], true);
+    assert_eq!(playlist["loop"], false);
+
+    let slides = playlist["slides"].as_array().unwrap();
+    assert_eq!(slides.len(), 60);
+    assert_eq!(slides[0]["id"], first_id.as_str());
+    assert_eq!(slides[0]["name"], "photo-00.jpg");
+    assert!(slides[0]["url"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("/raw/{}/prev", first_id)));
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            "/albums/missing/slideshow/playlist",
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_album_listing_per_page_limit() {
+    let app = TestApp::spawn().await;
+    for i in 0..102 {
+        app.fake
+            .add_dir(&format!("trip-{}", i), &format!("Trip {:03}", i));
+    }
+
+    // Sorted listings are paginated here, the others by files-rs
+    for path in [
+        "/albums/listing?per_page=100000",
+        "/albums/listing?per_page=100000&sort=label",
+    ] {
+        let res = app
+            .htmx_request(Method::GET, path, ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.text().await.unwrap();
+        assert!(body.contains("?page=2&amp;per_page=100"));
+        assert!(!body.contains("per_page=100000"));
+    }
+}
@@ -0,0 +1,167 @@
+use reqwest::{Method, StatusCode};
+
+use super::{TestApp, ADMIN_TOKEN, BUCKET_ID};
+
+#[tokio::test]
+async fn test_bucket_scoped_albums() {
+    let app = TestApp::spawn().await;
+    let family = app.fake.add_bucket("Family");
+    app.fake.add_dir("summer", "Summer Trip");
+    let dir = app
+        .fake
+        .add_bucket_dir(&family.id, "birthday", "Birthday Party");
+
+    // Without a bucket in the url, the actor's default bucket is used
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Summer Trip"));
+    assert!(!body.contains("Birthday Party"));
+
+    let path = format!("/buckets/{}/albums/listing", family.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Birthday Party"));
+    assert!(!body.contains("Summer Trip"));
+    assert!(body.contains(&format!("/buckets/{}/albums/{}", family.id, dir.id)));
+
+    let path = format!("/buckets/{}/albums/{}", family.id, dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains(&format!("{}/photo-grid?page=1", path)));
+
+    // The album does not exist in the default bucket
+    let res = app
+        .auth_request(Method::GET, &format!("/albums/{}", dir.id), ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_select_bucket() {
+    let app = TestApp::spawn().await;
+    let family = app.fake.add_bucket("Family");
+    app.fake
+        .add_bucket_dir(&family.id, "birthday", "Birthday Party");
+
+    let res = app
+        .auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("bucket-tabs"));
+    assert!(body.contains(&format!("/prefs/bucket/{}", family.id)));
+
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("/prefs/bucket/{}", family.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let redirect = res.headers().get("HX-Redirect").unwrap();
+    assert_eq!(
+        redirect.to_str().unwrap(),
+        format!("/buckets/{}", family.id)
+    );
+    let cookie = res.headers().get("Set-Cookie").unwrap().to_str().unwrap();
+    assert!(cookie.starts_with(&format!("bucket={}", family.id)));
+
+    // The selected bucket is used when the url has none
+    let res = app
+        .request(Method::GET, "/albums/listing")
+        .header(
+            "Cookie",
+            format!("auth_token={}; bucket={}", ADMIN_TOKEN, family.id),
+        )
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Birthday Party"));
+}
+
+#[tokio::test]
+async fn test_unknown_bucket() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .auth_request(Method::GET, "/buckets/missing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.text().await.unwrap().contains("Bucket not found"));
+
+    let res = app
+        .htmx_request(Method::POST, "/prefs/bucket/missing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.headers().get("Set-Cookie").is_none());
+
+    // A stale selection falls back to the default bucket
+    let res = app
+        .request(Method::GET, "/")
+        .header(
+            "Cookie",
+            format!("auth_token={}; bucket=missing", ADMIN_TOKEN),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains(&format!("/buckets/{}/albums/listing", BUCKET_ID)));
+}
+
+#[tokio::test]
+async fn test_no_default_bucket() {
+    let app = TestApp::spawn().await;
+    app.fake.add_dir("summer", "Summer Trip");
+    app.fake.state().users[0].actor.default_bucket_id = None;
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("Summer Trip"));
+}
+
+#[tokio::test]
+async fn test_buckets_cached() {
+    let app = TestApp::spawn().await;
+
+    for _ in 0..3 {
+        let res = app
+            .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    assert_eq!(app.fake.state().buckets_calls, 1);
+}
@@ -0,0 +1,113 @@
+use reqwest::{Method, StatusCode};
+
+use super::{exif_jpeg, multipart_body, TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+async fn upload(app: &TestApp, album_id: &str, filename: &str, data: &[u8]) -> String {
+    let path = format!("/albums/{}/upload", album_id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let token = super::extract_input_value(&res.text().await.unwrap(), "token").unwrap();
+
+    let (content_type, body) = multipart_body(filename, "image/jpeg", data);
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("{}?token={}", path, token),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let state = app.fake.state();
+    state.files.last().unwrap().id.clone()
+}
+
+async fn photo_grid(app: &TestApp, album_id: &str, query: &str) -> String {
+    let path = format!("/albums/{}/photo-grid?{}", album_id, query);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_photo_exif_details() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let sunset = upload(&app, &dir.id, "sunset.jpg", &exif_jpeg()).await;
+    let plain = upload(&app, &dir.id, "plain.jpg", b"jpeg data").await;
+
+    // The original goes to files-rs untouched
+    assert_eq!(app.fake.state().files[0].data, exif_jpeg());
+
+    let path = format!("/albums/{}/photos/{}", dir.id, sunset);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-detail-exif"));
+    assert!(body.contains("Jun 15, 2024 12:30"));
+    assert!(body.contains("Canon EOS R6"));
+    assert!(body.contains("RF24-105mm F4 L IS USM"));
+    assert!(body.contains("1/250 s · f/4 · ISO 200 · 50 mm"));
+    assert!(body.contains("https://www.openstreetmap.org/?mlat=14.58333"));
+
+    let path = format!("/albums/{}/photos/{}", dir.id, plain);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!res.text().await.unwrap().contains("photo-detail-exif"));
+}
+
+#[tokio::test]
+async fn test_sort_and_filter_by_exif() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    upload(&app, &dir.id, "plain.jpg", b"jpeg data").await;
+    upload(&app, &dir.id, "sunset.jpg", &exif_jpeg()).await;
+
+    // Photos without a capture date are placed by upload time
+    let body = photo_grid(&app, &dir.id, "page=1&sort=taken_at").await;
+    let sunset = body.find("sunset.jpg").unwrap();
+    let plain = body.find("plain.jpg").unwrap();
+    assert!(sunset < plain);
+
+    let body = photo_grid(&app, &dir.id, "page=1&sort=-taken_at").await;
+    assert!(body.find("plain.jpg").unwrap() < body.find("sunset.jpg").unwrap());
+
+    let body = photo_grid(&app, &dir.id, "page=1&camera=canon").await;
+    assert!(body.contains("sunset.jpg"));
+    assert!(!body.contains("plain.jpg"));
+
+    let body = photo_grid(&app, &dir.id, "page=1&camera=nikon").await;
+    assert!(!body.contains("sunset.jpg"));
+    assert!(!body.contains("plain.jpg"));
+
+    let path = format!("/albums/{}?sort=-taken_at&camera=canon", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains(r#"<option value="-taken_at" selected>Newest taken</option>"#));
+    assert!(body.contains(r#"name="camera""#));
+    assert!(body.contains(r#"value="canon""#));
+    assert!(body.contains("&amp;sort=-taken_at&amp;camera=canon"));
+}
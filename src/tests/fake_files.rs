@@ -0,0 +1,762 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use chrono::Utc;
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::models::{Actor, Bucket, Permission, Role, User};
+
+pub const BUCKET_ID: &str = "bucket-1";
+pub const ADMIN_USERNAME: &str = "admin";
+pub const ADMIN_PASSWORD: &str = "password123";
+pub const ADMIN_TOKEN: &str = "token-admin";
+pub const VIEWER_TOKEN: &str = "token-viewer";
+pub const CONTRIBUTOR_TOKEN: &str = "token-contributor";
+
+/// In-memory stand-in for the files-rs endpoints used by memo-rs
+#[derive(Clone)]
+pub struct FakeFiles {
+    inner: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+pub struct FakeState {
+    pub url: String,
+    pub users: Vec<FakeUser>,
+    pub buckets: Vec<Bucket>,
+    pub dirs: Vec<FakeDir>,
+    pub files: Vec<FakeFile>,
+    pub authz_calls: usize,
+    pub buckets_calls: usize,
+    pub list_files_calls: usize,
+    /// File ids whose raw downloads fail
+    pub broken_raw: Vec<String>,
+    /// File ids whose raw downloads break off after the first bytes
+    pub truncated_raw: Vec<String>,
+    /// File ids listed without urls, as files-rs does while still processing
+    pub processing: Vec<String>,
+    next_id: u64,
+}
+
+pub struct FakeUser {
+    pub username: String,
+    pub password: String,
+    pub token: String,
+    pub actor: Actor,
+}
+
+#[derive(Clone)]
+pub struct FakeDir {
+    pub id: String,
+    pub bucket_id: String,
+    pub name: String,
+    pub label: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone)]
+pub struct FakeFile {
+    pub id: String,
+    pub dir_id: String,
+    pub name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    keyword: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct NewDir {
+    name: String,
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateDir {
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateFile {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CaptchaForm {
+    response: String,
+}
+
+impl FakeFiles {
+    /// Creates a backend seeded with an admin, a read-only viewer and
+    /// a contributor who can only add photos
+    pub fn new() -> Self {
+        let state = FakeState {
+            users: vec![
+                FakeUser::new(
+                    ADMIN_USERNAME,
+                    ADMIN_PASSWORD,
+                    ADMIN_TOKEN,
+                    Role::Admin,
+                    all_permissions(),
+                ),
+                FakeUser::new(
+                    "viewer",
+                    "password123",
+                    VIEWER_TOKEN,
+                    Role::Viewer,
+                    vec![
+                        Permission::BucketsList,
+                        Permission::BucketsView,
+                        Permission::DirsList,
+                        Permission::DirsView,
+                        Permission::FilesList,
+                        Permission::FilesView,
+                    ],
+                ),
+                FakeUser::new(
+                    "contributor",
+                    "password123",
+                    CONTRIBUTOR_TOKEN,
+                    Role::Editor,
+                    vec![
+                        Permission::BucketsList,
+                        Permission::BucketsView,
+                        Permission::DirsList,
+                        Permission::DirsView,
+                        Permission::FilesCreate,
+                        Permission::FilesList,
+                        Permission::FilesView,
+                    ],
+                ),
+            ],
+            buckets: vec![Bucket {
+                id: BUCKET_ID.to_string(),
+                client_id: "client-1".to_string(),
+                name: "Photos".to_string(),
+                created_at: 0,
+            }],
+            ..Default::default()
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.inner.lock().unwrap()
+    }
+
+    /// Serves the fake backend on a random port and returns its base url
+    pub async fn spawn(&self) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        self.state().url = url.clone();
+
+        let routes = self.routes();
+        tokio::spawn(async move {
+            axum::serve(listener, routes.into_make_service())
+                .await
+                .unwrap();
+        });
+
+        url
+    }
+
+    fn routes(&self) -> Router {
+        Router::new()
+            .route("/v1/auth/token", post(auth_token_handler))
+            .route("/v1/user/authz", get(authz_handler))
+            .route("/v1/buckets", get(list_buckets_handler))
+            .route(
+                "/v1/buckets/:bucket_id/dirs",
+                get(list_dirs_handler).post(create_dir_handler),
+            )
+            .route(
+                "/v1/buckets/:bucket_id/dirs/:dir_id",
+                get(get_dir_handler)
+                    .patch(update_dir_handler)
+                    .delete(delete_dir_handler),
+            )
+            .route(
+                "/v1/buckets/:bucket_id/dirs/:dir_id/files",
+                get(list_files_handler).post(upload_file_handler),
+            )
+            .route(
+                "/v1/buckets/:bucket_id/dirs/:dir_id/files/:file_id",
+                get(get_file_handler)
+                    .patch(update_file_handler)
+                    .delete(delete_file_handler),
+            )
+            .route("/raw/:file_id/:version", get(raw_file_handler))
+            .route("/recaptcha/api/siteverify", post(captcha_handler))
+            .layer(DefaultBodyLimit::disable())
+            .with_state(self.clone())
+    }
+
+    /// Adds another bucket for the seeded users
+    pub fn add_bucket(&self, name: &str) -> Bucket {
+        let mut state = self.state();
+        let bucket = Bucket {
+            id: state.next_id("extra-bucket"),
+            client_id: "client-1".to_string(),
+            name: name.to_string(),
+            created_at: Utc::now().timestamp(),
+        };
+        state.buckets.push(bucket.clone());
+        bucket
+    }
+
+    /// Adds an album directly to the default bucket
+    pub fn add_dir(&self, name: &str, label: &str) -> FakeDir {
+        self.add_bucket_dir(BUCKET_ID, name, label)
+    }
+
+    /// Adds an album directly to the given bucket
+    pub fn add_bucket_dir(&self, bucket_id: &str, name: &str, label: &str) -> FakeDir {
+        let mut state = self.state();
+        let now = Utc::now().timestamp();
+        let dir = FakeDir {
+            id: state.next_id("dir"),
+            bucket_id: bucket_id.to_string(),
+            name: name.to_string(),
+            label: label.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.dirs.push(dir.clone());
+        dir
+    }
+
+    /// Adds a file directly to the backend
+    pub fn add_file(&self, dir_id: &str, name: &str, content_type: &str, data: &[u8]) -> FakeFile {
+        let mut state = self.state();
+        let now = Utc::now().timestamp();
+        let file = FakeFile {
+            id: state.next_id("file"),
+            dir_id: dir_id.to_string(),
+            name: name.to_string(),
+            content_type: content_type.to_string(),
+            data: data.to_vec(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.files.push(file.clone());
+        file
+    }
+}
+
+impl FakeUser {
+    fn new(
+        username: &str,
+        password: &str,
+        token: &str,
+        role: Role,
+        permissions: Vec<Permission>,
+    ) -> Self {
+        let user = User {
+            id: format!("user-{}", username),
+            client_id: "client-1".to_string(),
+            username: username.to_string(),
+            status: "active".to_string(),
+            roles: vec![role.clone()],
+            created_at: 0,
+            updated_at: 0,
+        };
+        let actor = Actor {
+            id: user.id.clone(),
+            client_id: user.client_id.clone(),
+            default_bucket_id: Some(BUCKET_ID.to_string()),
+            scope: "auth vault".to_string(),
+            user,
+            roles: vec![role],
+            permissions,
+        };
+
+        Self {
+            username: username.to_string(),
+            password: password.to_string(),
+            token: token.to_string(),
+            actor,
+        }
+    }
+}
+
+impl FakeState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}-{}", prefix, self.next_id)
+    }
+
+    fn actor(&self, headers: &HeaderMap) -> Option<Actor> {
+        let value = headers.get("Authorization")?.to_str().ok()?;
+        let token = value.strip_prefix("Bearer ")?;
+        self.users
+            .iter()
+            .find(|u| u.token == token)
+            .map(|u| u.actor.clone())
+    }
+
+    fn dir_json(&self, dir: &FakeDir) -> Value {
+        let file_count = self.files.iter().filter(|f| f.dir_id == dir.id).count();
+        json!({
+            "id": dir.id,
+            "bucket_id": dir.bucket_id,
+            "name": dir.name,
+            "label": dir.label,
+            "file_count": file_count,
+            "created_at": dir.created_at,
+            "updated_at": dir.updated_at,
+        })
+    }
+
+    fn file_json(&self, file: &FakeFile) -> Value {
+        let is_image = file.content_type.starts_with("image/");
+        let processing = self.processing.contains(&file.id);
+        let raw_url = |name: &str| {
+            if processing {
+                Value::Null
+            } else {
+                json!(format!("{}/raw/{}/{}", self.url, file.id, name))
+            }
+        };
+        let version = |name: &str, size: u32| {
+            json!({
+                "version": name,
+                "dimension": { "width": size, "height": size },
+                "url": raw_url(name),
+            })
+        };
+        let (url, img_versions) = if is_image {
+            let versions = vec![
+                version("orig", 1600),
+                version("prev", 800),
+                version("thumb", 200),
+            ];
+            (Value::Null, json!(versions))
+        } else {
+            (raw_url("orig"), Value::Null)
+        };
+
+        json!({
+            "id": file.id,
+            "dir_id": file.dir_id,
+            "name": file.name,
+            "filename": file.name,
+            "content_type": file.content_type,
+            "size": file.data.len(),
+            "url": url,
+            "is_image": is_image,
+            "img_versions": img_versions,
+            "created_at": file.created_at,
+            "updated_at": file.updated_at,
+        })
+    }
+}
+
+fn all_permissions() -> Vec<Permission> {
+    vec![
+        Permission::BucketsList,
+        Permission::BucketsView,
+        Permission::DirsCreate,
+        Permission::DirsEdit,
+        Permission::DirsDelete,
+        Permission::DirsList,
+        Permission::DirsView,
+        Permission::DirsManage,
+        Permission::FilesCreate,
+        Permission::FilesEdit,
+        Permission::FilesDelete,
+        Permission::FilesList,
+        Permission::FilesView,
+        Permission::FilesManage,
+    ]
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    let body = json!({
+        "status_code": status.as_u16(),
+        "message": message,
+        "error": status.canonical_reason().unwrap_or(""),
+    });
+    (status, Json(body)).into_response()
+}
+
+fn paginate(items: Vec<Value>, query: &ListQuery, default_per_page: i64) -> Value {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(default_per_page).max(1);
+    let total_records = items.len() as i64;
+    let total_pages = (total_records + per_page - 1) / per_page;
+    let data: Vec<Value> = items
+        .into_iter()
+        .skip(((page - 1) * per_page) as usize)
+        .take(per_page as usize)
+        .collect();
+
+    json!({
+        "meta": {
+            "page": page,
+            "per_page": per_page,
+            "total_records": total_records,
+            "total_pages": total_pages,
+        },
+        "data": data,
+    })
+}
+
+async fn auth_token_handler(
+    State(fake): State<FakeFiles>,
+    Json(payload): Json<Credentials>,
+) -> Response {
+    let state = fake.state();
+    let user = state
+        .users
+        .iter()
+        .find(|u| u.username == payload.username && u.password == payload.password);
+    match user {
+        Some(user) => Json(json!({ "token": user.token })).into_response(),
+        None => error_response(StatusCode::UNAUTHORIZED, "Invalid username or password"),
+    }
+}
+
+async fn authz_handler(State(fake): State<FakeFiles>, headers: HeaderMap) -> Response {
+    let mut state = fake.state();
+    state.authz_calls += 1;
+    match state.actor(&headers) {
+        Some(actor) => Json(actor).into_response(),
+        None => error_response(StatusCode::UNAUTHORIZED, "Invalid token"),
+    }
+}
+
+async fn list_buckets_handler(State(fake): State<FakeFiles>, headers: HeaderMap) -> Response {
+    let mut state = fake.state();
+    state.buckets_calls += 1;
+    let Some(actor) = state.actor(&headers) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    };
+    if !actor.has_permissions(&vec![Permission::BucketsList]) {
+        return error_response(StatusCode::FORBIDDEN, "Forbidden");
+    }
+    let buckets: Vec<&Bucket> = state
+        .buckets
+        .iter()
+        .filter(|b| b.client_id == actor.client_id)
+        .collect();
+
+    Json(buckets).into_response()
+}
+
+async fn list_dirs_handler(
+    State(fake): State<FakeFiles>,
+    Path(bucket_id): Path<String>,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    let keyword = query.keyword.clone().unwrap_or_default().to_lowercase();
+    let items: Vec<Value> = state
+        .dirs
+        .iter()
+        .filter(|d| d.bucket_id == bucket_id)
+        .filter(|d| keyword.is_empty() || d.label.to_lowercase().contains(&keyword))
+        .map(|d| state.dir_json(d))
+        .collect();
+
+    Json(paginate(items, &query, 10)).into_response()
+}
+
+async fn create_dir_handler(
+    State(fake): State<FakeFiles>,
+    Path(bucket_id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<NewDir>,
+) -> Response {
+    let mut state = fake.state();
+    let Some(actor) = state.actor(&headers) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    };
+    if !actor.has_permissions(&vec![Permission::DirsCreate]) {
+        return error_response(StatusCode::FORBIDDEN, "Forbidden");
+    }
+    if payload.name.is_empty() || payload.label.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Name and label are required");
+    }
+    if state
+        .dirs
+        .iter()
+        .any(|d| d.bucket_id == bucket_id && d.name == payload.name)
+    {
+        return error_response(StatusCode::BAD_REQUEST, "Directory already exists");
+    }
+
+    let now = Utc::now().timestamp();
+    let dir = FakeDir {
+        id: state.next_id("dir"),
+        bucket_id,
+        name: payload.name,
+        label: payload.label,
+        created_at: now,
+        updated_at: now,
+    };
+    let body = state.dir_json(&dir);
+    state.dirs.push(dir);
+
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+async fn get_dir_handler(
+    State(fake): State<FakeFiles>,
+    Path((bucket_id, dir_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    match state
+        .dirs
+        .iter()
+        .find(|d| d.bucket_id == bucket_id && d.id == dir_id)
+    {
+        Some(dir) => Json(state.dir_json(dir)).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, "Directory not found"),
+    }
+}
+
+async fn update_dir_handler(
+    State(fake): State<FakeFiles>,
+    Path((bucket_id, dir_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateDir>,
+) -> Response {
+    let mut state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    if payload.label.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Label is required");
+    }
+    let Some(dir) = state
+        .dirs
+        .iter_mut()
+        .find(|d| d.bucket_id == bucket_id && d.id == dir_id)
+    else {
+        return error_response(StatusCode::NOT_FOUND, "Directory not found");
+    };
+    dir.label = payload.label;
+    dir.updated_at = Utc::now().timestamp();
+    let dir = dir.clone();
+
+    Json(state.dir_json(&dir)).into_response()
+}
+
+async fn delete_dir_handler(
+    State(fake): State<FakeFiles>,
+    Path((bucket_id, dir_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let mut state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    if state.files.iter().any(|f| f.dir_id == dir_id) {
+        return error_response(StatusCode::BAD_REQUEST, "Directory is not empty");
+    }
+    let count = state.dirs.len();
+    state
+        .dirs
+        .retain(|d| !(d.bucket_id == bucket_id && d.id == dir_id));
+    if state.dirs.len() == count {
+        return error_response(StatusCode::NOT_FOUND, "Directory not found");
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn list_files_handler(
+    State(fake): State<FakeFiles>,
+    Path((_bucket_id, dir_id)): Path<(String, String)>,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let mut state = fake.state();
+    state.list_files_calls += 1;
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    if !state.dirs.iter().any(|d| d.id == dir_id) {
+        return error_response(StatusCode::NOT_FOUND, "Directory not found");
+    }
+    let items: Vec<Value> = state
+        .files
+        .iter()
+        .filter(|f| f.dir_id == dir_id)
+        .map(|f| state.file_json(f))
+        .collect();
+
+    Json(paginate(items, &query, 50)).into_response()
+}
+
+async fn upload_file_handler(
+    State(fake): State<FakeFiles>,
+    Path((_bucket_id, dir_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    if fake.state().actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+
+    let mut upload: Option<(String, String, Vec<u8>)> = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let name = field.file_name().unwrap_or("upload").to_string();
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let Ok(data) = field.bytes().await else {
+            return error_response(StatusCode::BAD_REQUEST, "Invalid upload");
+        };
+        upload = Some((name, content_type, data.to_vec()));
+    }
+    let Some((name, content_type, data)) = upload else {
+        return error_response(StatusCode::BAD_REQUEST, "No file uploaded");
+    };
+
+    let file = fake.add_file(&dir_id, &name, &content_type, &data);
+    let state = fake.state();
+
+    (StatusCode::CREATED, Json(state.file_json(&file))).into_response()
+}
+
+async fn get_file_handler(
+    State(fake): State<FakeFiles>,
+    Path((_bucket_id, dir_id, file_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    match state
+        .files
+        .iter()
+        .find(|f| f.dir_id == dir_id && f.id == file_id)
+    {
+        Some(file) => Json(state.file_json(file)).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, "File not found"),
+    }
+}
+
+async fn update_file_handler(
+    State(fake): State<FakeFiles>,
+    Path((_bucket_id, dir_id, file_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateFile>,
+) -> Response {
+    let mut state = fake.state();
+    if state.actor(&headers).is_none() {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    }
+    if payload.name.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Name is required");
+    }
+    let Some(file) = state
+        .files
+        .iter_mut()
+        .find(|f| f.dir_id == dir_id && f.id == file_id)
+    else {
+        return error_response(StatusCode::NOT_FOUND, "File not found");
+    };
+    file.name = payload.name;
+    file.updated_at = Utc::now().timestamp();
+    let file = file.clone();
+
+    Json(state.file_json(&file)).into_response()
+}
+
+async fn delete_file_handler(
+    State(fake): State<FakeFiles>,
+    Path((_bucket_id, dir_id, file_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let mut state = fake.state();
+    let Some(actor) = state.actor(&headers) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid token");
+    };
+    if !actor.has_permissions(&vec![Permission::FilesDelete]) {
+        return error_response(StatusCode::FORBIDDEN, "Forbidden");
+    }
+    let count = state.files.len();
+    state
+        .files
+        .retain(|f| !(f.dir_id == dir_id && f.id == file_id));
+    if state.files.len() == count {
+        return error_response(StatusCode::NOT_FOUND, "File not found");
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn raw_file_handler(
+    State(fake): State<FakeFiles>,
+    Path((file_id, version)): Path<(String, String)>,
+) -> Response {
+    let state = fake.state();
+    if state.broken_raw.contains(&file_id) {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    match state.files.iter().find(|f| f.id == file_id) {
+        Some(file) if state.truncated_raw.contains(&file_id) => {
+            let head = Bytes::from(file.data[..file.data.len() / 2].to_vec());
+            let head = stream::once(async move { Ok::<Bytes, std::io::Error>(head) });
+            // Fail only once the response is on its way
+            let reset = stream::once(async {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                Err(std::io::Error::other("connection reset"))
+            });
+            Body::from_stream(head.chain(reset)).into_response()
+        }
+        Some(file) => {
+            // Only the original is served as is, other versions are tagged
+            let mut data = file.data.clone();
+            if version != "orig" {
+                data = [format!("{}:", version).into_bytes(), data].concat();
+            }
+            ([("Content-Type", file.content_type.clone())], data).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn captcha_handler(Form(form): Form<CaptchaForm>) -> Response {
+    Json(json!({ "success": form.response == "valid-captcha" })).into_response()
+}
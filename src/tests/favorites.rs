@@ -0,0 +1,178 @@
+use reqwest::{Method, StatusCode};
+
+use crate::models::PhotoTags;
+
+use super::{TestApp, ADMIN_TOKEN, BUCKET_ID, VIEWER_TOKEN};
+
+/// Token carried by the first favorite button in the html
+fn extract_favorite_token(html: &str) -> Option<String> {
+    let needle = "{\"token\": \"";
+    let start = html.find(needle)? + needle.len();
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].to_string())
+}
+
+async fn photo_grid(app: &TestApp, album_id: &str, token: &str) -> String {
+    let path = format!("/albums/{}/photo-grid?page=1", album_id);
+    let res = app
+        .htmx_request(Method::GET, &path, token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn toggle(app: &TestApp, album_id: &str, photo_id: &str, token: &str) -> String {
+    let grid = photo_grid(app, album_id, token).await;
+    let csrf_token = extract_favorite_token(&grid).expect("favorite token not found");
+
+    let path = format!("/albums/{}/photos/{}/favorite", album_id, photo_id);
+    let res = app
+        .htmx_request(Method::POST, &path, token)
+        .form(&[("token", csrf_token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn favorites_page(app: &TestApp, token: &str) -> String {
+    let res = app
+        .auth_request(Method::GET, "/favorites", token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_favorite_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let grid = photo_grid(&app, &dir.id, ADMIN_TOKEN).await;
+    assert!(grid.contains("Add to favorites"));
+    assert!(!grid.contains("is-favorite"));
+
+    let body = toggle(&app, &dir.id, &photo.id, ADMIN_TOKEN).await;
+    assert!(body.contains("is-favorite"));
+    assert!(body.contains("Remove from favorites"));
+
+    let grid = photo_grid(&app, &dir.id, ADMIN_TOKEN).await;
+    assert!(grid.contains("is-favorite"));
+
+    // Stars belong to the user who gave them
+    let grid = photo_grid(&app, &dir.id, VIEWER_TOKEN).await;
+    assert!(!grid.contains("is-favorite"));
+
+    let body = toggle(&app, &dir.id, &photo.id, ADMIN_TOKEN).await;
+    assert!(!body.contains("is-favorite"));
+    assert!(body.contains("Add to favorites"));
+}
+
+#[tokio::test]
+async fn test_favorite_photo_invalid_token() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let path = format!("/albums/{}/photos/{}/favorite", dir.id, photo.id);
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", "invalid")])
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("is-favorite"));
+
+    let grid = photo_grid(&app, &dir.id, ADMIN_TOKEN).await;
+    assert!(!grid.contains("is-favorite"));
+}
+
+#[tokio::test]
+async fn test_favorites_album() {
+    let app = TestApp::spawn().await;
+    let summer = app.fake.add_dir("summer", "Summer Trip");
+    let winter = app.fake.add_dir("winter", "Winter Trip");
+    let beach = app
+        .fake
+        .add_file(&summer.id, "beach.jpg", "image/jpeg", b"jpeg");
+    app.fake
+        .add_file(&summer.id, "sunset.jpg", "image/jpeg", b"jpeg");
+    let snow = app
+        .fake
+        .add_file(&winter.id, "snow.jpg", "image/jpeg", b"jpeg");
+
+    let body = favorites_page(&app, ADMIN_TOKEN).await;
+    assert!(body.contains("No favorites yet"));
+
+    toggle(&app, &summer.id, &beach.id, ADMIN_TOKEN).await;
+    toggle(&app, &winter.id, &snow.id, ADMIN_TOKEN).await;
+
+    let body = favorites_page(&app, ADMIN_TOKEN).await;
+    assert!(body.contains("beach.jpg"));
+    assert!(body.contains("snow.jpg"));
+    assert!(!body.contains("sunset.jpg"));
+    assert!(body.contains(&format!("/albums/{}/photos/{}", summer.id, beach.id)));
+    assert!(body.contains(&format!("/albums/{}/photos/{}", winter.id, snow.id)));
+
+    let body = favorites_page(&app, VIEWER_TOKEN).await;
+    assert!(body.contains("No favorites yet"));
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    let favorites = body.find("Favorites").expect("favorites entry not found");
+    assert!(favorites < body.find("Summer Trip").unwrap());
+    assert!(body.contains("/favorites"));
+
+    // Filtered listings only show real albums
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing?keyword=summer", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("/favorites"));
+
+    // Photos removed from files-rs drop out of the favorites
+    app.fake.state().files.retain(|file| file.id != snow.id);
+    let body = favorites_page(&app, ADMIN_TOKEN).await;
+    assert!(body.contains("beach.jpg"));
+    assert!(!body.contains("snow.jpg"));
+    assert!(!body.contains("could not be loaded"));
+}
+
+#[tokio::test]
+async fn test_favorites_show_tags() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let tags = PhotoTags {
+        bucket_id: BUCKET_ID.to_string(),
+        album_id: dir.id.clone(),
+        tags: vec!["family".to_string()],
+        updated_at: 0,
+    };
+    app.state.photo_tags.set(&photo.id, tags).await.unwrap();
+    toggle(&app, &dir.id, &photo.id, ADMIN_TOKEN).await;
+
+    let body = favorites_page(&app, ADMIN_TOKEN).await;
+    assert!(body.contains(&format!("id=\"photo-tags-{}\"", photo.id)));
+    assert!(body.contains("/tags/family\""));
+}
@@ -0,0 +1,242 @@
+use chrono::{Duration, Utc};
+use reqwest::{Method, StatusCode};
+
+use crate::services::create_share_token;
+
+use super::{extract_input_value, multipart_body, TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+/// Path of the first guest upload link in the widget
+fn extract_guest_upload_path(html: &str) -> Option<String> {
+    let start = html.find("/guest-upload/")?;
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].to_string())
+}
+
+/// Id of the first guest upload link in the widget
+fn extract_link_id(html: &str) -> Option<String> {
+    let start = html.find("/guest-uploads/")? + "/guest-uploads/".len();
+    let end = html[start..].find('/')?;
+    Some(html[start..start + end].to_string())
+}
+
+async fn create_link(app: &TestApp, links_path: &str, max_uploads: &str) -> String {
+    let token = app.csrf_token(links_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, links_path, ADMIN_TOKEN)
+        .form(&[
+            ("expires_in", "7"),
+            ("max_uploads", max_uploads),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn upload_token(app: &TestApp, path: &str) -> String {
+    let res = app.request(Method::GET, path).send().await.unwrap();
+    let body = res.text().await.unwrap();
+    extract_input_value(&body, "token").expect("token input not found")
+}
+
+/// Uploads a photo the way the upload page does
+async fn guest_upload(
+    app: &TestApp,
+    path: &str,
+    token: &str,
+    name: &str,
+    filename: &str,
+) -> reqwest::Response {
+    let (content_type, body) = multipart_body(filename, "image/jpeg", b"jpeg data");
+    app.request(Method::POST, path)
+        .query(&[("token", token), ("name", name)])
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_guest_upload() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("wedding", "Our Wedding");
+    app.fake
+        .add_file(&dir.id, "vows.jpg", "image/jpeg", b"vows");
+    let links_path = format!("/albums/{}/guest-uploads", dir.id);
+
+    let res = app
+        .htmx_request(Method::GET, &links_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("No active links."));
+
+    let body = create_link(&app, &links_path, "5").await;
+    assert!(body.contains("0 / 5"));
+    let path = extract_guest_upload_path(&body).expect("guest upload link not found");
+
+    // Guests can upload but see none of the album photos
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Our Wedding"));
+    assert!(body.contains("Your name"));
+    assert!(!body.contains("vows.jpg"));
+    assert!(!body.contains(&format!("/albums/{}", dir.id)));
+
+    let token = upload_token(&app, &path).await;
+    let res = guest_upload(&app, &path, &token, "Aunt May", "cake.jpg").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert!(res.headers().get("X-Next-Token").is_some());
+    let body = res.text().await.unwrap();
+    assert!(body.contains("cake.jpg"));
+    assert!(!body.contains("<a "));
+
+    let file_id = {
+        let state = app.fake.state();
+        assert_eq!(state.files.len(), 2);
+        state.files[1].id.clone()
+    };
+
+    // Members see who uploaded the photo
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/photos/{}", dir.id, file_id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("Uploaded by Aunt May"));
+
+    let res = app
+        .htmx_request(Method::GET, &links_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("1 / 5"));
+}
+
+#[tokio::test]
+async fn test_guest_upload_limits() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("wedding", "Our Wedding");
+    let links_path = format!("/albums/{}/guest-uploads", dir.id);
+    let body = create_link(&app, &links_path, "1").await;
+    let path = extract_guest_upload_path(&body).unwrap();
+
+    // A name is required
+    let token = upload_token(&app, &path).await;
+    let res = guest_upload(&app, &path, &token, "  ", "cake.jpg").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("Enter your name"));
+
+    let (content_type, body) = multipart_body("cake.jpg", "image/jpeg", b"jpeg data");
+    let res = app
+        .request(Method::POST, &path)
+        .query(&[("token", "invalid"), ("name", "Aunt May")])
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(app.fake.state().files.is_empty());
+
+    let res = guest_upload(&app, &path, &token, "Aunt May", "cake.jpg").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = guest_upload(&app, &path, &token, "Uncle Ben", "dance.jpg").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("This link has reached its upload limit."));
+    assert_eq!(app.fake.state().files.len(), 1);
+
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("This link has reached its upload limit."));
+
+    // Caps are bounded
+    let token = app.csrf_token(&links_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &links_path, ADMIN_TOKEN)
+        .form(&[("expires_in", "7"), ("max_uploads", "0"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_revoke_guest_upload_link() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("wedding", "Our Wedding");
+    let links_path = format!("/albums/{}/guest-uploads", dir.id);
+    let body = create_link(&app, &links_path, "5").await;
+    let path = extract_guest_upload_path(&body).unwrap();
+    let link_id = extract_link_id(&body).unwrap();
+
+    let token = app.csrf_token(&links_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("{}/{}/revoke", links_path, link_id),
+            ADMIN_TOKEN,
+        )
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("No active links."));
+
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.text().await.unwrap().contains("Upload link not found"));
+}
+
+#[tokio::test]
+async fn test_invalid_guest_upload_links() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("wedding", "Our Wedding");
+
+    let res = app
+        .request(Method::GET, "/guest-upload/not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Share links only grant viewing
+    let exp = (Utc::now() + Duration::days(1)).timestamp();
+    let share_token = create_share_token(&dir.id, exp, "secret").unwrap();
+    let res = app
+        .request(Method::GET, &format!("/guest-upload/{}", share_token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .htmx_request(
+            Method::GET,
+            &format!("/albums/{}/guest-uploads", dir.id),
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
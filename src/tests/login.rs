@@ -0,0 +1,231 @@
+use reqwest::{Method, StatusCode};
+
+use crate::Error;
+
+use super::{TestApp, ADMIN_PASSWORD, ADMIN_TOKEN, ADMIN_USERNAME};
+
+fn login_form<'a>(
+    username: &'a str,
+    password: &'a str,
+    captcha: &'a str,
+) -> Vec<(&'a str, &'a str)> {
+    vec![
+        ("username", username),
+        ("password", password),
+        ("g-recaptcha-response", captcha),
+    ]
+}
+
+#[tokio::test]
+async fn test_login_page() {
+    let app = TestApp::spawn().await;
+    let res = app.request(Method::GET, "/login").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("id=\"login-form\""));
+}
+
+#[tokio::test]
+async fn test_login_success() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .request(Method::POST, "/login")
+        .form(&login_form(ADMIN_USERNAME, ADMIN_PASSWORD, "valid-captcha"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("HX-Redirect").unwrap(), "/");
+    let cookie = res.headers().get("Set-Cookie").unwrap().to_str().unwrap();
+    assert!(cookie.starts_with(&format!("auth_token={}", ADMIN_TOKEN)));
+    assert!(cookie.contains("HttpOnly"));
+}
+
+#[tokio::test]
+async fn test_login_invalid_password() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .request(Method::POST, "/login")
+        .form(&login_form(
+            ADMIN_USERNAME,
+            "wrong-password",
+            "valid-captcha",
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert!(res.headers().get("Set-Cookie").is_none());
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Invalid username or password"));
+    assert!(!body.contains("<!DOCTYPE html>"));
+}
+
+#[tokio::test]
+async fn test_login_invalid_captcha() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .request(Method::POST, "/login")
+        .form(&login_form(ADMIN_USERNAME, ADMIN_PASSWORD, "robot"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Invalid captcha."));
+}
+
+#[tokio::test]
+async fn test_login_missing_captcha() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .request(Method::POST, "/login")
+        .form(&login_form(ADMIN_USERNAME, ADMIN_PASSWORD, ""))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Click the I&#x27;m not a robot checkbox."));
+}
+
+#[tokio::test]
+async fn test_private_page_requires_login() {
+    let app = TestApp::spawn().await;
+    let res = app.request(Method::GET, "/").send().await.unwrap();
+    assert!(res.status().is_redirection());
+    assert_eq!(res.headers().get("Location").unwrap(), "/login");
+
+    let res = app
+        .auth_request(Method::GET, "/", "expired-token")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_redirection());
+    assert_eq!(res.headers().get("Location").unwrap(), "/login");
+}
+
+#[tokio::test]
+async fn test_expired_token_htmx_request() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", "expired-token")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("message is-danger"));
+}
+
+#[tokio::test]
+async fn test_home_page() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains(ADMIN_USERNAME));
+}
+
+#[tokio::test]
+async fn test_logout() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .auth_request(Method::POST, "/logout", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("HX-Redirect").unwrap(), "/login");
+    let cookie = res.headers().get("Set-Cookie").unwrap().to_str().unwrap();
+    assert!(cookie.starts_with("auth_token=;"));
+}
+
+#[tokio::test]
+async fn test_actor_cache() {
+    let app = TestApp::spawn().await;
+    for _ in 0..3 {
+        let res = app
+            .auth_request(Method::GET, "/", ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    assert_eq!(app.fake.state().authz_calls, 1);
+
+    // Logging out forgets the actor
+    app.auth_request(Method::POST, "/logout", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    app.auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(app.fake.state().authz_calls, 2);
+}
+
+#[tokio::test]
+async fn test_actor_cache_upstream_unauthorized() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Revoke the token on files-rs while memo-rs still trusts it
+    app.fake.state().users[0].token = "revoked".to_string();
+
+    let res = app
+        .htmx_request(Method::GET, "/albums/listing", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(app.fake.state().authz_calls, 1);
+
+    let res = app
+        .auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_redirection());
+    assert_eq!(app.fake.state().authz_calls, 2);
+}
+
+#[tokio::test]
+async fn test_actor_cache_forgets_rejected_token() {
+    let app = TestApp::spawn().await;
+    let res = app
+        .auth_request(Method::GET, "/", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(app.state.actor_cache.get(ADMIN_TOKEN).is_some());
+
+    app.fake.state().users[0].token = "revoked".to_string();
+
+    // Whatever the page makes of the error, the actor is no longer trusted
+    let result = app.state.files.list_buckets(ADMIN_TOKEN).await;
+    assert!(matches!(result, Err(Error::LoginRequired(_))));
+    assert!(app.state.actor_cache.get(ADMIN_TOKEN).is_none());
+}
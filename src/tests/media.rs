@@ -0,0 +1,162 @@
+use reqwest::{Method, StatusCode};
+
+use super::{TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+#[tokio::test]
+async fn test_video_and_file_tiles() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg data");
+    let video = app
+        .fake
+        .add_file(&dir.id, "waves.mp4", "video/mp4", b"mp4 data");
+    let file = app
+        .fake
+        .add_file(&dir.id, "itinerary.pdf", "application/pdf", &[0u8; 2048]);
+
+    let path = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+
+    assert!(body.contains("beach.jpg"));
+    assert!(body.contains(&format!("/raw/{}/orig", video.id)));
+    assert!(body.contains("<video"));
+    assert!(body.contains(&format!("id=\"photo-{}\"", file.id)));
+    assert!(body.contains("download=\"itinerary.pdf\""));
+    assert!(body.contains("application/pdf"));
+    assert!(body.contains("2.0 KB"));
+
+    // Every file is shown, so the counter matches files-rs
+    assert_eq!(body.matches("class=\"photo-grid-item\"").count(), 3);
+    assert!(body.contains("<span class=\"current-count\">3</span>"));
+    assert!(body.contains("<span class=\"total-records\">3</span>"));
+}
+
+#[tokio::test]
+async fn test_video_and_file_details() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let video = app
+        .fake
+        .add_file(&dir.id, "waves.mp4", "video/mp4", b"mp4 data");
+    let file = app
+        .fake
+        .add_file(&dir.id, "itinerary.pdf", "application/pdf", b"pdf data");
+
+    let path = format!("/albums/{}/photos/{}", dir.id, video.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-detail-video"));
+    assert!(body.contains("video/mp4"));
+    assert!(!body.contains("Versions"));
+    assert!(!body.contains("Set as cover"));
+
+    let path = format!("/albums/{}/photos/{}", dir.id, file.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-detail-file"));
+    assert!(body.contains("Delete this file?"));
+
+    // Only images can be the album cover
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &format!("{}/cover", path), ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_cover_and_slideshow_skip_files() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "waves.mp4", "video/mp4", b"mp4 data");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg data");
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/cover", dir.id),
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FOUND);
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.ends_with(&format!("/raw/{}/thumb", photo.id)));
+
+    let path = format!("/albums/{}/slideshow/playlist", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let playlist: serde_json::Value = res.json().await.unwrap();
+    let slides = playlist["slides"].as_array().unwrap();
+    assert_eq!(slides.len(), 1);
+    assert_eq!(slides[0]["id"], photo.id.as_str());
+}
+
+#[tokio::test]
+async fn test_processing_files_keep_their_tiles() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg data");
+    let pending = app
+        .fake
+        .add_file(&dir.id, "sunset.jpg", "image/jpeg", b"jpeg data");
+    let broken = app
+        .fake
+        .add_file(&dir.id, "notes.txt", "text/plain", b"notes");
+    app.fake.state().processing = vec![pending.id.clone(), broken.id.clone()];
+
+    let path = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+
+    assert_eq!(body.matches("class=\"photo-grid-item\"").count(), 3);
+    assert!(body.contains(&format!("id=\"photo-{}\"", pending.id)));
+    assert!(body.contains("Processing"));
+    assert!(body.contains(&format!("id=\"photo-{}\"", broken.id)));
+    assert!(!body.contains("download=\"notes.txt\""));
+    assert!(body.contains("<span class=\"total-records\">3</span>"));
+
+    let path = format!("/albums/{}/photos/{}", dir.id, pending.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("sunset.jpg is still processing."));
+    assert!(!body.contains("Open original"));
+}
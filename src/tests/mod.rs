@@ -0,0 +1,340 @@
+mod albums;
+mod buckets;
+mod exif;
+mod fake_files;
+mod favorites;
+mod guest_uploads;
+mod login;
+mod media;
+mod moderation;
+mod photos;
+mod shares;
+mod tags;
+mod timeline;
+mod tus;
+mod upload_settings;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use reqwest::{Client, Method, RequestBuilder};
+use tokio::net::TcpListener;
+
+use crate::config::{AssetManifest, Config};
+use crate::run::{build_routes, AppState};
+use crate::services::{
+    ActorCache, AttemptLimiter, ExifStore, FilesClient, JsonStore, PhotoListingCache,
+    ServiceAccount, StagingStore, TimelineCache, UploadStore,
+};
+
+pub use fake_files::*;
+
+/// memo-rs served on a random port and wired to a fake files-rs backend
+pub struct TestApp {
+    pub url: String,
+    pub fake: FakeFiles,
+    pub client: Client,
+    /// Lets tests look at what memo-rs keeps locally
+    pub state: AppState,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        Self::spawn_with(|_| {}).await
+    }
+
+    /// Lets a test change the config before the app starts
+    pub async fn spawn_with(configure: impl FnOnce(&mut Config)) -> Self {
+        let fake = FakeFiles::new();
+        let api_url = fake.spawn().await;
+
+        let mut config = test_config(&api_url);
+        configure(&mut config);
+        let actor_cache = Arc::new(ActorCache::default());
+        let files = FilesClient::new(&config.api_url, actor_cache.clone()).unwrap();
+        let uploads = UploadStore::new(config.upload_dir.clone()).unwrap();
+        let photo_meta = JsonStore::open(config.data_dir.join("photos.json")).unwrap();
+        let photo_exif = ExifStore::open(config.data_dir.join("exif")).unwrap();
+        let album_meta = JsonStore::open(config.data_dir.join("albums.json")).unwrap();
+        let favorites = JsonStore::open(config.data_dir.join("favorites.json")).unwrap();
+        let photo_tags = JsonStore::open(config.data_dir.join("tags.json")).unwrap();
+        let staging = StagingStore::open(config.data_dir.join("staging")).unwrap();
+        let share_links = JsonStore::open(config.data_dir.join("shares.json")).unwrap();
+        let guest_upload_links =
+            JsonStore::open(config.data_dir.join("guest_uploads.json")).unwrap();
+        let share_account =
+            ServiceAccount::new(config.share_username.clone(), config.share_password.clone());
+        let guest_upload_account = ServiceAccount::new(
+            config.guest_upload_username.clone(),
+            config.guest_upload_password.clone(),
+        );
+        let state = AppState {
+            config: Arc::new(config),
+            files: Arc::new(files),
+            actor_cache: actor_cache.clone(),
+            timeline: Arc::new(TimelineCache::default()),
+            photo_listings: Arc::new(PhotoListingCache::default()),
+            uploads: Arc::new(uploads),
+            photo_meta: Arc::new(photo_meta),
+            photo_exif: Arc::new(photo_exif),
+            album_meta: Arc::new(album_meta),
+            favorites: Arc::new(favorites),
+            photo_tags: Arc::new(photo_tags),
+            staging: Arc::new(staging),
+            share_links: Arc::new(share_links),
+            share_account: Arc::new(share_account),
+            guest_upload_links: Arc::new(guest_upload_links),
+            guest_upload_account: Arc::new(guest_upload_account),
+            unlock_attempts: Arc::new(AttemptLimiter::default()),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = build_routes(state.clone());
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                routes.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+
+        Self {
+            url,
+            fake,
+            client,
+            state,
+        }
+    }
+
+    /// Builds an anonymous request
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client.request(method, format!("{}{}", self.url, path))
+    }
+
+    /// Builds a request carrying the given auth token cookie
+    pub fn auth_request(&self, method: Method, path: &str, token: &str) -> RequestBuilder {
+        self.request(method, path)
+            .header("Cookie", format!("auth_token={}", token))
+    }
+
+    /// Builds an authenticated htmx request
+    pub fn htmx_request(&self, method: Method, path: &str, token: &str) -> RequestBuilder {
+        self.auth_request(method, path, token)
+            .header("HX-Request", "true")
+    }
+
+    /// Loads a page or widget and extracts its CSRF token
+    pub async fn csrf_token(&self, path: &str, token: &str) -> String {
+        let res = self.htmx_request(Method::GET, path, token).send().await;
+        let body = res.unwrap().text().await.unwrap();
+        extract_input_value(&body, "token").expect("token input not found")
+    }
+}
+
+fn test_config(api_url: &str) -> Config {
+    let frontend_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("frontend");
+
+    Config {
+        port: 0,
+        ssl: false,
+        frontend_dir,
+        captcha_site_key: "key".to_string(),
+        captcha_site_secret: "secret".to_string(),
+        captcha_verify_url: format!("{}/recaptcha/api/siteverify", api_url),
+        api_url: api_url.to_string(),
+        jwt_secret: "secret".to_string(),
+        ga_tag_id: None,
+        upload_max_size: 10_000_000,
+        upload_dir: std::env::temp_dir().join(format!("memo-rs-test-{}", uuid::Uuid::new_v4())),
+        upload_normalize: false,
+        data_dir: std::env::temp_dir().join(format!("memo-rs-data-{}", uuid::Uuid::new_v4())),
+        share_username: Some("viewer".to_string()),
+        share_password: Some("password123".to_string()),
+        guest_upload_username: Some(ADMIN_USERNAME.to_string()),
+        guest_upload_password: Some(ADMIN_PASSWORD.to_string()),
+        trusted_proxies: Vec::new(),
+        assets: AssetManifest {
+            main_js: "/assets/bundles/js/main-test.js".to_string(),
+            vendor_js: "/assets/bundles/js/vendor-test.js".to_string(),
+            gallery_js: "/assets/bundles/js/gallery-test.js".to_string(),
+            upload_js: "/assets/bundles/js/upload-test.js".to_string(),
+            slideshow_js: "/assets/bundles/js/slideshow-test.js".to_string(),
+            main_css: "/assets/bundles/css/main-test.css".to_string(),
+            gallery_css: "/assets/bundles/css/gallery-test.css".to_string(),
+        },
+    }
+}
+
+/// Finds the value of the first input with the given name
+pub fn extract_input_value(html: &str, name: &str) -> Option<String> {
+    let needle = format!("name=\"{}\"", name);
+    let start = html.find(&needle)?;
+    let rest = &html[start..];
+    let value_start = rest.find("value=\"")? + "value=\"".len();
+    let value_end = rest[value_start..].find('"')?;
+    Some(rest[value_start..value_start + value_end].to_string())
+}
+
+/// Builds a multipart/form-data body holding a single file field
+pub fn multipart_body(filename: &str, content_type: &str, data: &[u8]) -> (String, Vec<u8>) {
+    let boundary = "memo-rs-test-boundary";
+    let mut body = Vec::new();
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(
+        format!(
+            "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
+            filename
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+/// IFD entry values used by `exif_jpeg`
+enum ExifValue {
+    Ascii(&'static str),
+    Short(u16),
+    Long(u32),
+    Rational(Vec<(u32, u32)>),
+}
+
+/// A tiny JPEG holding only an EXIF segment: a Canon EOS R6 photo taken on
+/// 2024-06-15 12:30:00 at 14°35' N, 120°58'30" W
+pub fn exif_jpeg() -> Vec<u8> {
+    let mut jpeg = vec![0xFF, 0xD8];
+    jpeg.extend(exif_segment(None));
+    jpeg.extend([0xFF, 0xD9]);
+    jpeg
+}
+
+/// A real `width` x `height` JPEG with the EXIF of `exif_jpeg` and the given
+/// orientation
+pub fn oriented_jpeg(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+    let pixels = image::RgbImage::from_fn(width, height, |x, _| {
+        if x < width / 2 {
+            image::Rgb([255, 0, 0])
+        } else {
+            image::Rgb([0, 0, 255])
+        }
+    });
+    let mut encoded: Vec<u8> = Vec::new();
+    pixels
+        .write_to(
+            &mut std::io::Cursor::new(&mut encoded),
+            image::ImageFormat::Jpeg,
+        )
+        .unwrap();
+
+    let mut jpeg = encoded[..2].to_vec();
+    jpeg.extend(exif_segment(Some(orientation)));
+    jpeg.extend(&encoded[2..]);
+    jpeg
+}
+
+/// APP1 segment of `exif_jpeg`, with a body serial number to strip
+fn exif_segment(orientation: Option<u16>) -> Vec<u8> {
+    let primary = |exif_at: u32, gps_at: u32| {
+        let mut entries = vec![
+            (0x010F, ExifValue::Ascii("Canon")),
+            (0x0110, ExifValue::Ascii("Canon EOS R6")),
+        ];
+        if let Some(orientation) = orientation {
+            entries.push((0x0112, ExifValue::Short(orientation)));
+        }
+        entries.push((0x8769, ExifValue::Long(exif_at)));
+        entries.push((0x8825, ExifValue::Long(gps_at)));
+        entries
+    };
+    let exif = vec![
+        (0x829A, ExifValue::Rational(vec![(1, 250)])),
+        (0x829D, ExifValue::Rational(vec![(4, 1)])),
+        (0x8827, ExifValue::Short(200)),
+        (0x9003, ExifValue::Ascii("2024:06:15 12:30:00")),
+        (0x920A, ExifValue::Rational(vec![(50, 1)])),
+        (0xA431, ExifValue::Ascii("012345678901")),
+        (0xA434, ExifValue::Ascii("RF24-105mm F4 L IS USM")),
+    ];
+    let gps = vec![
+        (0x0001, ExifValue::Ascii("N")),
+        (0x0002, ExifValue::Rational(vec![(14, 1), (35, 1), (0, 1)])),
+        (0x0003, ExifValue::Ascii("W")),
+        (
+            0x0004,
+            ExifValue::Rational(vec![(120, 1), (58, 1), (30, 1)]),
+        ),
+    ];
+
+    // Pointers fit in the entries, so the sizes are known up front
+    let primary_len = exif_ifd(&primary(0, 0), 0).len() as u32;
+    let exif_at = 8 + primary_len;
+    let gps_at = exif_at + exif_ifd(&exif, 0).len() as u32;
+
+    let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+    tiff.extend(exif_ifd(&primary(exif_at, gps_at), 8));
+    tiff.extend(exif_ifd(&exif, exif_at));
+    tiff.extend(exif_ifd(&gps, gps_at));
+
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend(((tiff.len() + 8) as u16).to_be_bytes());
+    segment.extend(b"Exif\0\0");
+    segment.extend(tiff);
+    segment
+}
+
+/// Little endian IFD placed at `offset` in the TIFF, values follow the entries
+fn exif_ifd(entries: &[(u16, ExifValue)], offset: u32) -> Vec<u8> {
+    let data_at = offset + 2 + 12 * entries.len() as u32 + 4;
+    let mut ifd: Vec<u8> = (entries.len() as u16).to_le_bytes().to_vec();
+    let mut data: Vec<u8> = Vec::new();
+
+    for (tag, value) in entries {
+        let (kind, count, bytes): (u16, u32, Vec<u8>) = match value {
+            ExifValue::Ascii(text) => {
+                let mut bytes = text.as_bytes().to_vec();
+                bytes.push(0);
+                (2, bytes.len() as u32, bytes)
+            }
+            ExifValue::Short(value) => (3, 1, value.to_le_bytes().to_vec()),
+            ExifValue::Long(value) => (4, 1, value.to_le_bytes().to_vec()),
+            ExifValue::Rational(values) => {
+                let bytes = values
+                    .iter()
+                    .flat_map(|(num, denom)| [num.to_le_bytes(), denom.to_le_bytes()].concat())
+                    .collect();
+                (5, values.len() as u32, bytes)
+            }
+        };
+
+        ifd.extend(tag.to_le_bytes());
+        ifd.extend(kind.to_le_bytes());
+        ifd.extend(count.to_le_bytes());
+        if bytes.len() <= 4 {
+            let mut inline = bytes;
+            inline.resize(4, 0);
+            ifd.extend(inline);
+        } else {
+            ifd.extend((data_at + data.len() as u32).to_le_bytes());
+            data.extend(bytes);
+            // Values start on a word boundary
+            if data.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+    }
+
+    ifd.extend(0u32.to_le_bytes());
+    ifd.extend(data);
+    ifd
+}
@@ -0,0 +1,359 @@
+use reqwest::{Method, StatusCode};
+
+use super::{
+    extract_input_value, multipart_body, TestApp, ADMIN_TOKEN, CONTRIBUTOR_TOKEN, VIEWER_TOKEN,
+};
+
+/// Ids of the staged uploads listed on the review page
+fn extract_staged_ids(html: &str) -> Vec<String> {
+    html.match_indices("id=\"staged-")
+        .filter_map(|(start, pattern)| {
+            let start = start + pattern.len();
+            let end = html[start..].find('"')?;
+            Some(html[start..start + end].to_string())
+        })
+        .collect()
+}
+
+/// Id of the staged upload listed with the given filename
+fn extract_staged_id(html: &str, filename: &str) -> Option<String> {
+    let end = html.find(filename)?;
+    let start = html[..end].rfind("id=\"staged-")? + "id=\"staged-".len();
+    let len = html[start..].find('"')?;
+    Some(html[start..start + len].to_string())
+}
+
+async fn set_moderated(app: &TestApp, album_id: &str, moderated: bool) -> String {
+    let path = format!("/albums/{}/moderation/settings", album_id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let mut form = vec![("token", token.as_str())];
+    if moderated {
+        form.push(("moderated", "1"));
+    }
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&form)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn upload(app: &TestApp, album_id: &str, token: &str, filename: &str) -> reqwest::Response {
+    upload_file(app, album_id, token, filename, "image/jpeg", b"jpeg data").await
+}
+
+async fn upload_file(
+    app: &TestApp,
+    album_id: &str,
+    token: &str,
+    filename: &str,
+    content_type: &str,
+    data: &[u8],
+) -> reqwest::Response {
+    let path = format!("/albums/{}/upload", album_id);
+    let csrf_token = app.csrf_token(&path, token).await;
+
+    let (content_type, body) = multipart_body(filename, content_type, data);
+    app.auth_request(Method::POST, &path, token)
+        .query(&[("token", csrf_token)])
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn review_page(app: &TestApp, album_id: &str) -> String {
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/moderation", album_id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn moderate(
+    app: &TestApp,
+    album_id: &str,
+    token: &str,
+    action: &str,
+    staged_ids: &[String],
+) -> reqwest::Response {
+    let mut form = vec![("action", action.to_string()), ("token", token.to_string())];
+    for id in staged_ids {
+        form.push(("staged_id", id.clone()));
+    }
+
+    app.htmx_request(
+        Method::POST,
+        &format!("/albums/{}/moderation", album_id),
+        ADMIN_TOKEN,
+    )
+    .form(&form)
+    .send()
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_moderated_uploads() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let body = set_moderated(&app, &dir.id, true).await;
+    assert!(body.contains("checked"));
+
+    // Contributors without edit rights wait for review
+    let res = upload(&app, &dir.id, CONTRIBUTOR_TOKEN, "beach.jpg").await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert!(res.headers().get("X-Next-Token").is_some());
+    assert!(res.text().await.unwrap().contains("Waiting for review"));
+    assert!(app.fake.state().files.is_empty());
+
+    // Members who can edit photos skip the queue
+    let res = upload(&app, &dir.id, ADMIN_TOKEN, "sunset.jpg").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(app.fake.state().files.len(), 1);
+
+    let res = upload(&app, &dir.id, CONTRIBUTOR_TOKEN, "spam.jpg").await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    let body = review_page(&app, &dir.id).await;
+    assert!(body.contains("beach.jpg"));
+    assert!(body.contains("spam.jpg"));
+    assert!(body.contains("contributor"));
+    assert!(!body.contains("sunset.jpg"));
+    assert_eq!(extract_staged_ids(&body).len(), 2);
+    let staged_ids = [
+        extract_staged_id(&body, "beach.jpg").unwrap(),
+        extract_staged_id(&body, "spam.jpg").unwrap(),
+    ];
+    let token = extract_input_value(&body, "token").unwrap();
+
+    // Reviewers can look at the file before deciding
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/moderation/{}/file", dir.id, staged_ids[0]),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["Content-Type"], "image/jpeg");
+    assert_eq!(res.bytes().await.unwrap().as_ref(), b"jpeg data");
+
+    let res = moderate(&app, &dir.id, &token, "approve", &staged_ids[..1]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Approved 1 of 1 uploads."));
+    assert!(body.contains(&format!("staged-{}", staged_ids[0])));
+    {
+        let state = app.fake.state();
+        assert_eq!(state.files.len(), 2);
+        assert_eq!(state.files[1].name, "beach.jpg");
+        assert_eq!(state.files[1].data, b"jpeg data");
+    }
+
+    let res = moderate(&app, &dir.id, &token, "reject", &staged_ids[1..]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Rejected 1 of 1 uploads."));
+    assert_eq!(app.fake.state().files.len(), 2);
+
+    let body = review_page(&app, &dir.id).await;
+    assert!(extract_staged_ids(&body).is_empty());
+
+    // Already handled uploads are reported, not approved twice
+    let res = moderate(&app, &dir.id, &token, "approve", &staged_ids[..1]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Approved 0 of 1 uploads."));
+    assert_eq!(app.fake.state().files.len(), 2);
+
+    // Turning moderation off lets uploads through again
+    set_moderated(&app, &dir.id, false).await;
+    let res = upload(&app, &dir.id, CONTRIBUTOR_TOKEN, "pier.jpg").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(app.fake.state().files.len(), 3);
+}
+
+#[tokio::test]
+async fn test_staged_svg_is_downloaded() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_moderated(&app, &dir.id, true).await;
+
+    let svg = b"<svg xmlns=\"http://www.w3.org/2000/svg\"><script>alert(1)</script></svg>";
+    let res = upload_file(
+        &app,
+        &dir.id,
+        CONTRIBUTOR_TOKEN,
+        "drawing.svg",
+        "image/svg+xml",
+        svg,
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    let body = review_page(&app, &dir.id).await;
+    let staged_id = extract_staged_id(&body, "drawing.svg").unwrap();
+    let file_path = format!("/albums/{}/moderation/{}/file", dir.id, staged_id);
+    assert!(!body.contains("alt=\"drawing.svg\""));
+
+    let res = app
+        .auth_request(Method::GET, &file_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["Content-Type"], "application/octet-stream");
+    assert_eq!(
+        res.headers()["Content-Disposition"],
+        "attachment; filename=\"drawing.svg\""
+    );
+}
+
+#[tokio::test]
+async fn test_moderation_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_moderated(&app, &dir.id, true).await;
+    let res = upload(&app, &dir.id, CONTRIBUTOR_TOKEN, "beach.jpg").await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let staged_id = extract_staged_ids(&review_page(&app, &dir.id).await)[0].clone();
+
+    for token in [VIEWER_TOKEN, CONTRIBUTOR_TOKEN] {
+        let res = app
+            .auth_request(
+                Method::GET,
+                &format!("/albums/{}/moderation", dir.id),
+                token,
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = app
+            .auth_request(
+                Method::GET,
+                &format!("/albums/{}/moderation/{}/file", dir.id, staged_id),
+                token,
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = app
+            .htmx_request(
+                Method::POST,
+                &format!("/albums/{}/moderation", dir.id),
+                token,
+            )
+            .form(&[("action", "approve"), ("staged_id", staged_id.as_str())])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = app
+            .htmx_request(
+                Method::POST,
+                &format!("/albums/{}/moderation/settings", dir.id),
+                token,
+            )
+            .form(&[("moderated", "1")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    // Staged uploads are only reachable from their own album
+    let other = app.fake.add_dir("winter", "Winter Trip");
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/moderation/{}/file", other.id, staged_id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(app.fake.state().files.is_empty());
+}
+
+#[tokio::test]
+async fn test_moderated_guest_upload() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("wedding", "Our Wedding");
+    set_moderated(&app, &dir.id, true).await;
+
+    let links_path = format!("/albums/{}/guest-uploads", dir.id);
+    let token = app.csrf_token(&links_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &links_path, ADMIN_TOKEN)
+        .form(&[("expires_in", "7"), ("max_uploads", "5"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    let start = body.find("/guest-upload/").unwrap();
+    let end = body[start..].find('"').unwrap();
+    let path = body[start..start + end].to_string();
+
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("reviewed before they show up"));
+    let token = extract_input_value(&body, "token").unwrap();
+
+    let (content_type, body) = multipart_body("cake.jpg", "image/jpeg", b"jpeg data");
+    let res = app
+        .request(Method::POST, &path)
+        .query(&[("token", token.as_str()), ("name", "Aunt May")])
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert!(app.fake.state().files.is_empty());
+
+    let body = review_page(&app, &dir.id).await;
+    assert!(body.contains("Aunt May"));
+    let staged_ids = extract_staged_ids(&body);
+    let token = extract_input_value(&body, "token").unwrap();
+    let res = moderate(&app, &dir.id, &token, "approve", &staged_ids).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The guest name is kept once the photo is in the album
+    let file_id = app.fake.state().files[0].id.clone();
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/photos/{}", dir.id, file_id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("Uploaded by Aunt May"));
+}
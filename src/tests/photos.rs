@@ -0,0 +1,1022 @@
+use reqwest::{Method, StatusCode};
+
+use super::{multipart_body, TestApp, ADMIN_TOKEN, CONTRIBUTOR_TOKEN, VIEWER_TOKEN};
+
+#[tokio::test]
+async fn test_photos_page() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .auth_request(Method::GET, &format!("/albums/{}", dir.id), ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains(&format!("/albums/{}/photo-grid?page=1", dir.id)));
+    assert!(body.contains("Add Photos"));
+}
+
+#[tokio::test]
+async fn test_photo_grid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    for i in 0..55 {
+        let name = format!("photo-{}.jpg", i);
+        app.fake.add_file(&dir.id, &name, "image/jpeg", b"jpeg");
+    }
+
+    let path = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("photo-0.jpg"));
+    assert!(!body.contains("photo-50.jpg"));
+    assert!(body.contains(&format!("/albums/{}/photo-grid?page=2", dir.id)));
+    assert!(body.contains("id=\"photos-count-w\" hx-swap-oob=\"true\""));
+
+    let path = format!("/albums/{}/photo-grid?page=2", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-54.jpg"));
+    assert!(!body.contains("photo-grid?page=3"));
+}
+
+#[tokio::test]
+async fn test_photo_grid_reuses_album_listing() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    for i in 0..30 {
+        app.fake
+            .add_file(&dir.id, &format!("beach-{:02}.jpg", i), "image/jpeg", b"a");
+    }
+
+    let grid = |page: u32| {
+        format!(
+            "/albums/{}/photo-grid?page={}&per_page=25&sort=-name",
+            dir.id, page
+        )
+    };
+    for page in [1, 2] {
+        let res = app
+            .htmx_request(Method::GET, &grid(page), ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    assert_eq!(app.fake.state().list_files_calls, 1);
+
+    // A new upload changes the album count and the listing is loaded again
+    app.fake
+        .add_file(&dir.id, "beach-99.jpg", "image/jpeg", b"a");
+    let res = app
+        .htmx_request(Method::GET, &grid(1), ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("beach-99.jpg"));
+    assert_eq!(app.fake.state().list_files_calls, 2);
+}
+
+#[tokio::test]
+async fn test_photo_grid_per_page() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    for i in 0..30 {
+        let name = format!("photo-{}.jpg", i);
+        app.fake.add_file(&dir.id, &name, "image/jpeg", b"jpeg");
+    }
+
+    let path = format!("/albums/{}/photo-grid?page=1&per_page=25", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-24.jpg"));
+    assert!(!body.contains("photo-25.jpg"));
+    assert!(body.contains(&format!(
+        "/albums/{}/photo-grid?page=2&amp;per_page=25",
+        dir.id
+    )));
+
+    // Unsupported sizes fall back to the default
+    let path = format!("/albums/{}/photo-grid?page=1&per_page=3", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-29.jpg"));
+}
+
+#[tokio::test]
+async fn test_photo_grid_sort_and_keyword() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"123");
+    app.fake
+        .add_file(&dir.id, "beach-sunset.jpg", "image/jpeg", b"12345");
+    app.fake
+        .add_file(&dir.id, "mountain.jpg", "image/jpeg", b"1");
+
+    let path = format!("/albums/{}/photo-grid?page=1&sort=-size", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    let sunset = body.find("beach-sunset.jpg").unwrap();
+    let beach = body.find("beach.jpg").unwrap();
+    let mountain = body.find("mountain.jpg").unwrap();
+    assert!(sunset < beach && beach < mountain);
+
+    let path = format!(
+        "/albums/{}/photo-grid?page=1&per_page=25&sort=name&keyword=BEACH",
+        dir.id
+    );
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.find("beach-sunset.jpg").unwrap() < body.find("beach.jpg").unwrap());
+    assert!(!body.contains("mountain.jpg"));
+    assert!(body.contains(r#"<span class="total-records">2</span>"#));
+}
+
+#[tokio::test]
+async fn test_photo_grid_keyword_pagination() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    for i in 0..30 {
+        app.fake
+            .add_file(&dir.id, &format!("beach-{:02}.jpg", i), "image/jpeg", b"a");
+        app.fake
+            .add_file(&dir.id, &format!("city-{:02}.jpg", i), "image/jpeg", b"a");
+    }
+
+    let path = format!(
+        "/albums/{}/photo-grid?page=1&per_page=25&keyword=beach",
+        dir.id
+    );
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("city-"));
+    assert!(body.contains(&format!(
+        "/albums/{}/photo-grid?page=2&amp;per_page=25&amp;keyword=beach",
+        dir.id
+    )));
+
+    let path = format!(
+        "/albums/{}/photo-grid?page=2&per_page=25&keyword=beach",
+        dir.id
+    );
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("beach-29.jpg"));
+    assert!(!body.contains("beach-24.jpg"));
+    assert!(!body.contains("photo-grid?page=3"));
+}
+
+#[tokio::test]
+async fn test_photos_page_keeps_grid_options() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let path = format!("/albums/{}?per_page=100&sort=-created_at", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains(&format!(
+        "/albums/{}/photo-grid?page=1&amp;per_page=100&amp;sort=-created_at",
+        dir.id
+    )));
+    assert!(body.contains(r#"<option value="100" selected>"#));
+    assert!(body.contains(r#"<option value="-created_at" selected>"#));
+}
+
+#[tokio::test]
+async fn test_photo_grid_after_delete_and_upload() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let beach = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let grid = format!("/albums/{}/photo-grid?page=1&sort=-name", dir.id);
+    let get_grid = || async {
+        let res = app
+            .htmx_request(Method::GET, &grid, ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        res.text().await.unwrap()
+    };
+    assert!(get_grid().await.contains("beach.jpg"));
+
+    // A delete then an upload leave the album count as it was
+    let path = format!("/albums/{}/photos/{}/delete", dir.id, beach.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let path = format!("/albums/{}/upload", dir.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let token = super::extract_input_value(&res.text().await.unwrap(), "token").unwrap();
+    let (content_type, body) = multipart_body("sunset.jpg", "image/jpeg", b"jpeg data");
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("{}?token={}", path, token),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let body = get_grid().await;
+    assert!(!body.contains("beach.jpg"));
+    assert!(body.contains("sunset.jpg"));
+}
+
+#[tokio::test]
+async fn test_upload_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/upload", dir.id);
+
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let token = super::extract_input_value(&res.text().await.unwrap(), "token").unwrap();
+
+    let (content_type, body) = multipart_body("beach.jpg", "image/jpeg", b"jpeg data");
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("{}?token={}", path, token),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert!(res.headers().get("X-Next-Token").is_some());
+    let body = res.text().await.unwrap();
+    assert!(body.contains("photo-grid-item"));
+    assert!(body.contains("beach.jpg"));
+
+    let state = app.fake.state();
+    assert_eq!(state.files.len(), 1);
+    assert_eq!(state.files[0].data, b"jpeg data");
+}
+
+#[tokio::test]
+async fn test_upload_large_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/upload", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    // Larger than the old 8 MB cap but within the configured limit
+    let data = vec![7u8; 9_000_000];
+    let (content_type, body) = multipart_body("raw.jpg", "image/jpeg", &data);
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("{}?token={}", path, token),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(app.fake.state().files[0].data.len(), data.len());
+}
+
+#[tokio::test]
+async fn test_upload_photo_too_large() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/upload", dir.id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let data = vec![7u8; 11_000_000];
+    let (content_type, body) = multipart_body("raw.jpg", "image/jpeg", &data);
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("{}?token={}", path, token),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(app.fake.state().files.is_empty());
+}
+
+#[tokio::test]
+async fn test_upload_photo_invalid_token() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let (content_type, body) = multipart_body("beach.jpg", "image/jpeg", b"jpeg data");
+    let res = app
+        .auth_request(
+            Method::POST,
+            &format!("/albums/{}/upload", dir.id),
+            ADMIN_TOKEN,
+        )
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Stale form data"));
+    assert!(app.fake.state().files.is_empty());
+}
+
+#[tokio::test]
+async fn test_upload_page_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .auth_request(
+            Method::GET,
+            &format!("/albums/{}/upload", dir.id),
+            VIEWER_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("You do not have permission to upload photos."));
+}
+
+#[tokio::test]
+async fn test_photo_controls_follow_policies() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let grid_path = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let controls_path = format!("/albums/{}/photos/{}/delete-controls", dir.id, file.id);
+    let delete_path = format!("/albums/{}/photos/{}/delete\"", dir.id, file.id);
+
+    for path in [&grid_path, &controls_path] {
+        let res = app
+            .htmx_request(Method::GET, path, ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        let body = res.text().await.unwrap();
+        assert!(body.contains("Edit tags"));
+        assert!(body.contains("Move or copy"));
+        assert!(body.contains(&delete_path));
+
+        // Contributors can only copy photos into other albums
+        let res = app
+            .htmx_request(Method::GET, path, CONTRIBUTOR_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.text().await.unwrap();
+        assert!(!body.contains("Edit tags"));
+        assert!(body.contains("Move or copy"));
+        assert!(!body.contains(&delete_path));
+    }
+
+    let res = app
+        .htmx_request(Method::GET, &grid_path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("photo-item-controls"));
+
+    let res = app
+        .htmx_request(Method::GET, &controls_path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_delete_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let path = format!("/albums/{}/photos/{}/delete", dir.id, file.id);
+
+    let res = app
+        .htmx_request(
+            Method::GET,
+            &format!("/albums/{}/photos/{}/delete-controls", dir.id, file.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains(&path));
+
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        res.headers().get("HX-Trigger").unwrap(),
+        "PhotoDeletedEvent"
+    );
+    assert!(app.fake.state().files.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_photo_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let path = format!("/albums/{}/photos/{}/delete", dir.id, file.id);
+
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("You do not have permission to delete photos."));
+    assert_eq!(app.fake.state().files.len(), 1);
+}
+
+#[tokio::test]
+async fn test_photo_not_found() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/photos/missing/delete", dir.id);
+
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("Photo not found"));
+}
+
+#[tokio::test]
+async fn test_photo_page() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let first = app
+        .fake
+        .add_file(&dir.id, "first.jpg", "image/jpeg", b"first");
+    let second = app
+        .fake
+        .add_file(&dir.id, "second.jpg", "image/jpeg", &[0; 2048]);
+    let third = app
+        .fake
+        .add_file(&dir.id, "third.jpg", "image/jpeg", b"third");
+
+    let path = format!("/albums/{}/photos/{}", dir.id, second.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("second.jpg"));
+    assert!(body.contains("image/jpeg"));
+    assert!(body.contains("2.0 KB"));
+    assert!(body.contains("1600 &times; 1600"));
+    assert!(body.contains("800 &times; 800"));
+    assert!(body.contains("200 &times; 200"));
+    assert!(body.contains("2 of 3"));
+    assert!(body.contains(&format!("/photos/{}?page=1", first.id)));
+    assert!(body.contains(&format!("/photos/{}?page=1", third.id)));
+    assert!(body.contains("Delete"));
+}
+
+#[tokio::test]
+async fn test_photo_page_follows_grid_order() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let first = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    let second = app.fake.add_file(&dir.id, "b.jpg", "image/jpeg", b"b");
+
+    let path = format!("/albums/{}/photos/{}?sort=-name", dir.id, first.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("2 of 2"));
+    assert!(body.contains(&format!(
+        "/photos/{}?page=1&amp;per_page=50&amp;sort=-name",
+        second.id
+    )));
+
+    // Moving on to the next photo does not list the album again
+    let path = format!("/albums/{}/photos/{}?sort=-name", dir.id, second.id);
+    let res = app
+        .auth_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("1 of 2"));
+    assert_eq!(app.fake.state().list_files_calls, 1);
+}
+
+#[tokio::test]
+async fn test_photo_page_viewer() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app.fake.add_file(&dir.id, "only.jpg", "image/jpeg", b"a");
+
+    let path = format!("/albums/{}/photos/{}", dir.id, photo.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("only.jpg"));
+    assert!(body.contains("1 of 1"));
+    assert!(!body.contains("/delete\""));
+}
+
+#[tokio::test]
+async fn test_edit_photo() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "IMG_0001.jpg", "image/jpeg", b"jpeg");
+    let path = format!("/albums/{}/photos/{}/edit", dir.id, file.id);
+
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[
+            ("name", "Sunset"),
+            ("caption", "  Last day of the trip  "),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Sunset"));
+    assert!(body.contains("Last day of the trip"));
+    assert!(body.contains(r#"id="breadcrumb-photo-name" hx-swap-oob="true""#));
+    assert_eq!(app.fake.state().files[0].name, "Sunset");
+
+    // Caption shows up on the grid and detail page
+    let grid = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &grid, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("Last day of the trip"));
+
+    // Clearing the caption keeps the name
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("name", "Sunset"), ("caption", ""), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Sunset"));
+    assert!(!body.contains("Last day of the trip"));
+}
+
+#[tokio::test]
+async fn test_edit_photo_invalid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "beach.jpg", "image/jpeg", b"a");
+    let path = format!("/albums/{}/photos/{}/edit", dir.id, file.id);
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("name", "Sunset"), ("caption", ""), ("token", "bad")])
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::OK);
+    assert_eq!(app.fake.state().files[0].name, "beach.jpg");
+
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("name", "   "), ("caption", ""), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("Name must be"));
+    assert_eq!(app.fake.state().files[0].name, "beach.jpg");
+}
+
+#[tokio::test]
+async fn test_edit_photo_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "beach.jpg", "image/jpeg", b"a");
+
+    let path = format!("/albums/{}/photos/{}", dir.id, file.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(!res.text().await.unwrap().contains("/edit\""));
+
+    let path = format!("/albums/{}/photos/{}/edit", dir.id, file.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("You do not have permission to edit photos."));
+}
+
+#[tokio::test]
+async fn test_copy_photos() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let target = app.fake.add_dir("best", "Best Of");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let edit_path = format!("/albums/{}/photos/{}/edit", dir.id, file.id);
+    let token = app.csrf_token(&edit_path, ADMIN_TOKEN).await;
+    app.htmx_request(Method::POST, &edit_path, ADMIN_TOKEN)
+        .form(&[
+            ("name", "Sunset"),
+            ("caption", "Golden hour"),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+
+    let form_path = format!("/albums/{}/transfer?photo_id={}", dir.id, file.id);
+    let res = app
+        .htmx_request(Method::GET, &form_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Best Of"));
+    assert!(body.contains(r#"value="move""#));
+
+    let token = app.csrf_token(&form_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("/albums/{}/transfer", dir.id),
+            ADMIN_TOKEN,
+        )
+        .form(&[
+            ("photo_id", file.id.as_str()),
+            ("target_album_id", &target.id),
+            ("mode", "copy"),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Copied 1 of 1 photos"));
+    assert!(!body.contains("hx-swap-oob=\"delete\""));
+
+    let files = app.fake.state().files.clone();
+    assert_eq!(files.len(), 2);
+    let copy = files.iter().find(|f| f.dir_id == target.id).unwrap();
+    assert_eq!(copy.name, "Sunset");
+    assert_eq!(copy.data, b"jpeg");
+
+    // Caption comes along with the copy
+    let grid = format!("/albums/{}/photo-grid?page=1", target.id);
+    let res = app
+        .htmx_request(Method::GET, &grid, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.text().await.unwrap().contains("Golden hour"));
+}
+
+#[tokio::test]
+async fn test_move_photos_partial_failure() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let target = app.fake.add_dir("best", "Best Of");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let form_path = format!("/albums/{}/transfer?photo_id={}", dir.id, file.id);
+    let token = app.csrf_token(&form_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("/albums/{}/transfer", dir.id),
+            ADMIN_TOKEN,
+        )
+        .form(&[
+            ("photo_id", file.id.as_str()),
+            ("photo_id", "missing"),
+            ("target_album_id", &target.id),
+            ("mode", "move"),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Moved 1 of 2 photos"));
+    assert!(body.contains("<strong>missing</strong>"));
+    assert!(body.contains(&format!(
+        r#"<div id="photo-{}" hx-swap-oob="delete"></div>"#,
+        file.id
+    )));
+
+    let files = app.fake.state().files.clone();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].dir_id, target.id);
+    assert_eq!(files[0].name, "beach.jpg");
+}
+
+#[tokio::test]
+async fn test_transfer_photos_invalid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let target = app.fake.add_dir("best", "Best Of");
+    let file = app.fake.add_file(&dir.id, "beach.jpg", "image/jpeg", b"a");
+    let path = format!("/albums/{}/transfer", dir.id);
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[
+            ("photo_id", file.id.as_str()),
+            ("target_album_id", &target.id),
+            ("token", "bad"),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::OK);
+
+    let form_path = format!("{}?photo_id={}", path, file.id);
+    let token = app.csrf_token(&form_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[
+            ("photo_id", file.id.as_str()),
+            ("target_album_id", &dir.id),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("Select another album."));
+    assert_eq!(app.fake.state().files.len(), 1);
+}
+
+#[tokio::test]
+async fn test_transfer_photos_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "beach.jpg", "image/jpeg", b"a");
+
+    let path = format!("/albums/{}/transfer?photo_id={}", dir.id, file.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let path = format!("/albums/{}/photos/{}", dir.id, file.id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert!(!res.text().await.unwrap().contains("/transfer?"));
+}
+
+#[tokio::test]
+async fn test_bulk_delete_photos() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let first = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    let second = app.fake.add_file(&dir.id, "b.jpg", "image/jpeg", b"b");
+    app.fake.add_file(&dir.id, "c.jpg", "image/jpeg", b"c");
+    let path = format!("/albums/{}/bulk-delete", dir.id);
+
+    let form_path = format!("{}?photo_id={}&photo_id={}", path, first.id, second.id);
+    let res = app
+        .htmx_request(Method::GET, &form_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Delete 2 selected photos?"));
+
+    let token = app.csrf_token(&form_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[
+            ("photo_id", first.id.as_str()),
+            ("photo_id", second.id.as_str()),
+            ("photo_id", "missing"),
+            ("loaded", "3"),
+            ("token", &token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Deleted 2 of 3 photos."));
+    assert!(body.contains("<strong>missing</strong>"));
+    for id in [&first.id, &second.id] {
+        assert!(body.contains(&format!(
+            r#"<div id="photo-{}" hx-swap-oob="delete"></div>"#,
+            id
+        )));
+    }
+    assert!(!body.contains(r#"<div id="photo-missing""#));
+    assert!(body.contains(r#"<span id="photos-count-w" hx-swap-oob="true">"#));
+    assert!(body.contains(r#"<span class="current-count">1</span>"#));
+    assert!(body.contains(r#"<span class="total-records">1</span>"#));
+
+    let files = app.fake.state().files.clone();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].name, "c.jpg");
+}
+
+#[tokio::test]
+async fn test_bulk_delete_photos_invalid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+    let path = format!("/albums/{}/bulk-delete", dir.id);
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("photo_id", file.id.as_str()), ("token", "bad")])
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::OK);
+
+    // The token is scoped to the album, not to a single photo
+    let other = app.fake.add_dir("winter", "Winter");
+    let other_path = format!("/albums/{}/bulk-delete?photo_id={}", other.id, file.id);
+    let token = app.csrf_token(&other_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("photo_id", file.id.as_str()), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::OK);
+
+    let form_path = format!("{}?photo_id={}", path, file.id);
+    let token = app.csrf_token(&form_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Select at least one photo."));
+    assert_eq!(app.fake.state().files.len(), 1);
+}
+
+#[tokio::test]
+async fn test_bulk_delete_photos_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app.fake.add_file(&dir.id, "a.jpg", "image/jpeg", b"a");
+
+    let path = format!("/albums/{}/bulk-delete?photo_id={}", dir.id, file.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("/albums/{}/bulk-delete", dir.id),
+            VIEWER_TOKEN,
+        )
+        .form(&[("photo_id", file.id.as_str()), ("token", "any")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(app.fake.state().files.len(), 1);
+}
@@ -0,0 +1,484 @@
+use chrono::{Duration, Utc};
+use reqwest::{Method, StatusCode};
+
+use crate::services::create_share_token;
+
+use super::{extract_input_value, TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+/// Path of the first share link in the widget
+fn extract_share_path(html: &str) -> Option<String> {
+    let start = html.find("/share/")?;
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].to_string())
+}
+
+/// Id of the first share link in the widget
+fn extract_share_id(html: &str) -> Option<String> {
+    let start = html.find("/shares/")? + "/shares/".len();
+    let end = html[start..].find('/')?;
+    Some(html[start..start + end].to_string())
+}
+
+async fn create_link(app: &TestApp, shares_path: &str) -> String {
+    let token = app.csrf_token(shares_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, shares_path, ADMIN_TOKEN)
+        .form(&[("expires_in", "7"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_share_album() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    app.fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"beach");
+    let shares_path = format!("/albums/{}/shares", dir.id);
+
+    let res = app
+        .htmx_request(Method::GET, &shares_path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("No active links."));
+
+    let body = create_link(&app, &shares_path).await;
+    assert!(body.contains("admin"));
+    let share_path = extract_share_path(&body).expect("share link not found");
+
+    // Visitors need no login and get none of the member controls
+    let res = app.request(Method::GET, &share_path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Summer Trip"));
+    assert!(body.contains(&format!("{}/photo-grid?page=1", share_path)));
+    assert!(!body.contains("edit-album-container"));
+    assert!(!body.contains("breadcrumb"));
+
+    let res = app
+        .request(Method::GET, &format!("{}/photo-grid", share_path))
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("beach.jpg"));
+    assert!(!body.contains("photo-item-controls"));
+    assert!(!body.contains("photo-select"));
+}
+
+#[tokio::test]
+async fn test_revoke_share_link() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let shares_path = format!("/albums/{}/shares", dir.id);
+
+    let body = create_link(&app, &shares_path).await;
+    let share_path = extract_share_path(&body).unwrap();
+    let share_id = extract_share_id(&body).unwrap();
+
+    let token = app.csrf_token(&shares_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("{}/{}/revoke", shares_path, share_id),
+            ADMIN_TOKEN,
+        )
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.text().await.unwrap().contains("No active links."));
+
+    let res = app.request(Method::GET, &share_path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_invalid_share_links() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let shares_path = format!("/albums/{}/shares", dir.id);
+
+    let body = create_link(&app, &shares_path).await;
+    let share_id = extract_share_id(&body).unwrap();
+
+    let expired = (Utc::now() - Duration::days(1)).timestamp();
+    let token = create_share_token(&share_id, expired, "secret").unwrap();
+    let res = app
+        .request(Method::GET, &format!("/share/{}", token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .request(Method::GET, "/share/not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Expiry is limited to the offered choices
+    let token = app.csrf_token(&shares_path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &shares_path, ADMIN_TOKEN)
+        .form(&[("expires_in", "365"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_share_links_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let shares_path = format!("/albums/{}/shares", dir.id);
+
+    let res = app
+        .htmx_request(Method::GET, &shares_path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .htmx_request(Method::POST, &shares_path, VIEWER_TOKEN)
+        .form(&[("expires_in", "7"), ("token", "any")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+async fn set_passphrase(app: &TestApp, album_id: &str, fields: &[(&str, &str)]) {
+    let path = format!("/albums/{}/passphrase", album_id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let mut form: Vec<(&str, &str)> = fields.to_vec();
+    form.push(("token", &token));
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&form)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("This album is protected."));
+}
+
+/// Submits the passphrase as an anonymous visitor
+async fn unlock(app: &TestApp, album_id: &str, passphrase: &str) -> reqwest::Response {
+    unlock_from(app, album_id, passphrase, "").await
+}
+
+/// Submits the passphrase with the address a proxy would pass along
+async fn unlock_from(
+    app: &TestApp,
+    album_id: &str,
+    passphrase: &str,
+    real_ip: &str,
+) -> reqwest::Response {
+    let path = format!("/albums/{}/unlock", album_id);
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    let body = res.text().await.unwrap();
+    let token = extract_input_value(&body, "token").expect("token input not found");
+
+    let mut req = app
+        .request(Method::POST, &path)
+        .header("HX-Request", "true")
+        .form(&[("passphrase", passphrase), ("token", &token)]);
+    if !real_ip.is_empty() {
+        req = req.header("X-Real-IP", real_ip);
+    }
+    req.send().await.unwrap()
+}
+
+fn unlock_cookie(res: &reqwest::Response) -> String {
+    let value = res.headers().get("Set-Cookie").unwrap().to_str().unwrap();
+    value.split(';').next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_album_passphrase() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"beach");
+    let unlock_path = format!("/albums/{}/unlock", dir.id);
+    let grid_path = format!("{}/photo-grid", unlock_path);
+
+    // Nothing to unlock yet
+    let res = app.request(Method::GET, &unlock_path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    let res = app.request(Method::GET, &unlock_path).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Protected album"));
+    assert!(!body.contains("Summer Trip"));
+
+    let res = app
+        .request(Method::GET, &grid_path)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = unlock(&app, &dir.id, "wrong passphrase").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("Wrong passphrase."));
+
+    let res = unlock(&app, &dir.id, "open sesame").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("HX-Redirect").unwrap(), &unlock_path);
+    let cookie = unlock_cookie(&res);
+
+    let res = app
+        .request(Method::GET, &unlock_path)
+        .header("Cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Summer Trip"));
+    assert!(body.contains(&format!("{}?page=1", grid_path)));
+    assert!(!body.contains("edit-album-container"));
+
+    let res = app
+        .request(Method::GET, &grid_path)
+        .header("Cookie", &cookie)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("beach.jpg"));
+    assert!(body.contains(&format!("/raw/{}/orig", file.id)));
+    assert!(!body.contains("photo-select"));
+
+    // A new passphrase locks out everyone who unlocked the old one
+    set_passphrase(&app, &dir.id, &[("passphrase", "close sesame")]).await;
+    let res = app
+        .request(Method::GET, &grid_path)
+        .header("Cookie", &cookie)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_album_passphrase_limits() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let file = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"beach");
+    let unlock_path = format!("/albums/{}/unlock", dir.id);
+
+    set_passphrase(
+        &app,
+        &dir.id,
+        &[
+            ("passphrase", "open sesame"),
+            ("max_views", "1"),
+            ("no_downloads", "1"),
+        ],
+    )
+    .await;
+
+    let res = unlock(&app, &dir.id, "open sesame").await;
+    let cookie = unlock_cookie(&res);
+
+    let res = app
+        .request(Method::GET, &format!("{}/photo-grid", unlock_path))
+        .header("Cookie", &cookie)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains(&format!("/raw/{}/prev", file.id)));
+    assert!(!body.contains(&format!("/raw/{}/orig", file.id)));
+
+    let res = app
+        .request(Method::GET, &unlock_path)
+        .header("Cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The only view has been used
+    let res = app
+        .request(Method::GET, &unlock_path)
+        .header("Cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_album_passphrase_attempts() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    for _ in 0..5 {
+        let res = unlock(&app, &dir.id, "wrong passphrase").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let res = unlock(&app, &dir.id, "open sesame").await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().get("Set-Cookie").is_none());
+}
+
+#[tokio::test]
+async fn test_album_passphrase_attempts_spoofed_address() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    // Without a trusted proxy the header is ignored
+    for i in 0..5 {
+        let ip = format!("10.0.0.{}", i);
+        let res = unlock_from(&app, &dir.id, "wrong passphrase", &ip).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let res = unlock_from(&app, &dir.id, "open sesame", "10.0.0.9").await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_album_passphrase_attempts_concurrent() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    let attempts = (0..10).map(|_| unlock(&app, &dir.id, "wrong passphrase"));
+    let responses = futures_util::future::join_all(attempts).await;
+    let wrong = responses
+        .iter()
+        .filter(|res| res.status() == StatusCode::BAD_REQUEST)
+        .count();
+    assert_eq!(wrong, 5);
+}
+
+#[tokio::test]
+async fn test_album_passphrase_attempts_behind_proxy() {
+    let app = TestApp::spawn_with(|config| {
+        config.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    })
+    .await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    for _ in 0..5 {
+        let res = unlock_from(&app, &dir.id, "wrong passphrase", "10.0.0.1").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+    let res = unlock_from(&app, &dir.id, "open sesame", "10.0.0.1").await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Other visitors behind the same proxy are not locked out
+    let res = unlock_from(&app, &dir.id, "open sesame", "10.0.0.2").await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_unlock_token_cannot_change_album() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    let path = format!("/albums/{}/unlock", dir.id);
+    let res = app.request(Method::GET, &path).send().await.unwrap();
+    let body = res.text().await.unwrap();
+    let token = extract_input_value(&body, "token").expect("token input not found");
+
+    // Forged on behalf of a logged in member
+    let res = app
+        .htmx_request(
+            Method::POST,
+            &format!("/albums/{}/delete", dir.id),
+            ADMIN_TOKEN,
+        )
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.fake.state().dirs.len(), 1);
+}
+
+#[tokio::test]
+async fn test_remove_album_passphrase() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/passphrase", dir.id);
+
+    // Members who cannot edit the album cannot protect it either
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Too short
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("passphrase", "short"), ("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    set_passphrase(&app, &dir.id, &[("passphrase", "open sesame")]).await;
+
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let res = app
+        .htmx_request(Method::POST, &format!("{}/remove", path), ADMIN_TOKEN)
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!res
+        .text()
+        .await
+        .unwrap()
+        .contains("This album is protected."));
+
+    let res = app
+        .request(Method::GET, &format!("/albums/{}/unlock", dir.id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
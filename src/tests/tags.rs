@@ -0,0 +1,155 @@
+use reqwest::{Method, StatusCode};
+
+use super::{TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+async fn tag_photo(app: &TestApp, album_id: &str, photo_id: &str, tags: &str) -> reqwest::Response {
+    let path = format!("/albums/{}/photos/{}/tags", album_id, photo_id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    app.htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("tags", tags), ("token", token.as_str())])
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_page(app: &TestApp, path: &str, token: &str) -> String {
+    let res = app
+        .auth_request(Method::GET, path, token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_photo_tags() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let res = tag_photo(&app, &dir.id, &photo.id, " Beach, #family  trip, beach,").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Edit tags"));
+    assert!(body.contains(&format!("id=\"photo-tags-{}\"", photo.id)));
+    assert!(body.contains("hx-swap-oob=\"true\""));
+    assert!(body.contains("/tags/beach\""));
+    assert!(body.contains("/tags/family-trip\""));
+
+    let path = format!("/albums/{}/photo-grid?page=1", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("/tags/beach\""));
+    assert!(body.contains("/tags/family-trip\""));
+
+    let path = format!("/albums/{}/photos/{}", dir.id, photo.id);
+    let body = get_page(&app, &path, ADMIN_TOKEN).await;
+    assert!(body.contains("photo-detail-tags"));
+    assert!(body.contains("/tags/family-trip\""));
+
+    let path = format!("/albums/{}/photos/{}/tags", dir.id, photo.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("value=\"beach, family-trip\""));
+
+    let res = tag_photo(&app, &dir.id, &photo.id, "beach, not/valid").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Tags must be at most 30 letters"));
+    assert!(body.contains("value=\"beach, not/valid\""));
+
+    // An empty input clears the tags
+    let res = tag_photo(&app, &dir.id, &photo.id, "").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("/tags/beach\""));
+    let body = get_page(&app, "/tags", ADMIN_TOKEN).await;
+    assert!(body.contains("No tags yet"));
+}
+
+#[tokio::test]
+async fn test_photo_tags_viewer() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let photo = app
+        .fake
+        .add_file(&dir.id, "beach.jpg", "image/jpeg", b"jpeg");
+
+    let path = format!("/albums/{}/photos/{}/tags", dir.id, photo.id);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(!body.contains("name=\"tags\""));
+
+    let res = app
+        .htmx_request(Method::POST, &path, VIEWER_TOKEN)
+        .form(&[("tags", "beach"), ("token", "invalid")])
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::OK);
+
+    let body = get_page(&app, "/tags", VIEWER_TOKEN).await;
+    assert!(body.contains("No tags yet"));
+}
+
+#[tokio::test]
+async fn test_tag_pages() {
+    let app = TestApp::spawn().await;
+    let summer = app.fake.add_dir("summer", "Summer Trip");
+    let winter = app.fake.add_dir("winter", "Winter Trip");
+    let beach = app
+        .fake
+        .add_file(&summer.id, "beach.jpg", "image/jpeg", b"jpeg");
+    let sunset = app
+        .fake
+        .add_file(&summer.id, "sunset.jpg", "image/jpeg", b"jpeg");
+    let snow = app
+        .fake
+        .add_file(&winter.id, "snow.jpg", "image/jpeg", b"jpeg");
+
+    tag_photo(&app, &summer.id, &beach.id, "family, beach").await;
+    tag_photo(&app, &summer.id, &sunset.id, "sky").await;
+    tag_photo(&app, &winter.id, &snow.id, "family").await;
+
+    let body = get_page(&app, "/tags", VIEWER_TOKEN).await;
+    assert!(body.contains("/tags/beach\""));
+    assert!(body.contains("/tags/family\""));
+    assert!(body.contains("/tags/sky\""));
+
+    let body = get_page(&app, "/tags/family", VIEWER_TOKEN).await;
+    assert!(body.contains("beach.jpg"));
+    assert!(body.contains("snow.jpg"));
+    assert!(!body.contains("sunset.jpg"));
+    assert!(body.contains(&format!("/albums/{}/photos/{}", summer.id, beach.id)));
+    assert!(body.contains(&format!("/albums/{}/photos/{}", winter.id, snow.id)));
+
+    let body = get_page(&app, "/tags/unknown", VIEWER_TOKEN).await;
+    assert!(body.contains("No photos with this tag."));
+
+    // Photos removed from files-rs lose their tags
+    app.fake.state().files.retain(|file| file.id != snow.id);
+    let body = get_page(&app, "/tags/family", ADMIN_TOKEN).await;
+    assert!(body.contains("beach.jpg"));
+    assert!(!body.contains("snow.jpg"));
+    assert!(!body.contains("could not be loaded"));
+
+    let body = get_page(&app, "/tags", ADMIN_TOKEN).await;
+    assert!(body.contains("/tags/family\""));
+    assert!(body.contains(">1<"));
+}
@@ -0,0 +1,120 @@
+use reqwest::{Method, StatusCode};
+
+use super::{TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+/// 2024-03-15 and 2024-05-15, UTC
+const MARCH: i64 = 1710460800;
+const MAY: i64 = 1715731200;
+
+fn set_created_at(app: &TestApp, file_id: &str, created_at: i64) {
+    let mut state = app.fake.state();
+    let file = state.files.iter_mut().find(|f| f.id == file_id).unwrap();
+    file.created_at = created_at;
+}
+
+async fn timeline_grid(app: &TestApp, query: &str) -> String {
+    let path = format!("/timeline/grid?{}", query);
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_timeline_page() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .auth_request(Method::GET, "/timeline", ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("/timeline/grid?page=1"));
+
+    let body = timeline_grid(&app, "page=1").await;
+    assert!(body.contains("No photos yet."));
+}
+
+#[tokio::test]
+async fn test_timeline_grid() {
+    let app = TestApp::spawn().await;
+    let summer = app.fake.add_dir("summer", "Summer Trip");
+    let winter = app.fake.add_dir("winter", "Winter Trip");
+    app.fake.add_dir("empty", "Empty Album");
+
+    let old = app
+        .fake
+        .add_file(&winter.id, "snow.jpg", "image/jpeg", b"jpeg");
+    set_created_at(&app, &old.id, MARCH);
+    let new = app
+        .fake
+        .add_file(&summer.id, "beach.jpg", "image/jpeg", b"jpeg");
+    set_created_at(&app, &new.id, MAY);
+
+    let body = timeline_grid(&app, "page=1").await;
+    let may = body.find("May 2024").expect("may header not found");
+    let beach = body.find("beach.jpg").unwrap();
+    let march = body.find("March 2024").expect("march header not found");
+    let snow = body.find("snow.jpg").unwrap();
+    assert!(may < beach && beach < march && march < snow);
+    assert!(body.contains("Summer Trip"));
+    assert!(body.contains("Winter Trip"));
+    assert!(body.contains(&format!("/albums/{}/photos/{}", winter.id, old.id)));
+    assert!(!body.contains("hx-trigger=\"revealed\""));
+}
+
+#[tokio::test]
+async fn test_timeline_pages() {
+    let app = TestApp::spawn().await;
+    let summer = app.fake.add_dir("summer", "Summer Trip");
+    let winter = app.fake.add_dir("winter", "Winter Trip");
+    for i in 0..30 {
+        let file = app.fake.add_file(
+            &summer.id,
+            &format!("summer-{}.jpg", i),
+            "image/jpeg",
+            b"jpeg",
+        );
+        set_created_at(&app, &file.id, MAY + i);
+    }
+    for i in 0..30 {
+        let file = app.fake.add_file(
+            &winter.id,
+            &format!("winter-{}.jpg", i),
+            "image/jpeg",
+            b"jpeg",
+        );
+        set_created_at(&app, &file.id, MARCH + i);
+    }
+
+    let body = timeline_grid(&app, "page=1").await;
+    assert!(body.contains("summer-29.jpg"));
+    assert!(body.contains("winter-29.jpg"));
+    assert!(!body.contains("winter-9.jpg\""));
+    assert!(body.contains("/timeline/grid?page=2&month=2024-03"));
+    assert!(body.contains("hx-trigger=\"revealed\""));
+
+    // Photos added while scrolling show up once the timeline is reloaded
+    let late = app
+        .fake
+        .add_file(&summer.id, "late.jpg", "image/jpeg", b"jpeg");
+    set_created_at(&app, &late.id, MARCH);
+
+    let body = timeline_grid(&app, "page=2&month=2024-03").await;
+    assert!(body.contains("winter-9.jpg"));
+    assert!(body.contains("winter-0.jpg"));
+    assert!(!body.contains("late.jpg"));
+    assert!(!body.contains("March 2024"));
+    assert!(!body.contains("hx-trigger=\"revealed\""));
+
+    let body = timeline_grid(&app, "page=1").await;
+    assert_eq!(body.matches("class=\"photo-grid-item\"").count(), 50);
+    let body = timeline_grid(&app, "page=2&month=2024-03").await;
+    assert!(body.contains("late.jpg"));
+}
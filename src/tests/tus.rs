@@ -0,0 +1,231 @@
+use base64::prelude::*;
+use reqwest::{Method, RequestBuilder, StatusCode};
+
+use super::{TestApp, ADMIN_TOKEN, BUCKET_ID, VIEWER_TOKEN};
+
+fn tus_request(app: &TestApp, method: Method, path: &str, token: &str) -> RequestBuilder {
+    app.auth_request(method, path, token)
+        .header("Tus-Resumable", "1.0.0")
+}
+
+fn metadata(filename: &str, filetype: &str) -> String {
+    format!(
+        "filename {},filetype {}",
+        BASE64_STANDARD.encode(filename),
+        BASE64_STANDARD.encode(filetype)
+    )
+}
+
+async fn create_upload(app: &TestApp, album_id: &str, length: usize) -> String {
+    let path = format!("/albums/{}/upload", album_id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let res = tus_request(
+        app,
+        Method::POST,
+        &format!("{}/tus?token={}", path, token),
+        ADMIN_TOKEN,
+    )
+    .header("Upload-Length", length.to_string())
+    .header("Upload-Metadata", metadata("beach.jpg", "image/jpeg"))
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert!(res.headers().get("Upload-Expires").is_some());
+
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.starts_with(&format!("/buckets/{}{}/tus/", BUCKET_ID, path)));
+    location.to_string()
+}
+
+async fn patch_chunk(
+    app: &TestApp,
+    location: &str,
+    offset: usize,
+    chunk: &[u8],
+) -> reqwest::Response {
+    tus_request(app, Method::PATCH, location, ADMIN_TOKEN)
+        .header("Content-Type", "application/offset+octet-stream")
+        .header("Upload-Offset", offset.to_string())
+        .body(chunk.to_vec())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_tus_options() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let res = app
+        .auth_request(
+            Method::OPTIONS,
+            &format!("/albums/{}/upload/tus", dir.id),
+            ADMIN_TOKEN,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers().get("Tus-Version").unwrap(), "1.0.0");
+    assert_eq!(res.headers().get("Tus-Max-Size").unwrap(), "10000000");
+}
+
+#[tokio::test]
+async fn test_tus_upload() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let data = b"0123456789abcdefghij";
+    let location = create_upload(&app, &dir.id, data.len()).await;
+
+    let res = patch_chunk(&app, &location, 0, &data[..8]).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers().get("Upload-Offset").unwrap(), "8");
+
+    // Client reconnects and asks where to resume from
+    let res = tus_request(&app, Method::HEAD, &location, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("Upload-Offset").unwrap(), "8");
+    assert_eq!(res.headers().get("Upload-Length").unwrap(), "20");
+    assert!(app.fake.state().files.is_empty());
+
+    let res = patch_chunk(&app, &location, 8, &data[8..]).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers().get("Upload-Offset").unwrap(), "20");
+    assert!(res.headers().get("X-Photo-Id").is_some());
+    assert!(res.headers().get("X-Next-Token").is_some());
+
+    {
+        let state = app.fake.state();
+        assert_eq!(state.files.len(), 1);
+        assert_eq!(state.files[0].name, "beach.jpg");
+        assert_eq!(state.files[0].content_type, "image/jpeg");
+        assert_eq!(state.files[0].data, data);
+    }
+
+    // Completed uploads are cleaned up
+    let res = tus_request(&app, Method::HEAD, &location, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tus_offset_mismatch() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let location = create_upload(&app, &dir.id, 10).await;
+
+    let res = patch_chunk(&app, &location, 5, b"12345").await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let res = patch_chunk(&app, &location, 0, b"0123456789extra").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(app.fake.state().files.is_empty());
+}
+
+#[tokio::test]
+async fn test_tus_terminate() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let location = create_upload(&app, &dir.id, 10).await;
+
+    let res = tus_request(&app, Method::DELETE, &location, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = patch_chunk(&app, &location, 0, b"0123456789").await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tus_scoped_to_album_and_user() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let other = app.fake.add_dir("winter", "Winter Trip");
+    let location = create_upload(&app, &dir.id, 10).await;
+    let upload_id = location.rsplit('/').next().unwrap();
+
+    let res = tus_request(
+        &app,
+        Method::HEAD,
+        &format!("/albums/{}/upload/tus/{}", other.id, upload_id),
+        ADMIN_TOKEN,
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = tus_request(&app, Method::HEAD, &location, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tus_create_invalid() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/upload/tus", dir.id);
+
+    // Stale CSRF token
+    let res = tus_request(
+        &app,
+        Method::POST,
+        &format!("{}?token=stale", path),
+        ADMIN_TOKEN,
+    )
+    .header("Upload-Length", "10")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Missing protocol version
+    let res = app
+        .auth_request(Method::POST, &path, ADMIN_TOKEN)
+        .header("Upload-Length", "10")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    // Larger than the configured limit
+    let token = app
+        .csrf_token(&format!("/albums/{}/upload", dir.id), ADMIN_TOKEN)
+        .await;
+    let res = tus_request(
+        &app,
+        Method::POST,
+        &format!("{}?token={}", path, token),
+        ADMIN_TOKEN,
+    )
+    .header("Upload-Length", "20000000")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Viewers cannot upload
+    let res = tus_request(
+        &app,
+        Method::POST,
+        &format!("{}?token={}", path, token),
+        VIEWER_TOKEN,
+    )
+    .header("Upload-Length", "10")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
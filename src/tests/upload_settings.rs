@@ -0,0 +1,125 @@
+use reqwest::{Method, StatusCode};
+
+use super::{exif_jpeg, multipart_body, oriented_jpeg, TestApp, ADMIN_TOKEN, VIEWER_TOKEN};
+
+async fn set_normalize_uploads(app: &TestApp, album_id: &str, enabled: bool) -> String {
+    let path = format!("/albums/{}/upload-settings", album_id);
+    let token = app.csrf_token(&path, ADMIN_TOKEN).await;
+    let mut form = vec![("token", token.as_str())];
+    if enabled {
+        form.push(("normalize_uploads", "1"));
+    }
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&form)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    res.text().await.unwrap()
+}
+
+async fn upload(app: &TestApp, album_id: &str, filename: &str, data: &[u8]) -> Vec<u8> {
+    let path = format!("/albums/{}/upload", album_id);
+    let csrf_token = app.csrf_token(&path, ADMIN_TOKEN).await;
+
+    let (content_type, body) = multipart_body(filename, "image/jpeg", data);
+    let res = app
+        .auth_request(Method::POST, &path, ADMIN_TOKEN)
+        .query(&[("token", csrf_token)])
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let state = app.fake.state();
+    state.files.last().unwrap().data.clone()
+}
+
+#[tokio::test]
+async fn test_upload_settings() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    let path = format!("/albums/{}/upload-settings", dir.id);
+    let res = app
+        .htmx_request(Method::GET, &path, ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Auto-rotate and strip location from uploads"));
+    assert!(!body.contains(" checked"));
+
+    let body = set_normalize_uploads(&app, &dir.id, true).await;
+    assert!(body.contains(" checked"));
+
+    let body = set_normalize_uploads(&app, &dir.id, false).await;
+    assert!(!body.contains(" checked"));
+}
+
+#[tokio::test]
+async fn test_upload_settings_forbidden() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+    let path = format!("/albums/{}/upload-settings", dir.id);
+
+    let res = app
+        .htmx_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .htmx_request(Method::POST, &path, ADMIN_TOKEN)
+        .form(&[("token", "invalid"), ("normalize_uploads", "1")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(!res.text().await.unwrap().contains(" checked"));
+}
+
+#[tokio::test]
+async fn test_normalized_upload() {
+    let app = TestApp::spawn().await;
+    let dir = app.fake.add_dir("summer", "Summer Trip");
+
+    // Originals are kept as they are unless the album asks otherwise
+    let original = oriented_jpeg(16, 8, 6);
+    assert_eq!(
+        upload(&app, &dir.id, "sideways.jpg", &original).await,
+        original
+    );
+
+    set_normalize_uploads(&app, &dir.id, true).await;
+    let data = upload(&app, &dir.id, "upright.jpg", &original).await;
+    let image = image::load_from_memory(&data).unwrap();
+    assert_eq!((image.width(), image.height()), (8, 16));
+
+    let data = upload(&app, &dir.id, "sunset.jpg", &exif_jpeg()).await;
+    assert_ne!(data, exif_jpeg());
+
+    // Location is gone from the file and from the photo info alike
+    let photo_id = app.fake.state().files.last().unwrap().id.clone();
+    let path = format!("/albums/{}/photos/{}", dir.id, photo_id);
+    let res = app
+        .auth_request(Method::GET, &path, VIEWER_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("Canon EOS R6"));
+    assert!(!body.contains("openstreetmap.org"));
+
+    // Other files go through untouched
+    assert_eq!(
+        upload(&app, &dir.id, "notes.jpg", b"jpeg data").await,
+        b"jpeg data"
+    );
+}
@@ -2,11 +2,10 @@ use askama::Template;
 use axum::http::StatusCode;
 use axum::{body::Body, extract::State, response::Response, Extension, Form};
 
-use crate::models::{NewAlbumForm, Pref};
+use crate::models::{Album, Bucket, NewAlbum, NewAlbumForm, Pref};
 use crate::run::AppState;
-use crate::services::create_csrf_token;
-use crate::Error;
-use crate::{ctx::Ctx, models::TemplateData, services::create_album};
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::{ctx::Ctx, models::TemplateData, Result};
 
 use crate::web::{enforce_policy, handle_error, Action, ErrorInfo, Resource};
 
@@ -14,6 +13,7 @@ use crate::web::{enforce_policy, handle_error, Action, ErrorInfo, Resource};
 #[template(path = "pages/new_album.html")]
 struct NewAlbumTemplate {
     t: TemplateData,
+    bucket: Bucket,
     action: String,
     payload: NewAlbumForm,
     error_message: Option<String>,
@@ -22,6 +22,7 @@ struct NewAlbumTemplate {
 #[derive(Template)]
 #[template(path = "widgets/new_album_form.html")]
 struct AlbumFormTemplate {
+    bucket: Bucket,
     action: String,
     payload: NewAlbumForm,
     error_message: Option<String>,
@@ -30,6 +31,7 @@ struct AlbumFormTemplate {
 pub async fn new_album_handler(
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
     State(state): State<AppState>,
 ) -> Response<Body> {
     let config = state.config.clone();
@@ -49,7 +51,8 @@ pub async fn new_album_handler(
 
     let tpl = NewAlbumTemplate {
         t,
-        action: "/albums/new".to_string(),
+        action: format!("/buckets/{}/albums/new", &bucket.id),
+        bucket,
         payload: NewAlbumForm {
             name: "".to_string(),
             label: "".to_string(),
@@ -67,21 +70,12 @@ pub async fn new_album_handler(
 pub async fn post_new_album_handler(
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
     State(state): State<AppState>,
     payload: Option<Form<NewAlbumForm>>,
 ) -> Response<Body> {
     let config = state.config.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(actor.clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            false,
-        );
-    };
 
     if let Err(err) = enforce_policy(actor, Resource::Album, Action::Create) {
         return handle_error(&state, Some(actor.clone()), &pref, err.into(), false);
@@ -93,7 +87,8 @@ pub async fn post_new_album_handler(
     };
 
     let mut tpl = AlbumFormTemplate {
-        action: "/albums/new".to_string(),
+        bucket: bucket.clone(),
+        action: format!("/buckets/{}/albums/new", &bucket.id),
         payload: NewAlbumForm {
             name: "".to_string(),
             label: "".to_string(),
@@ -105,17 +100,11 @@ pub async fn post_new_album_handler(
     let mut status: StatusCode = StatusCode::INTERNAL_SERVER_ERROR;
 
     if let Some(form) = payload {
-        let album = NewAlbumForm {
-            name: form.name.clone(),
-            label: form.label.clone(),
-            token: form.token.clone(),
-        };
-
-        let result = create_album(&config, ctx.token(), &bucket_id, album).await;
+        let result = create_album(&state, &ctx, &bucket, &form).await;
 
         match result {
             Ok(album) => {
-                let next_url = format!("/albums/{}", &album.id);
+                let next_url = format!("/buckets/{}/albums/{}", &bucket.id, &album.id);
                 // Weird but can't do a redirect here, let htmx handle it
                 return Response::builder()
                     .status(200)
@@ -142,3 +131,21 @@ pub async fn post_new_album_handler(
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+async fn create_album(
+    state: &AppState,
+    ctx: &Ctx,
+    bucket: &Bucket,
+    form: &NewAlbumForm,
+) -> Result<Album> {
+    verify_csrf_subject(&form.token, "new_album", &state.config.jwt_secret)?;
+
+    let album = NewAlbum {
+        name: form.name.clone(),
+        label: form.label.clone(),
+    };
+    state
+        .files
+        .create_album(ctx.token(), &bucket.id, &album)
+        .await
+}
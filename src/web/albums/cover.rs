@@ -0,0 +1,140 @@
+use askama::Template;
+use axum::http::header::{CACHE_CONTROL, LOCATION};
+use axum::http::StatusCode;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use tracing::error;
+
+use crate::models::{DeletePhotoForm, ListPhotosParams, Photo};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/album_cover_control.html")]
+struct AlbumCoverControlTemplate {
+    album: Album,
+    photo: Photo,
+    is_cover: bool,
+    token: String,
+}
+
+/// Redirects to the cover thumbnail.
+/// Photo urls are short lived so only the photo id is kept.
+pub async fn album_cover_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    match find_cover(&state, &ctx, &album).await {
+        Ok(Some(photo)) => Response::builder()
+            .status(StatusCode::FOUND)
+            .header(LOCATION, photo.thumb.url)
+            .header(CACHE_CONTROL, "private, max-age=300")
+            .body(Body::empty())
+            .unwrap(),
+        Ok(None) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+        Err(err) => handle_error_message(err),
+    }
+}
+
+/// Uses the current photo as the album cover
+pub async fn set_album_cover_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+    payload: Option<Form<DeletePhotoForm>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return handle_error_message(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        ));
+    };
+    if let Err(err) = verify_csrf_subject(&form.token, &photo.id, &config.jwt_secret) {
+        return handle_error_message(err);
+    }
+    if !photo.is_image() {
+        return handle_error_message(Error::BadRequest(
+            "Only photos can be used as the album cover.".to_string(),
+        ));
+    }
+
+    let mut meta = state.album_meta.get(&album.id).unwrap_or_default();
+    meta.cover_photo_id = Some(photo.id.clone());
+    if let Err(err) = state.album_meta.set(&album.id, meta).await {
+        return handle_error_message(err);
+    }
+
+    let Ok(token) = create_csrf_token(&photo.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize album cover form.".to_string(),
+        ));
+    };
+
+    let tpl = AlbumCoverControlTemplate {
+        album,
+        photo,
+        is_cover: true,
+        token,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// The chosen cover or else the first photo on the first page, which is then remembered
+async fn find_cover(state: &AppState, ctx: &Ctx, album: &Album) -> Result<Option<Photo>> {
+    let cover_id = state
+        .album_meta
+        .get(&album.id)
+        .and_then(|meta| meta.cover_photo_id);
+
+    if let Some(photo_id) = cover_id {
+        let result = state
+            .files
+            .get_photo(ctx.token(), &album.bucket_id, &album.id, &photo_id)
+            .await;
+        match result {
+            Ok(photo) => return Ok(Some(photo)),
+            Err(Error::PhotoNotFound) => {
+                state.album_meta.clear_cover(&album.id, &photo_id).await?;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    if album.file_count == 0 {
+        return Ok(None);
+    }
+
+    let params = ListPhotosParams::default();
+    let listing = state
+        .files
+        .list_photos(ctx.token(), &album.bucket_id, &album.id, &params)
+        .await?;
+    let Some(photo) = listing.data.into_iter().find(|p| p.is_image()) else {
+        return Ok(None);
+    };
+
+    let mut meta = state.album_meta.get(&album.id).unwrap_or_default();
+    meta.cover_photo_id = Some(photo.id.clone());
+    if let Err(err) = state.album_meta.set(&album.id, meta).await {
+        // Still fine to show it, we just look it up again next time
+        error!("Error: {}", err);
+    }
+
+    Ok(Some(photo))
+}
@@ -2,14 +2,14 @@ use askama::Template;
 use axum::http::{Method, StatusCode};
 use axum::Form;
 use axum::{body::Body, extract::State, response::Response, Extension};
+use tracing::warn;
 
 use crate::models::{DeleteAlbumForm, Pref};
 use crate::run::AppState;
-use crate::services::{create_csrf_token, delete_album};
-use crate::Error;
-use crate::{ctx::Ctx, models::Album};
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::{ctx::Ctx, models::Album, Result};
 
-use crate::web::{enforce_policy, handle_error, Action, ErrorInfo, Resource};
+use crate::web::{enforce_policy, forget_photo, handle_error, Action, ErrorInfo, Resource};
 
 #[derive(Template)]
 #[template(path = "widgets/delete_album_form.html")]
@@ -30,16 +30,6 @@ pub async fn delete_album_handler(
 ) -> Response<Body> {
     let config = state.config.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(actor.clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            false,
-        );
-    };
 
     if let Err(err) = enforce_policy(actor, Resource::Album, Action::Delete) {
         return handle_error(&state, Some(actor.clone()), &pref, err.into(), false);
@@ -55,11 +45,11 @@ pub async fn delete_album_handler(
 
     if method == Method::POST {
         if let Some(form) = payload {
-            let result =
-                delete_album(&config, ctx.token(), &bucket_id, &album.id, &form.token).await;
+            let result = delete_album(&state, &ctx, &album, &form).await;
             match result {
                 Ok(_) => {
-                    // Render same form but trigger a redirect to home
+                    // Render same form but trigger a redirect to the bucket home
+                    let next_url = format!("/buckets/{}", &album.bucket_id);
                     let tpl = DeleteAlbumTemplate {
                         album,
                         payload: DeleteAlbumForm {
@@ -69,7 +59,7 @@ pub async fn delete_album_handler(
                     };
                     return Response::builder()
                         .status(200)
-                        .header("HX-Redirect", "/")
+                        .header("HX-Redirect", next_url)
                         .body(Body::from(tpl.render().unwrap()))
                         .unwrap();
                 }
@@ -97,3 +87,63 @@ pub async fn delete_album_handler(
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+/// Deletes the album in files-rs, then everything memo-rs kept about it
+async fn delete_album(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    form: &DeleteAlbumForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    // Photo details are keyed by photo, so the ids are needed before they are gone
+    let photos = state
+        .files
+        .list_all_photos(ctx.token(), &album.bucket_id, &album.id)
+        .await?;
+
+    state
+        .files
+        .delete_album(ctx.token(), &album.bucket_id, &album.id)
+        .await?;
+
+    let photo_ids: Vec<String> = photos.into_iter().map(|photo| photo.id).collect();
+    forget_album(state, &album.id, &photo_ids).await;
+    Ok(())
+}
+
+/// The album is already gone from files-rs, failures here are only logged
+async fn forget_album(state: &AppState, album_id: &str, photo_ids: &[String]) {
+    for photo_id in photo_ids {
+        forget_photo(state, album_id, photo_id).await;
+    }
+
+    let mut results: Vec<Result<()>> = Vec::new();
+    results.push(state.album_meta.remove(album_id).await.map(|_| ()));
+    results.push(
+        state
+            .share_links
+            .remove_where(|link| link.album_id == album_id)
+            .await,
+    );
+    results.push(
+        state
+            .guest_upload_links
+            .remove_where(|link| link.album_id == album_id)
+            .await,
+    );
+    results.push(
+        state
+            .photo_tags
+            .remove_where(|entry| entry.album_id == album_id)
+            .await,
+    );
+    results.push(state.favorites.forget_album(album_id).await);
+    results.push(state.staging.remove_album(album_id).await);
+    state.photo_listings.invalidate(album_id);
+
+    for err in results.into_iter().filter_map(|result| result.err()) {
+        warn!("Unable to forget deleted album {}: {}", album_id, err);
+    }
+}
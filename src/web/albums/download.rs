@@ -0,0 +1,205 @@
+use axum::extract::Query;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    response::Response,
+    Extension,
+};
+use futures_util::StreamExt;
+use tokio::sync::mpsc::{channel, Sender};
+use tracing::error;
+
+use crate::models::{
+    DownloadAlbumParams, ImgVersion, ListPhotosParams, Photo, DEFAULT_PHOTO_PAGE_SIZE,
+};
+use crate::run::AppState;
+use crate::services::ZipArchive;
+use crate::web::handle_error_message;
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+type Chunk = std::result::Result<Bytes, std::io::Error>;
+
+/// Why a photo is missing from the archive
+enum PhotoFailure {
+    /// Nothing of the photo was sent, it is listed in the download errors
+    Skipped(Error),
+    /// Part of the photo was already sent, the archive cannot be completed
+    Aborted(Error),
+}
+
+/// Streams every photo in the album as a ZIP archive.
+/// Photos are fetched one at a time so the album is never held in memory.
+pub async fn download_album_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<DownloadAlbumParams>,
+) -> Response<Body> {
+    let version = query.version.unwrap_or(ImgVersion::Original);
+    if version == ImgVersion::Thumbnail {
+        return handle_error_message(Error::BadRequest(
+            "Only original and preview photos can be downloaded.".to_string(),
+        ));
+    }
+
+    let filename = archive_filename(&album, &version);
+
+    // A small buffer keeps a slow client from pulling the whole album into memory
+    let (tx, rx) = channel::<Chunk>(4);
+    tokio::spawn(write_archive(state, ctx, album, version, tx));
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+
+    Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/zip")
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        // Tell nginx to pass the archive through as it is written
+        .header("X-Accel-Buffering", "no")
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
+async fn write_archive(
+    state: AppState,
+    ctx: Ctx,
+    album: Album,
+    version: ImgVersion,
+    tx: Sender<Chunk>,
+) {
+    let mut zip = ZipArchive::new();
+    let mut failed: Vec<String> = Vec::new();
+    let mut page: u32 = 1;
+
+    loop {
+        let params = ListPhotosParams {
+            page: Some(page),
+            per_page: Some(DEFAULT_PHOTO_PAGE_SIZE),
+            ..Default::default()
+        };
+        let listing = match state
+            .files
+            .list_photos(ctx.token(), &album.bucket_id, &album.id, &params)
+            .await
+        {
+            Ok(listing) => listing,
+            Err(err) => {
+                // Abort the response so the browser does not keep a partial archive
+                error!("Error: {}", err);
+                let _ = tx.send(Err(std::io::Error::other(err.to_string()))).await;
+                return;
+            }
+        };
+
+        // Videos and other files have no previews of their own
+        let photos = listing
+            .data
+            .iter()
+            .filter(|photo| photo.is_image() || version != ImgVersion::Preview);
+        for photo in photos {
+            match add_photo(&state, &mut zip, photo, &version, &tx).await {
+                Ok(_) => {}
+                Err(PhotoFailure::Skipped(err)) => {
+                    failed.push(format!("{}: {}", photo.filename, err));
+                }
+                Err(PhotoFailure::Aborted(err)) => {
+                    // A truncated photo must not look complete, fail the whole download
+                    error!("Error: {}", err);
+                    let _ = tx.send(Err(std::io::Error::other(err.to_string()))).await;
+                    return;
+                }
+            }
+        }
+
+        if page as i64 >= listing.meta.total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    // Let the user know what is missing instead of failing the whole download
+    if !failed.is_empty() {
+        let contents = format!(
+            "These photos could not be downloaded:\n\n{}\n",
+            failed.join("\n")
+        );
+        let header = zip.start_entry("download-errors.txt", chrono::Utc::now().timestamp());
+        if zip.add_data(contents.as_bytes()).is_ok() {
+            let _ = tx.send(Ok(header)).await;
+            let _ = tx.send(Ok(Bytes::from(contents))).await;
+        }
+    }
+
+    let _ = tx.send(Ok(zip.finish())).await;
+}
+
+async fn add_photo(
+    state: &AppState,
+    zip: &mut ZipArchive,
+    photo: &Photo,
+    version: &ImgVersion,
+    tx: &Sender<Chunk>,
+) -> std::result::Result<(), PhotoFailure> {
+    if !photo.has_url() {
+        return Err(PhotoFailure::Skipped(Error::ServiceError(
+            "File is still processing.".to_string(),
+        )));
+    }
+
+    let url = match version {
+        ImgVersion::Preview => &photo.preview.url,
+        _ => &photo.orig.url,
+    };
+    let mut stream = state
+        .files
+        .download_photo(url)
+        .await
+        .map_err(PhotoFailure::Skipped)?;
+
+    send(tx, zip.start_entry(&photo.filename, photo.created_at))
+        .await
+        .map_err(PhotoFailure::Aborted)?;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(PhotoFailure::Aborted)?;
+        zip.add_data(&chunk).map_err(PhotoFailure::Aborted)?;
+        send(tx, chunk).await.map_err(PhotoFailure::Aborted)?;
+    }
+
+    send(tx, zip.finish_entry())
+        .await
+        .map_err(PhotoFailure::Aborted)
+}
+
+async fn send(tx: &Sender<Chunk>, chunk: Bytes) -> Result<()> {
+    tx.send(Ok(chunk))
+        .await
+        .map_err(|_| Error::AnyError("Download cancelled.".to_string()))
+}
+
+/// Album label with anything unsafe for a header or a filesystem replaced
+fn archive_filename(album: &Album, version: &ImgVersion) -> String {
+    let label: String = album
+        .label
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    let label = match label.trim() {
+        "" => album.name.clone(),
+        label => label.to_string(),
+    };
+
+    match version {
+        ImgVersion::Preview => format!("{}-previews.zip", label),
+        _ => format!("{}.zip", label),
+    }
+}
@@ -1,12 +1,14 @@
 use askama::Template;
 use axum::{body::Body, extract::State, response::Response, Extension, Form};
 
-use crate::models::{Pref, UpdateAlbumForm};
+use crate::models::{Pref, UpdateAlbum, UpdateAlbumForm};
 use crate::run::AppState;
-use crate::services::{create_csrf_token, update_album};
-use crate::{ctx::Ctx, models::Album, Error};
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::{ctx::Ctx, models::Album, Error, Result};
 
-use crate::web::{enforce_policy, handle_error, Action, ErrorInfo, Resource};
+use crate::web::{
+    enforce_policy, enforce_review_policy, handle_error, Action, ErrorInfo, Resource,
+};
 
 #[derive(Template)]
 #[template(path = "widgets/edit_album_form.html")]
@@ -26,6 +28,7 @@ struct EditAlbumControlsTemplate {
     can_delete: bool,
     can_add_photos: bool,
     can_delete_photos: bool,
+    can_review_uploads: bool,
 }
 
 /// Simply re-renders the edit and delete album controls
@@ -40,6 +43,7 @@ pub async fn edit_album_controls_handler(
         can_delete: enforce_policy(ctx.actor(), Resource::Album, Action::Delete).is_ok(),
         can_add_photos: enforce_policy(ctx.actor(), Resource::Photo, Action::Create).is_ok(),
         can_delete_photos: enforce_policy(ctx.actor(), Resource::Photo, Action::Delete).is_ok(),
+        can_review_uploads: enforce_review_policy(ctx.actor()).is_ok(),
     };
 
     Response::builder()
@@ -90,17 +94,8 @@ pub async fn post_edit_album_handler(
 ) -> Response<Body> {
     let config = state.config.clone();
     let album_id = album.id.clone();
+    let bucket_id = album.bucket_id.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(actor.clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            false,
-        );
-    };
 
     if let Err(err) = enforce_policy(actor, Resource::Album, Action::Update) {
         return handle_error(&state, Some(actor.clone()), &pref, err.into(), false);
@@ -125,7 +120,7 @@ pub async fn post_edit_album_handler(
         Some(form) => {
             tpl.payload.label = form.label.clone();
 
-            let result = update_album(&config, ctx.token(), &bucket_id, &album_id, &form).await;
+            let result = update_album(&state, &ctx, &bucket_id, &album_id, &form).await;
             match result {
                 Ok(updated_album) => {
                     tpl.album = updated_album;
@@ -162,6 +157,7 @@ pub async fn post_edit_album_handler(
             can_delete: enforce_policy(actor, Resource::Album, Action::Delete).is_ok(),
             can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
             can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+            can_review_uploads: enforce_review_policy(actor).is_ok(),
         };
         Response::builder()
             .status(status)
@@ -174,3 +170,21 @@ pub async fn post_edit_album_handler(
             .unwrap()
     }
 }
+
+async fn update_album(
+    state: &AppState,
+    ctx: &Ctx,
+    bucket_id: &str,
+    album_id: &str,
+    form: &UpdateAlbumForm,
+) -> Result<Album> {
+    verify_csrf_subject(&form.token, album_id, &state.config.jwt_secret)?;
+
+    let data = UpdateAlbum {
+        label: form.label.clone(),
+    };
+    state
+        .files
+        .update_album(ctx.token(), bucket_id, album_id, &data)
+        .await
+}
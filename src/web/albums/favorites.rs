@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use askama::Template;
+use axum::{body::Body, extract::State, response::Response, Extension};
+
+use crate::ctx::Ctx;
+use crate::models::{Bucket, Photo, Pref, TemplateData};
+use crate::run::AppState;
+use crate::services::create_csrf_token;
+use crate::web::{bucket_photos, enforce_policy, handle_error, Action, Resource};
+
+#[derive(Template)]
+#[template(path = "pages/favorites.html")]
+struct FavoritesTemplate {
+    t: TemplateData,
+    bucket: Bucket,
+    items: Vec<FavoriteItem>,
+    truncated: bool,
+    failed_count: usize,
+}
+
+struct FavoriteItem {
+    photo: Photo,
+    /// Stars are toggled with an album scoped token
+    token: String,
+}
+
+/// Starred photos across every album in the bucket, newest star first.
+/// Photos deleted in the meantime are dropped from the favorites.
+pub async fn favorites_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Read) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), true);
+    }
+
+    let ids = state
+        .favorites
+        .bucket_favorites(&actor.user.id, &bucket.id)
+        .into_iter()
+        .map(|favorite| (favorite.album_id, favorite.photo_id))
+        .collect();
+    let listing = match bucket_photos(&state, ctx.token(), &bucket.id, ids).await {
+        Ok(listing) => listing,
+        Err(err) => return handle_error(&state, Some(actor.clone()), &pref, err.into(), true),
+    };
+    for photo_id in listing.missing.iter() {
+        let _ = state.favorites.unstar(&actor.user.id, photo_id).await;
+    }
+
+    let mut tokens: HashMap<String, String> = HashMap::new();
+    let mut items: Vec<FavoriteItem> = Vec::with_capacity(listing.photos.len());
+    for mut photo in listing.photos {
+        let token = tokens
+            .entry(photo.dir_id.clone())
+            .or_insert_with(|| {
+                create_csrf_token(&photo.dir_id, &config.jwt_secret).unwrap_or_default()
+            })
+            .clone();
+
+        photo.favorite = true;
+        items.push(FavoriteItem { photo, token });
+    }
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = "Favorites".to_string();
+
+    let tpl = FavoritesTemplate {
+        t,
+        bucket,
+        items,
+        truncated: listing.truncated,
+        failed_count: listing.failed_count,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
@@ -0,0 +1,271 @@
+use askama::Template;
+use axum::extract::Path;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::models::{
+    GuestUploadLink, GuestUploadLinkParams, NewGuestUploadLinkForm, RevokeGuestUploadLinkForm,
+    GUEST_UPLOAD_EXPIRY_DAYS, GUEST_UPLOAD_MAX_PHOTOS,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, create_guest_upload_token, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+use super::link_origin;
+
+#[derive(Template)]
+#[template(path = "widgets/album_guest_uploads.html")]
+struct AlbumGuestUploadsTemplate {
+    album: Album,
+    enabled: bool,
+    links: Vec<GuestUploadLinkItem>,
+    expiry_options: Vec<ExpiryOption>,
+    max_photos: u32,
+    token: String,
+    error_message: Option<String>,
+}
+
+struct ExpiryOption {
+    days: i64,
+    label: String,
+}
+
+struct GuestUploadLinkItem {
+    id: String,
+    url: String,
+    created_by: String,
+    expires: String,
+    uploads: u32,
+    max_uploads: u32,
+}
+
+/// Lists the album guest upload links with a form to create a new one
+pub async fn album_guest_uploads_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    render_guest_uploads(&state, &headers, album, StatusCode::OK, None).await
+}
+
+/// Creates an upload link with an expiry and a cap on the number of photos
+pub async fn post_album_guest_upload_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Form<NewGuestUploadLinkForm>>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return render_guest_uploads(
+            &state,
+            &headers,
+            album,
+            StatusCode::BAD_REQUEST,
+            Some("Invalid form data. Refresh the page and try again.".to_string()),
+        )
+        .await;
+    };
+
+    let result = create_guest_upload_link(&state, &album, &actor.user.username, &form).await;
+    match result {
+        Ok(_) => render_guest_uploads(&state, &headers, album, StatusCode::OK, None).await,
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_guest_uploads(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+            .await
+        }
+    }
+}
+
+/// Removes the link, guests can no longer upload with it
+pub async fn revoke_album_guest_upload_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<GuestUploadLinkParams>,
+    headers: HeaderMap,
+    payload: Option<Form<RevokeGuestUploadLinkForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return render_guest_uploads(
+            &state,
+            &headers,
+            album,
+            StatusCode::BAD_REQUEST,
+            Some("Invalid form data. Refresh the page and try again.".to_string()),
+        )
+        .await;
+    };
+
+    let result = revoke_guest_upload_link(&state, &album, &params.link_id, &form).await;
+    match result {
+        Ok(_) => render_guest_uploads(&state, &headers, album, StatusCode::OK, None).await,
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_guest_uploads(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+            .await
+        }
+    }
+}
+
+async fn create_guest_upload_link(
+    state: &AppState,
+    album: &Album,
+    created_by: &str,
+    form: &NewGuestUploadLinkForm,
+) -> Result<GuestUploadLink> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    if !state.guest_upload_account.is_enabled() {
+        return Err(Error::BadRequest(
+            "Guest uploads are not set up on this server.".to_string(),
+        ));
+    }
+    if !GUEST_UPLOAD_EXPIRY_DAYS.contains(&form.expires_in) {
+        return Err(Error::ValidationError(
+            "Choose when the link expires.".to_string(),
+        ));
+    }
+    if form.max_uploads == 0 || form.max_uploads > GUEST_UPLOAD_MAX_PHOTOS {
+        return Err(Error::ValidationError(format!(
+            "Max photos must be between 1 and {}.",
+            GUEST_UPLOAD_MAX_PHOTOS
+        )));
+    }
+
+    let now = Utc::now();
+    let link = GuestUploadLink {
+        id: Uuid::new_v4().to_string(),
+        bucket_id: album.bucket_id.clone(),
+        album_id: album.id.clone(),
+        created_by: created_by.to_string(),
+        created_at: now.timestamp(),
+        expires_at: (now + Duration::days(form.expires_in)).timestamp(),
+        max_uploads: form.max_uploads,
+        uploads: 0,
+    };
+    state.guest_upload_links.set(&link.id, link.clone()).await?;
+
+    Ok(link)
+}
+
+async fn revoke_guest_upload_link(
+    state: &AppState,
+    album: &Album,
+    link_id: &str,
+    form: &RevokeGuestUploadLinkForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    // Only links of this album can be revoked from here
+    match state.guest_upload_links.get(link_id) {
+        Some(link) if link.album_id == album.id => {
+            state.guest_upload_links.remove(link_id).await?;
+            Ok(())
+        }
+        _ => Err(Error::GuestUploadNotFound),
+    }
+}
+
+async fn render_guest_uploads(
+    state: &AppState,
+    headers: &HeaderMap,
+    album: Album,
+    status: StatusCode,
+    error_message: Option<String>,
+) -> Response<Body> {
+    let config = state.config.clone();
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize guest upload links form.".to_string(),
+        ));
+    };
+
+    let links = match state
+        .guest_upload_links
+        .album_links(&album.id, Utc::now().timestamp())
+        .await
+    {
+        Ok(links) => links,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let origin = link_origin(headers, config.ssl);
+    let mut items: Vec<GuestUploadLinkItem> = Vec::with_capacity(links.len());
+    for link in links.into_iter() {
+        let Ok(guest_token) =
+            create_guest_upload_token(&link.id, link.expires_at, &config.jwt_secret)
+        else {
+            return handle_error_message(Error::AnyError(
+                "Failed to initialize guest upload links.".to_string(),
+            ));
+        };
+        items.push(GuestUploadLinkItem {
+            id: link.id,
+            url: format!("{}/guest-upload/{}", origin, guest_token),
+            created_by: link.created_by,
+            expires: DateTime::from_timestamp(link.expires_at, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+            uploads: link.uploads,
+            max_uploads: link.max_uploads,
+        });
+    }
+
+    let tpl = AlbumGuestUploadsTemplate {
+        album,
+        enabled: state.guest_upload_account.is_enabled(),
+        links: items,
+        expiry_options: GUEST_UPLOAD_EXPIRY_DAYS
+            .iter()
+            .map(|days| ExpiryOption {
+                days: *days,
+                label: match days {
+                    1 => "Expires in 1 day".to_string(),
+                    days => format!("Expires in {} days", days),
+                },
+            })
+            .collect(),
+        max_photos: GUEST_UPLOAD_MAX_PHOTOS,
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
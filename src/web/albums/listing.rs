@@ -5,14 +5,12 @@ use axum::{
     response::Response,
     Extension,
 };
-use urlencoding::encode;
 
 use crate::{
     ctx::Ctx,
-    models::{Album, ListAlbumsParams},
-    services::list_albums,
+    models::{Album, Bucket, ListAlbumsParams, Paginated, Pref},
     web::{enforce_policy, Action, ErrorInfo, Resource},
-    Error,
+    Error, Result,
 };
 use crate::{models::PaginationLinks, run::AppState};
 
@@ -20,43 +18,82 @@ use crate::{models::PaginationLinks, run::AppState};
 #[template(path = "widgets/albums.html")]
 struct AlbumsTemplate {
     error_message: Option<String>,
+    bucket: Bucket,
     albums: Vec<Album>,
     pagination: Option<PaginationLinks>,
     can_create: bool,
+    grid: bool,
+    /// Starred photos in the bucket, only listed on the first unfiltered page
+    favorites_count: Option<usize>,
 }
 
 pub async fn album_listing_handler(
     Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
     State(state): State<AppState>,
     Query(query): Query<ListAlbumsParams>,
 ) -> Response<Body> {
-    let config = state.config.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
 
     let mut tpl = AlbumsTemplate {
         error_message: None,
+        bucket: bucket.clone(),
         albums: Vec::new(),
         pagination: None,
         can_create: enforce_policy(actor, Resource::Album, Action::Create).is_ok(),
+        grid: pref.album_view == "grid",
+        favorites_count: None,
     };
 
-    let Some(bucket_id) = default_bucket_id else {
-        return build_error_response(tpl, Error::NoDefaultBucket);
+    let first_page = query.page.unwrap_or(1) <= 1;
+    let filtered = query.keyword.as_deref().is_some_and(|k| !k.is_empty());
+    if first_page && !filtered {
+        let favorites = state.favorites.bucket_favorites(&actor.user.id, &bucket.id);
+        tpl.favorites_count = Some(favorites.len());
+    }
+
+    let result = if query.is_custom() {
+        list_sorted_albums(&state, ctx.token(), &bucket.id, &query).await
+    } else {
+        state
+            .files
+            .list_albums(ctx.token(), &bucket.id, &query)
+            .await
     };
 
-    return match list_albums(&config.api_url, ctx.token(), &bucket_id, &query).await {
+    match result {
         Ok(albums) => {
-            let mut keyword_param: String = "".to_string();
-            if let Some(keyword) = &query.keyword {
-                keyword_param = format!("&keyword={}", encode(keyword).to_string());
-            }
             tpl.albums = albums.data;
-            tpl.pagination = Some(PaginationLinks::new(&albums.meta, "", &keyword_param));
+            tpl.pagination = Some(PaginationLinks::new(
+                &albums.meta,
+                "",
+                &query.filter_query(),
+            ));
             build_response(tpl)
         }
         Err(err) => build_error_response(tpl, err),
-    };
+    }
+}
+
+/// files-rs can only search by keyword, so we fetch every album
+/// then filter, sort and paginate them ourselves
+async fn list_sorted_albums(
+    state: &AppState,
+    token: &str,
+    bucket_id: &str,
+    query: &ListAlbumsParams,
+) -> Result<Paginated<Album>> {
+    let albums = state
+        .files
+        .list_all_albums(token, bucket_id, query.keyword.clone())
+        .await?;
+
+    let albums = query.apply(albums);
+    let page = query.page.unwrap_or(1) as i64;
+    let per_page = query.page_size() as i64;
+
+    Ok(Paginated::from_items(albums, page, per_page))
 }
 
 fn build_response(tpl: AlbumsTemplate) -> Response<Body> {
@@ -1,9 +1,33 @@
 mod add;
+mod cover;
 mod del;
+mod download;
 mod edit;
+mod favorites;
+mod guest_uploads;
 mod listing;
+mod moderation;
+mod passphrase;
+mod share;
+mod slideshow;
+mod tags;
+mod timeline;
+mod unlock;
+mod upload_settings;
 
 pub use add::*;
+pub use cover::*;
 pub use del::*;
+pub use download::*;
 pub use edit::*;
+pub use favorites::*;
+pub use guest_uploads::*;
 pub use listing::*;
+pub use moderation::*;
+pub use passphrase::*;
+pub use share::*;
+pub use slideshow::*;
+pub use tags::*;
+pub use timeline::*;
+pub use unlock::*;
+pub use upload_settings::*;
@@ -0,0 +1,397 @@
+use askama::Template;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use chrono::DateTime;
+
+use crate::models::{
+    Actor, AlbumModerationForm, ModerateUploadsForm, ModerationAction, ModerationResult,
+    Permission, PhotoMeta, Pref, StagedUpload, StagedUploadParams, TemplateData,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{
+    enforce_policy, handle_error, handle_error_message, upload_and_index_photo, Action, ErrorInfo,
+    Resource,
+};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/album_moderation.html")]
+struct AlbumModerationTemplate {
+    album: Album,
+    moderated: bool,
+    waiting: usize,
+    can_review: bool,
+    token: String,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/moderation.html")]
+struct ModerationPageTemplate {
+    t: TemplateData,
+    album: Album,
+    moderated: bool,
+    uploads: Vec<StagedUploadItem>,
+    token: String,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/moderation_result.html")]
+struct ModerationResultTemplate {
+    results: Vec<ModerationResult>,
+    approve: bool,
+    done_count: usize,
+    failed_count: usize,
+}
+
+struct StagedUploadItem {
+    id: String,
+    filename: String,
+    uploaded_by: String,
+    guest: bool,
+    is_image: bool,
+    uploaded: String,
+}
+
+/// Keeps a single request from tying up the server for too long
+const MAX_MODERATE_UPLOADS: usize = 100;
+
+/// Uploads to a moderated album wait for review unless the uploader can edit photos.
+/// Guests have no permissions of their own.
+pub fn needs_review(state: &AppState, album_id: &str, actor: Option<&Actor>) -> bool {
+    let moderated = state
+        .album_meta
+        .get(album_id)
+        .is_some_and(|meta| meta.moderated);
+
+    moderated && !actor.is_some_and(|actor| actor.has_permissions(&vec![Permission::FilesEdit]))
+}
+
+/// Shows whether uploads are reviewed with a form to turn it on or off
+pub async fn album_moderation_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    render_moderation(&state, &ctx, album, StatusCode::OK, None)
+}
+
+/// Turns moderation on or off, uploads already waiting stay in the queue
+pub async fn post_album_moderation_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    payload: Option<Form<AlbumModerationForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let result = match payload {
+        Some(Form(form)) => set_moderated(&state, &album, &form).await,
+        None => Err(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        )),
+    };
+
+    match result {
+        Ok(_) => render_moderation(&state, &ctx, album, StatusCode::OK, None),
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_moderation(
+                &state,
+                &ctx,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+        }
+    }
+}
+
+/// Lists the uploads waiting for review
+pub async fn moderation_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_review_policy(actor) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), true);
+    }
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize review uploads form.".to_string());
+        return handle_error(&state, Some(actor.clone()), &pref, error, true);
+    };
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = format!("Photos - {} - Review Uploads", &album.label);
+
+    let uploads = state
+        .staging
+        .album_uploads(&album.id)
+        .into_iter()
+        .map(|upload| StagedUploadItem {
+            is_image: upload.is_previewable(),
+            uploaded: DateTime::from_timestamp(upload.created_at, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+            id: upload.id,
+            filename: upload.filename,
+            uploaded_by: upload.uploaded_by,
+            guest: upload.guest,
+        })
+        .collect();
+
+    let tpl = ModerationPageTemplate {
+        t,
+        moderated: state
+            .album_meta
+            .get(&album.id)
+            .is_some_and(|meta| meta.moderated),
+        album,
+        uploads,
+        token,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Approves or rejects the selected uploads one by one, reporting each outcome.
+/// Approved files are sent to files-rs, rejected ones are deleted.
+pub async fn post_moderation_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    payload: Option<Form<Vec<(String, String)>>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_review_policy(ctx.actor()) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(pairs)) = payload else {
+        return handle_error_message(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        ));
+    };
+    let form = ModerateUploadsForm::from_pairs(pairs);
+
+    let action = match validate_moderation(&state, &album, &form) {
+        Ok(action) => action,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let mut results: Vec<ModerationResult> = Vec::new();
+    for staged_id in form.staged_ids.iter() {
+        results.push(moderate_upload(&state, &ctx, &album, staged_id, action).await);
+    }
+
+    let failed_count = results.iter().filter(|r| r.error.is_some()).count();
+    let tpl = ModerationResultTemplate {
+        done_count: results.len() - failed_count,
+        failed_count,
+        results,
+        approve: action == ModerationAction::Approve,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Serves a staged file so reviewers can see what they approve
+pub async fn staged_upload_file_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<StagedUploadParams>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    let result = enforce_review_policy(actor).and_then(|_| {
+        find_staged_upload(&state, &album, &params.staged_id).ok_or(Error::UploadNotFound)
+    });
+    let staged = match result {
+        Ok(staged) => staged,
+        Err(err) => return handle_error(&state, Some(actor.clone()), &pref, err.into(), false),
+    };
+
+    let body = match state.staging.file_body(&staged).await {
+        Ok(body) => body,
+        Err(err) => return handle_error(&state, Some(actor.clone()), &pref, err.into(), false),
+    };
+
+    // Anything but plain images is offered as a download
+    let (content_type, disposition) = if staged.is_previewable() {
+        (staged.content_type.clone(), "inline".to_string())
+    } else {
+        (
+            "application/octet-stream".to_string(),
+            format!("attachment; filename=\"{}\"", staged.filename),
+        )
+    };
+
+    Response::builder()
+        .status(200)
+        .header("Content-Type", content_type)
+        .header("Content-Disposition", disposition)
+        .header("Content-Length", staged.size.to_string())
+        .header("X-Content-Type-Options", "nosniff")
+        .header("Cache-Control", "private, no-store")
+        .body(body)
+        .unwrap()
+}
+
+/// Reviewers decide what goes into the album and what gets deleted
+pub fn enforce_review_policy(actor: &Actor) -> Result<()> {
+    enforce_policy(actor, Resource::Photo, Action::Update)?;
+    enforce_policy(actor, Resource::Photo, Action::Delete)
+}
+
+async fn set_moderated(state: &AppState, album: &Album, form: &AlbumModerationForm) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    let mut meta = state.album_meta.get(&album.id).unwrap_or_default();
+    meta.moderated = form.moderated.is_some();
+    if meta.is_empty() {
+        state.album_meta.remove(&album.id).await?;
+    } else {
+        state.album_meta.set(&album.id, meta).await?;
+    }
+    Ok(())
+}
+
+fn validate_moderation(
+    state: &AppState,
+    album: &Album,
+    form: &ModerateUploadsForm,
+) -> Result<ModerationAction> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    let Some(action) = form.action else {
+        return Err(Error::ValidationError(
+            "Choose to approve or reject.".to_string(),
+        ));
+    };
+    if form.staged_ids.is_empty() {
+        return Err(Error::ValidationError(
+            "Select at least one upload.".to_string(),
+        ));
+    }
+    if form.staged_ids.len() > MAX_MODERATE_UPLOADS {
+        return Err(Error::ValidationError(format!(
+            "Select at most {} uploads at a time.",
+            MAX_MODERATE_UPLOADS
+        )));
+    }
+
+    Ok(action)
+}
+
+/// Only uploads staged for this album can be reviewed from it
+fn find_staged_upload(state: &AppState, album: &Album, staged_id: &str) -> Option<StagedUpload> {
+    state
+        .staging
+        .get(staged_id)
+        .filter(|staged| staged.album_id == album.id)
+}
+
+async fn moderate_upload(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    staged_id: &str,
+    action: ModerationAction,
+) -> ModerationResult {
+    let mut result = ModerationResult {
+        staged_id: staged_id.to_string(),
+        filename: staged_id.to_string(),
+        error: None,
+    };
+
+    let Some(staged) = find_staged_upload(state, album, staged_id) else {
+        result.error = Some(Error::UploadNotFound.to_string());
+        return result;
+    };
+    result.filename = staged.filename.clone();
+
+    let outcome = match action {
+        ModerationAction::Approve => approve_upload(state, ctx, album, &staged).await,
+        ModerationAction::Reject => state.staging.remove(&staged.id).await,
+    };
+    if let Err(err) = outcome {
+        result.error = Some(err.to_string());
+    }
+
+    result
+}
+
+async fn approve_upload(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    staged: &StagedUpload,
+) -> Result<()> {
+    let (content_type, length, body) = state.staging.multipart_body(staged).await?;
+    let photo =
+        upload_and_index_photo(state, ctx.token(), album, &content_type, Some(length), body)
+            .await?;
+
+    if staged.guest {
+        let meta = PhotoMeta {
+            uploaded_by: Some(staged.uploaded_by.clone()),
+            ..Default::default()
+        };
+        let _ = state.photo_meta.set(&photo.id, meta).await;
+    }
+
+    // The photo is in the album, a leftover file is cleaned up on the next try
+    let _ = state.staging.remove(&staged.id).await;
+    Ok(())
+}
+
+fn render_moderation(
+    state: &AppState,
+    ctx: &Ctx,
+    album: Album,
+    status: StatusCode,
+    error_message: Option<String>,
+) -> Response<Body> {
+    let Ok(token) = create_csrf_token(&album.id, &state.config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize moderation form.".to_string(),
+        ));
+    };
+
+    let tpl = AlbumModerationTemplate {
+        moderated: state
+            .album_meta
+            .get(&album.id)
+            .is_some_and(|meta| meta.moderated),
+        waiting: state.staging.album_uploads(&album.id).len(),
+        can_review: enforce_review_policy(ctx.actor()).is_ok(),
+        album,
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
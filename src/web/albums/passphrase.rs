@@ -0,0 +1,212 @@
+use askama::Template;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{AlbumPassphrase, AlbumPassphraseForm, RemovePassphraseForm};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, hash_passphrase, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+use super::link_origin;
+
+#[derive(Template)]
+#[template(path = "widgets/album_passphrase_form.html")]
+struct AlbumPassphraseTemplate {
+    album: Album,
+    enabled: bool,
+    passphrase: Option<AlbumPassphrase>,
+    unlock_url: String,
+    token: String,
+    error_message: Option<String>,
+}
+
+/// Shows whether the album is protected with a form to set a new passphrase
+pub async fn album_passphrase_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    render_passphrase(&state, &headers, album, StatusCode::OK, None)
+}
+
+/// Sets or replaces the passphrase, visitors have to unlock the album again
+pub async fn post_album_passphrase_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Form<AlbumPassphraseForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return render_passphrase(
+            &state,
+            &headers,
+            album,
+            StatusCode::BAD_REQUEST,
+            Some("Invalid form data. Refresh the page and try again.".to_string()),
+        );
+    };
+
+    match set_passphrase(&state, &album, &form).await {
+        Ok(_) => render_passphrase(&state, &headers, album, StatusCode::OK, None),
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_passphrase(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+        }
+    }
+}
+
+/// Removes the passphrase, only members can view the album afterwards
+pub async fn remove_album_passphrase_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Form<RemovePassphraseForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let result = match payload {
+        Some(Form(form)) => remove_passphrase(&state, &album, &form).await,
+        None => Err(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        )),
+    };
+
+    match result {
+        Ok(_) => render_passphrase(&state, &headers, album, StatusCode::OK, None),
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_passphrase(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+        }
+    }
+}
+
+async fn set_passphrase(state: &AppState, album: &Album, form: &AlbumPassphraseForm) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    if !state.share_account.is_enabled() {
+        return Err(Error::BadRequest(
+            "Sharing is not set up on this server.".to_string(),
+        ));
+    }
+    if form.validate().is_err() {
+        return Err(Error::ValidationError(
+            "Passphrase must be 8 to 100 characters long.".to_string(),
+        ));
+    }
+
+    let max_views = match form.max_views.trim() {
+        "" => None,
+        value => match value.parse::<u32>() {
+            Ok(max) if max > 0 => Some(max),
+            _ => {
+                return Err(Error::ValidationError(
+                    "Max views must be a positive number.".to_string(),
+                ))
+            }
+        },
+    };
+
+    // Argon2 is slow on purpose, keep it off the async workers
+    let secret = form.passphrase.clone();
+    let hash = match tokio::task::spawn_blocking(move || hash_passphrase(&secret)).await {
+        Ok(hash) => hash?,
+        Err(_) => return Err("Unable to set the passphrase.".into()),
+    };
+
+    let passphrase = AlbumPassphrase {
+        bucket_id: album.bucket_id.clone(),
+        hash,
+        version: Uuid::new_v4().to_string(),
+        max_views,
+        views: 0,
+        no_downloads: form.no_downloads.is_some(),
+    };
+
+    let mut meta = state.album_meta.get(&album.id).unwrap_or_default();
+    meta.passphrase = Some(passphrase);
+    state.album_meta.set(&album.id, meta).await
+}
+
+async fn remove_passphrase(
+    state: &AppState,
+    album: &Album,
+    form: &RemovePassphraseForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    let Some(mut meta) = state.album_meta.get(&album.id) else {
+        return Ok(());
+    };
+    meta.passphrase = None;
+    if meta.is_empty() {
+        state.album_meta.remove(&album.id).await?;
+    } else {
+        state.album_meta.set(&album.id, meta).await?;
+    }
+    Ok(())
+}
+
+fn render_passphrase(
+    state: &AppState,
+    headers: &HeaderMap,
+    album: Album,
+    status: StatusCode,
+    error_message: Option<String>,
+) -> Response<Body> {
+    let config = state.config.clone();
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize passphrase form.".to_string(),
+        ));
+    };
+
+    let tpl = AlbumPassphraseTemplate {
+        enabled: state.share_account.is_enabled(),
+        passphrase: state
+            .album_meta
+            .get(&album.id)
+            .and_then(|meta| meta.passphrase),
+        unlock_url: format!(
+            "{}/albums/{}/unlock",
+            link_origin(headers, config.ssl),
+            album.id
+        ),
+        album,
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
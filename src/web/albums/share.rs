@@ -0,0 +1,263 @@
+use askama::Template;
+use axum::extract::Path;
+use axum::http::header::HOST;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::models::{
+    NewShareLinkForm, RevokeShareLinkForm, ShareLink, ShareLinkParams, SHARE_EXPIRY_DAYS,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, create_share_token, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/album_shares.html")]
+struct AlbumSharesTemplate {
+    album: Album,
+    enabled: bool,
+    links: Vec<ShareLinkItem>,
+    expiry_options: Vec<ExpiryOption>,
+    token: String,
+    error_message: Option<String>,
+}
+
+struct ExpiryOption {
+    days: i64,
+    label: String,
+}
+
+struct ShareLinkItem {
+    id: String,
+    url: String,
+    created_by: String,
+    expires: String,
+}
+
+/// Lists the album share links with a form to create a new one
+pub async fn album_shares_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    render_shares(&state, &headers, album, StatusCode::OK, None).await
+}
+
+/// Creates a share link that expires after the chosen number of days
+pub async fn post_album_share_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Form<NewShareLinkForm>>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return render_shares(
+            &state,
+            &headers,
+            album,
+            StatusCode::BAD_REQUEST,
+            Some("Invalid form data. Refresh the page and try again.".to_string()),
+        )
+        .await;
+    };
+
+    let result = create_share_link(&state, &album, &actor.user.username, &form).await;
+    match result {
+        Ok(_) => render_shares(&state, &headers, album, StatusCode::OK, None).await,
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_shares(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+            .await
+        }
+    }
+}
+
+/// Removes the link, anyone still holding it loses access right away
+pub async fn revoke_album_share_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<ShareLinkParams>,
+    headers: HeaderMap,
+    payload: Option<Form<RevokeShareLinkForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(form)) = payload else {
+        return render_shares(
+            &state,
+            &headers,
+            album,
+            StatusCode::BAD_REQUEST,
+            Some("Invalid form data. Refresh the page and try again.".to_string()),
+        )
+        .await;
+    };
+
+    let result = revoke_share_link(&state, &album, &params.share_id, &form).await;
+    match result {
+        Ok(_) => render_shares(&state, &headers, album, StatusCode::OK, None).await,
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_shares(
+                &state,
+                &headers,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+            .await
+        }
+    }
+}
+
+async fn create_share_link(
+    state: &AppState,
+    album: &Album,
+    created_by: &str,
+    form: &NewShareLinkForm,
+) -> Result<ShareLink> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    if !state.share_account.is_enabled() {
+        return Err(Error::BadRequest(
+            "Sharing is not set up on this server.".to_string(),
+        ));
+    }
+    if !SHARE_EXPIRY_DAYS.contains(&form.expires_in) {
+        return Err(Error::ValidationError(
+            "Choose when the link expires.".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let link = ShareLink {
+        id: Uuid::new_v4().to_string(),
+        bucket_id: album.bucket_id.clone(),
+        album_id: album.id.clone(),
+        created_by: created_by.to_string(),
+        created_at: now.timestamp(),
+        expires_at: (now + Duration::days(form.expires_in)).timestamp(),
+    };
+    state.share_links.set(&link.id, link.clone()).await?;
+
+    Ok(link)
+}
+
+async fn revoke_share_link(
+    state: &AppState,
+    album: &Album,
+    share_id: &str,
+    form: &RevokeShareLinkForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    // Only links of this album can be revoked from here
+    match state.share_links.get(share_id) {
+        Some(link) if link.album_id == album.id => {
+            state.share_links.remove(share_id).await?;
+            Ok(())
+        }
+        _ => Err(Error::ShareNotFound),
+    }
+}
+
+async fn render_shares(
+    state: &AppState,
+    headers: &HeaderMap,
+    album: Album,
+    status: StatusCode,
+    error_message: Option<String>,
+) -> Response<Body> {
+    let config = state.config.clone();
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize share links form.".to_string(),
+        ));
+    };
+
+    let links = match state
+        .share_links
+        .album_links(&album.id, Utc::now().timestamp())
+        .await
+    {
+        Ok(links) => links,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let origin = link_origin(headers, config.ssl);
+    let mut items: Vec<ShareLinkItem> = Vec::with_capacity(links.len());
+    for link in links.into_iter() {
+        let Ok(share_token) = create_share_token(&link.id, link.expires_at, &config.jwt_secret)
+        else {
+            return handle_error_message(Error::AnyError(
+                "Failed to initialize share links.".to_string(),
+            ));
+        };
+        items.push(ShareLinkItem {
+            id: link.id,
+            url: format!("{}/share/{}", origin, share_token),
+            created_by: link.created_by,
+            expires: DateTime::from_timestamp(link.expires_at, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        });
+    }
+
+    let tpl = AlbumSharesTemplate {
+        album,
+        enabled: state.share_account.is_enabled(),
+        links: items,
+        expiry_options: SHARE_EXPIRY_DAYS
+            .iter()
+            .map(|days| ExpiryOption {
+                days: *days,
+                label: match days {
+                    1 => "Expires in 1 day".to_string(),
+                    days => format!("Expires in {} days", days),
+                },
+            })
+            .collect(),
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Links are handed out to other people so they need the full address
+pub fn link_origin(headers: &HeaderMap, ssl: bool) -> String {
+    let scheme = if ssl { "https" } else { "http" };
+    match headers.get(HOST).and_then(|host| host.to_str().ok()) {
+        Some(host) => format!("{}://{}", scheme, host),
+        None => "".to_string(),
+    }
+}
@@ -0,0 +1,103 @@
+use askama::Template;
+use axum::extract::Query;
+use axum::http::header::CONTENT_TYPE;
+use axum::{body::Body, extract::State, response::Response, Extension};
+
+use crate::models::{Playlist, Pref, SlideshowParams, TemplateData, SLIDESHOW_INTERVALS};
+use crate::run::AppState;
+use crate::web::handle_error_message;
+use crate::{ctx::Ctx, models::Album};
+
+#[derive(Template)]
+#[template(path = "pages/slideshow.html")]
+struct SlideshowTemplate {
+    t: TemplateData,
+    album: Album,
+    intervals: Vec<IntervalOption>,
+    interval: u32,
+    shuffle: bool,
+    repeat: bool,
+    playlist_url: String,
+}
+
+struct IntervalOption {
+    value: u32,
+    selected: bool,
+}
+
+/// Plays the album fullscreen, photos are loaded from the playlist
+pub async fn slideshow_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<SlideshowParams>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let mut t = TemplateData::new(&state, Some(ctx.actor().clone()), &pref);
+
+    t.title = format!("Photos - {} - Slideshow", &album.label);
+    t.scripts = vec![config.assets.slideshow_js.clone()];
+
+    let interval = query.interval();
+    let tpl = SlideshowTemplate {
+        t,
+        intervals: SLIDESHOW_INTERVALS
+            .iter()
+            .map(|value| IntervalOption {
+                value: *value,
+                selected: *value == interval,
+            })
+            .collect(),
+        interval,
+        shuffle: query.shuffle(),
+        repeat: query.repeat(),
+        playlist_url: format!(
+            "/buckets/{}/albums/{}/slideshow/playlist?{}",
+            album.bucket_id,
+            album.id,
+            query.to_query()
+        ),
+        album,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Lists every photo in the album so the slideshow can prefetch the next ones
+pub async fn slideshow_playlist_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<SlideshowParams>,
+) -> Response<Body> {
+    let result = state
+        .files
+        .list_all_photos(ctx.token(), &album.bucket_id, &album.id)
+        .await;
+    let photos = match result {
+        Ok(photos) => photos,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let playlist = Playlist {
+        interval: query.interval(),
+        shuffle: query.shuffle(),
+        repeat: query.repeat(),
+        slides: photos
+            .into_iter()
+            .filter(|photo| photo.is_image())
+            .map(|photo| photo.into())
+            .collect(),
+    };
+
+    Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/json")
+        .header("Cache-Control", "private, no-store")
+        .body(Body::from(serde_json::to_string(&playlist).unwrap()))
+        .unwrap()
+}
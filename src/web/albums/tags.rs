@@ -0,0 +1,106 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    response::Response,
+    Extension,
+};
+
+use crate::ctx::Ctx;
+use crate::models::{Bucket, Photo, Pref, TagCount, TagParams, TemplateData};
+use crate::run::AppState;
+use crate::web::{bucket_photos, enforce_policy, handle_error, Action, Resource};
+
+#[derive(Template)]
+#[template(path = "pages/tags.html")]
+struct TagsTemplate {
+    t: TemplateData,
+    bucket: Bucket,
+    tags: Vec<TagCount>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/tag.html")]
+struct TagTemplate {
+    t: TemplateData,
+    bucket: Bucket,
+    tag: String,
+    photos: Vec<Photo>,
+    truncated: bool,
+    failed_count: usize,
+}
+
+/// Every tag used in the bucket
+pub async fn tags_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Read) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), true);
+    }
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = "Tags".to_string();
+
+    let tpl = TagsTemplate {
+        t,
+        tags: state.photo_tags.bucket_tags(&bucket.id),
+        bucket,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Photos across every album in the bucket having the tag.
+/// Photos deleted in the meantime lose their tags.
+pub async fn tag_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+    Path(params): Path<TagParams>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Read) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), true);
+    }
+
+    let ids = state
+        .photo_tags
+        .tagged_photos(&bucket.id, &params.tag)
+        .into_iter()
+        .map(|(photo_id, entry)| (entry.album_id, photo_id))
+        .collect();
+    let listing = match bucket_photos(&state, ctx.token(), &bucket.id, ids).await {
+        Ok(listing) => listing,
+        Err(err) => return handle_error(&state, Some(actor.clone()), &pref, err.into(), true),
+    };
+    for photo_id in listing.missing.iter() {
+        let _ = state.photo_tags.remove(photo_id).await;
+    }
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = format!("Tag: {}", params.tag);
+
+    let tpl = TagTemplate {
+        t,
+        bucket,
+        tag: params.tag,
+        photos: listing.photos,
+        truncated: listing.truncated,
+        failed_count: listing.failed_count,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
@@ -0,0 +1,165 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    response::Response,
+    Extension,
+};
+use futures_util::{stream, StreamExt};
+
+use crate::models::{
+    sort_timeline, timeline_entries, Bucket, Paginated, Pref, TemplateData, TimelineEntry,
+    TimelineParams, TimelinePhoto, TIMELINE_PAGE_SIZE,
+};
+use crate::run::AppState;
+use crate::web::{enforce_policy, handle_error, handle_error_message, Action, Resource};
+use crate::{ctx::Ctx, Result};
+
+/// Albums listed from files-rs at once while building the timeline
+const TIMELINE_ALBUM_CONCURRENCY: usize = 4;
+
+#[derive(Template)]
+#[template(path = "pages/timeline.html")]
+struct TimelineTemplate {
+    t: TemplateData,
+    bucket: Bucket,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/timeline_grid.html")]
+struct TimelineGridTemplate {
+    theme: String,
+    bucket: Bucket,
+    entries: Vec<TimelineEntry>,
+    next_page: Option<i64>,
+    last_item: String,
+    last_month: String,
+}
+
+/// Photos of every album in the bucket, newest first
+pub async fn timeline_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Read) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), true);
+    }
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = "Timeline".to_string();
+
+    let tpl = TimelineTemplate { t, bucket };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Renders a page of the timeline, loaded on scroll like the photo grid.
+/// The first page walks every album again, later pages reuse that walk.
+pub async fn timeline_grid_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+    Query(query): Query<TimelineParams>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Read) {
+        return handle_error_message(err);
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let cached = match page {
+        1 => None,
+        _ => state.timeline.get(&actor.user.id, &bucket.id),
+    };
+    let items = match cached {
+        Some(items) => items,
+        None => match build_timeline(&state, &ctx, &bucket).await {
+            Ok(items) => {
+                state
+                    .timeline
+                    .insert(&actor.user.id, &bucket.id, items.clone());
+                items
+            }
+            Err(err) => return handle_error_message(err),
+        },
+    };
+
+    let listing = Paginated::from_items(items, page as i64, TIMELINE_PAGE_SIZE as i64);
+    let mut items = listing.data;
+    for item in items.iter_mut() {
+        state.photo_meta.fill(std::slice::from_mut(&mut item.photo));
+    }
+
+    let last_item = items
+        .last()
+        .map(|item| item.photo.id.clone())
+        .unwrap_or_default();
+    let last_month = items.last().map(|item| item.month()).unwrap_or_default();
+    let next_page = (listing.meta.total_pages > listing.meta.page).then_some(listing.meta.page + 1);
+
+    let tpl = TimelineGridTemplate {
+        theme: pref.theme,
+        bucket,
+        entries: timeline_entries(items, query.month.as_deref()),
+        next_page,
+        last_item,
+        last_month,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Merges the photos of every non-empty album into a single list
+async fn build_timeline(
+    state: &AppState,
+    ctx: &Ctx,
+    bucket: &Bucket,
+) -> Result<Vec<TimelinePhoto>> {
+    let albums = state
+        .files
+        .list_all_albums(ctx.token(), &bucket.id, None)
+        .await?;
+
+    let listings: Vec<Result<Vec<TimelinePhoto>>> = stream::iter(albums)
+        .filter(|album| std::future::ready(album.file_count > 0))
+        .map(|album| async move {
+            let mut photos = state
+                .files
+                .list_all_photos(ctx.token(), &bucket.id, &album.id)
+                .await?;
+            // Videos and other files have no thumbnails to show
+            photos.retain(|photo| photo.is_image());
+            state.photo_exif.fill(&mut photos).await;
+
+            Ok(photos
+                .into_iter()
+                .map(|photo| TimelinePhoto {
+                    photo,
+                    album_label: album.label.clone(),
+                })
+                .collect())
+        })
+        .buffered(TIMELINE_ALBUM_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut items: Vec<TimelinePhoto> = Vec::new();
+    for listing in listings {
+        items.extend(listing?);
+    }
+
+    sort_timeline(&mut items);
+    Ok(items)
+}
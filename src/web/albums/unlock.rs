@@ -0,0 +1,299 @@
+use askama::Template;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Path, Query};
+use axum::http::{HeaderMap, StatusCode};
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use axum_extra::extract::CookieJar;
+use tower_cookies::{cookie::time::Duration, Cookie, Cookies};
+
+use crate::models::{
+    AlbumParams, AlbumPassphrase, ListPhotosParams, Pref, TemplateData, UnlockAlbumForm,
+};
+use crate::run::AppState;
+use crate::services::{
+    create_csrf_token, create_unlock_token, verify_csrf_subject, verify_passphrase,
+    verify_unlock_token,
+};
+use crate::web::{
+    handle_error, handle_error_message, render_photo_grid, render_read_only_page, GridView,
+    UNLOCK_COOKIE,
+};
+use crate::{Error, Result};
+
+#[derive(Template)]
+#[template(path = "pages/unlock_album.html")]
+struct UnlockAlbumTemplate {
+    t: TemplateData,
+    album_id: String,
+    token: String,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/unlock_album_form.html")]
+struct UnlockAlbumFormTemplate {
+    album_id: String,
+    token: String,
+    error_message: Option<String>,
+}
+
+/// Shows the album to visitors who entered the passphrase, or asks for it.
+/// Every album page load counts as a view.
+pub async fn unlock_album_handler(
+    Extension(pref): Extension<Pref>,
+    State(state): State<AppState>,
+    Path(params): Path<AlbumParams>,
+    Query(query): Query<ListPhotosParams>,
+    cookies: CookieJar,
+) -> Response<Body> {
+    let album_id = params.album_id.expect("album_id is required");
+
+    let passphrase = match find_passphrase(&state, &album_id) {
+        Ok(passphrase) => passphrase,
+        Err(err) => return handle_error(&state, None, &pref, err.into(), true),
+    };
+
+    if is_unlocked(&state, &cookies, &album_id, &passphrase) {
+        let result = state
+            .share_account
+            .get_album(state.files.as_ref(), &passphrase.bucket_id, &album_id)
+            .await;
+        let album = match result {
+            Ok((_, album)) => album,
+            Err(err) => return handle_error(&state, None, &pref, err.into(), true),
+        };
+
+        return match state.album_meta.add_passphrase_view(&album_id).await {
+            Ok(true) => {
+                let base_url = format!("/albums/{}/unlock", album_id);
+                render_read_only_page(&state, &pref, album, &query, base_url)
+            }
+            Ok(false) => handle_error(&state, None, &pref, no_views_left().into(), true),
+            Err(err) => handle_error(&state, None, &pref, err.into(), true),
+        };
+    }
+
+    if !passphrase.views_left() {
+        return handle_error(&state, None, &pref, no_views_left().into(), true);
+    }
+
+    let Ok(token) = create_csrf_token(&unlock_subject(&album_id), &state.config.jwt_secret) else {
+        return handle_error(
+            &state,
+            None,
+            &pref,
+            Error::AnyError("Failed to initialize unlock form.".to_string()).into(),
+            true,
+        );
+    };
+
+    let mut t = TemplateData::new(&state, None, &pref);
+    t.title = "Protected album".to_string();
+
+    let tpl = UnlockAlbumTemplate {
+        t,
+        album_id,
+        token,
+        error_message: None,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Checks the passphrase and remembers the unlock for this album only
+pub async fn post_unlock_album_handler(
+    State(state): State<AppState>,
+    Path(params): Path<AlbumParams>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    payload: Option<Form<UnlockAlbumForm>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let album_id = params.album_id.expect("album_id is required");
+
+    let passphrase = match find_passphrase(&state, &album_id) {
+        Ok(passphrase) => passphrase,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let Ok(token) = create_csrf_token(&unlock_subject(&album_id), &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize unlock form.".to_string(),
+        ));
+    };
+
+    let address = client_address(&config.trusted_proxies, peer, &headers);
+    let attempts_key = format!("{}:{}", album_id, address);
+    let result = match payload {
+        Some(Form(form)) => {
+            check_passphrase(&state, &album_id, &passphrase, &attempts_key, form).await
+        }
+        None => Err(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        )),
+    };
+
+    if let Err(err) = result {
+        let tpl = UnlockAlbumFormTemplate {
+            album_id,
+            token,
+            error_message: Some(err.to_string()),
+        };
+        return Response::builder()
+            .status(StatusCode::from(err))
+            .body(Body::from(tpl.render().unwrap()))
+            .unwrap();
+    }
+
+    let base_url = format!("/albums/{}/unlock", album_id);
+    let subject = format!("{}:{}", album_id, passphrase.version);
+    let Ok(unlock_token) = create_unlock_token(&subject, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError("Failed to unlock the album.".to_string()));
+    };
+
+    let unlock_cookie = Cookie::build((UNLOCK_COOKIE, unlock_token))
+        .http_only(true)
+        .max_age(Duration::hours(12))
+        .secure(config.ssl)
+        .path(base_url.clone())
+        .build();
+
+    cookies.add(unlock_cookie);
+
+    Response::builder()
+        .status(200)
+        .header("HX-Redirect", base_url)
+        .body(Body::from("".to_string()))
+        .unwrap()
+}
+
+pub async fn unlocked_photo_listing_handler(
+    Extension(pref): Extension<Pref>,
+    State(state): State<AppState>,
+    Path(params): Path<AlbumParams>,
+    Query(query): Query<ListPhotosParams>,
+    cookies: CookieJar,
+) -> Response<Body> {
+    let album_id = params.album_id.expect("album_id is required");
+
+    let passphrase = match find_passphrase(&state, &album_id) {
+        Ok(passphrase) => passphrase,
+        Err(err) => return handle_error(&state, None, &pref, err.into(), false),
+    };
+
+    if !is_unlocked(&state, &cookies, &album_id, &passphrase) {
+        let err = Error::Forbidden("Enter the passphrase to view this album.".to_string());
+        return handle_error(&state, None, &pref, err.into(), false);
+    }
+
+    let result = state
+        .share_account
+        .get_album(state.files.as_ref(), &passphrase.bucket_id, &album_id)
+        .await;
+    let (token, album) = match result {
+        Ok(found) => found,
+        Err(err) => return handle_error(&state, None, &pref, err.into(), false),
+    };
+
+    let view = GridView {
+        base_url: format!("/albums/{}/unlock", album_id),
+        read_only: true,
+        no_downloads: passphrase.no_downloads,
+        user_id: None,
+        can_edit_photos: false,
+        can_add_photos: false,
+        can_delete_photos: false,
+    };
+    render_photo_grid(&state, &token, pref.theme, album, &query, view).await
+}
+
+/// Albums without a passphrase look the same as missing ones
+fn find_passphrase(state: &AppState, album_id: &str) -> Result<AlbumPassphrase> {
+    if !state.share_account.is_enabled() {
+        return Err(Error::AlbumNotFound);
+    }
+
+    state
+        .album_meta
+        .get(album_id)
+        .and_then(|meta| meta.passphrase)
+        .ok_or(Error::AlbumNotFound)
+}
+
+async fn check_passphrase(
+    state: &AppState,
+    album_id: &str,
+    passphrase: &AlbumPassphrase,
+    attempts_key: &str,
+    form: UnlockAlbumForm,
+) -> Result<()> {
+    verify_csrf_subject(
+        &form.token,
+        &unlock_subject(album_id),
+        &state.config.jwt_secret,
+    )?;
+
+    if !state.unlock_attempts.try_attempt(attempts_key) {
+        return Err(Error::TooManyRequests(
+            "Too many wrong passphrases. Try again in a few minutes.".to_string(),
+        ));
+    }
+
+    // Argon2 is slow on purpose, keep it off the async workers
+    let hash = passphrase.hash.clone();
+    let verified = tokio::task::spawn_blocking(move || verify_passphrase(&form.passphrase, &hash))
+        .await
+        .unwrap_or(false);
+    if !verified {
+        return Err(Error::ValidationError("Wrong passphrase.".to_string()));
+    }
+    state.unlock_attempts.reset(attempts_key);
+
+    if !passphrase.views_left() {
+        return Err(no_views_left());
+    }
+    Ok(())
+}
+
+/// Unlocks stop working once the passphrase is changed or removed
+fn is_unlocked(
+    state: &AppState,
+    cookies: &CookieJar,
+    album_id: &str,
+    passphrase: &AlbumPassphrase,
+) -> bool {
+    let Some(cookie) = cookies.get(UNLOCK_COOKIE) else {
+        return false;
+    };
+
+    let expected = format!("{}:{}", album_id, passphrase.version);
+    verify_unlock_token(cookie.value(), &state.config.jwt_secret) == Some(expected)
+}
+
+/// Visitors get their own CSRF subject, album tokens let members change the album
+fn unlock_subject(album_id: &str) -> String {
+    format!("unlock:{}", album_id)
+}
+
+fn no_views_left() -> Error {
+    Error::Forbidden("This album can no longer be viewed.".to_string())
+}
+
+/// Visitor address from `X-Real-IP` when a trusted proxy like nginx sent the
+/// request, otherwise the connecting address. See the README.
+fn client_address(trusted_proxies: &[IpAddr], peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    if !trusted_proxies.contains(&peer.ip()) {
+        return peer.ip();
+    }
+
+    headers
+        .get("X-Real-IP")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer.ip())
+}
@@ -0,0 +1,116 @@
+use askama::Template;
+use axum::http::StatusCode;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+
+use crate::models::AlbumUploadSettingsForm;
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/album_upload_settings.html")]
+struct AlbumUploadSettingsTemplate {
+    album: Album,
+    normalize_uploads: bool,
+    normalize_all: bool,
+    token: String,
+    error_message: Option<String>,
+}
+
+/// Uploads are auto-rotated and stripped of location when the album or the
+/// whole server asks for it
+pub fn normalizes_uploads(state: &AppState, album_id: &str) -> bool {
+    state.config.upload_normalize
+        || state
+            .album_meta
+            .get(album_id)
+            .is_some_and(|meta| meta.normalize_uploads)
+}
+
+/// Shows how uploads to the album are processed with a form to change it
+pub async fn album_upload_settings_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    render_upload_settings(&state, album, StatusCode::OK, None)
+}
+
+/// Only applies to new uploads, photos already in the album stay as they are
+pub async fn post_album_upload_settings_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    payload: Option<Form<AlbumUploadSettingsForm>>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Album, Action::Update) {
+        return handle_error_message(err);
+    }
+
+    let result = match payload {
+        Some(Form(form)) => set_upload_settings(&state, &album, &form).await,
+        None => Err(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        )),
+    };
+
+    match result {
+        Ok(_) => render_upload_settings(&state, album, StatusCode::OK, None),
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            render_upload_settings(
+                &state,
+                album,
+                error_info.status_code,
+                Some(error_info.message),
+            )
+        }
+    }
+}
+
+async fn set_upload_settings(
+    state: &AppState,
+    album: &Album,
+    form: &AlbumUploadSettingsForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    let mut meta = state.album_meta.get(&album.id).unwrap_or_default();
+    meta.normalize_uploads = form.normalize_uploads.is_some();
+    if meta.is_empty() {
+        state.album_meta.remove(&album.id).await?;
+    } else {
+        state.album_meta.set(&album.id, meta).await?;
+    }
+    Ok(())
+}
+
+fn render_upload_settings(
+    state: &AppState,
+    album: Album,
+    status: StatusCode,
+    error_message: Option<String>,
+) -> Response<Body> {
+    let token = create_csrf_token(&album.id, &state.config.jwt_secret).unwrap_or_default();
+
+    let tpl = AlbumUploadSettingsTemplate {
+        normalize_uploads: state
+            .album_meta
+            .get(&album.id)
+            .is_some_and(|meta| meta.normalize_uploads),
+        normalize_all: state.config.upload_normalize,
+        album,
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
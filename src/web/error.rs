@@ -98,11 +98,11 @@ impl From<Error> for ErrorInfo {
                 message: msg.clone(),
                 description: msg,
             },
-            Error::NoDefaultBucket => Self {
-                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                title: "Internal Server Error".to_string(),
-                message: "No default bucket".to_string(),
-                description: "No default bucket configured".to_string(),
+            Error::BucketNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                title: "Not Found".to_string(),
+                message: "Bucket not found".to_string(),
+                description: "The bucket does not exist or you have no access to it".to_string(),
             },
             Error::AlbumNotFound => Self {
                 status_code: StatusCode::NOT_FOUND,
@@ -116,6 +116,42 @@ impl From<Error> for ErrorInfo {
                 message: "Photo not found".to_string(),
                 description: "The photo you are looking for does not exist".to_string(),
             },
+            Error::ShareNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                title: "Not Found".to_string(),
+                message: "Shared album not found".to_string(),
+                description: "The link has expired or is no longer shared".to_string(),
+            },
+            Error::GuestUploadNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                title: "Not Found".to_string(),
+                message: "Upload link not found".to_string(),
+                description: "The link has expired or was revoked".to_string(),
+            },
+            Error::UploadNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                title: "Not Found".to_string(),
+                message: "Upload not found".to_string(),
+                description: "The upload does not exist or has already expired".to_string(),
+            },
+            Error::UploadConflict(msg) => Self {
+                status_code: StatusCode::CONFLICT,
+                title: "Conflict".to_string(),
+                message: msg.clone(),
+                description: msg,
+            },
+            Error::UploadTooLarge => Self {
+                status_code: StatusCode::PAYLOAD_TOO_LARGE,
+                title: "Payload Too Large".to_string(),
+                message: "Upload is too large".to_string(),
+                description: "The file exceeds the maximum upload size".to_string(),
+            },
+            Error::TooManyRequests(msg) => Self {
+                status_code: StatusCode::TOO_MANY_REQUESTS,
+                title: "Too Many Requests".to_string(),
+                message: msg.clone(),
+                description: msg,
+            },
             Error::NoAuthCookie => Self {
                 status_code: StatusCode::UNAUTHORIZED,
                 title: "Unauthorized".to_string(),
@@ -179,7 +215,7 @@ pub fn handle_error(
         let title = error.title.as_str();
         let status_code = error.status_code;
 
-        let mut t = TemplateData::new(&state, actor, pref);
+        let mut t = TemplateData::new(state, actor, pref);
         t.title = String::from(title);
 
         let tpl = ErrorPageData { t, error };
@@ -8,22 +8,37 @@ use axum::{
 
 use crate::{
     ctx::Ctx,
-    models::{ListAlbumsParams, TemplateData},
+    models::{Bucket, ListAlbumsParams, TemplateData},
 };
 use crate::{models::Pref, run::AppState};
 
 use super::{enforce_policy, handle_error, Action, Resource};
 
+struct SortOption {
+    value: &'static str,
+    label: &'static str,
+    selected: bool,
+}
+
 #[derive(Template)]
 #[template(path = "pages/index.html")]
 struct IndexTemplate {
     t: TemplateData,
+    bucket: Bucket,
+    buckets: Vec<Bucket>,
     query_params: String,
+    keyword: String,
+    non_empty: bool,
+    updated_since: String,
+    sort_options: Vec<SortOption>,
+    album_view: String,
 }
 
 pub async fn index_handler(
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
+    Extension(buckets): Extension<Vec<Bucket>>,
     State(state): State<AppState>,
     Query(query): Query<ListAlbumsParams>,
 ) -> Response<Body> {
@@ -37,7 +52,17 @@ pub async fn index_handler(
 
     let tpl = IndexTemplate {
         t,
+        bucket,
+        buckets,
         query_params: query.to_string(),
+        keyword: query.keyword.clone().unwrap_or_default(),
+        non_empty: query.non_empty == Some(true),
+        updated_since: query
+            .updated_since_ts()
+            .and(query.updated_since.clone())
+            .unwrap_or_default(),
+        sort_options: sort_options(&query),
+        album_view: pref.album_view.clone(),
     };
 
     // Prevent caching the home page
@@ -53,3 +78,30 @@ pub async fn index_handler(
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+fn sort_options(query: &ListAlbumsParams) -> Vec<SortOption> {
+    let current = query
+        .sort_order()
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let options = [
+        ("", "Default order"),
+        ("label", "Name (A-Z)"),
+        ("-label", "Name (Z-A)"),
+        ("-created_at", "Newest first"),
+        ("created_at", "Oldest first"),
+        ("-updated_at", "Recently updated"),
+        ("updated_at", "Least recently updated"),
+        ("-file_count", "Most photos"),
+        ("file_count", "Fewest photos"),
+    ];
+
+    options
+        .into_iter()
+        .map(|(value, label)| SortOption {
+            value,
+            label,
+            selected: value == current,
+        })
+        .collect()
+}
@@ -13,7 +13,7 @@ use crate::{
 };
 use crate::{
     models::{LoginFormPayload, TemplateData},
-    services::{authenticate, validate_catpcha, AuthPayload},
+    services::{validate_catpcha, AuthPayload},
     Error,
 };
 
@@ -92,8 +92,12 @@ pub async fn post_login_handler(
     }
 
     // Validate captcha
-    if let Err(captcha_err) =
-        validate_catpcha(&captcha_secret, login_payload.g_recaptcha_response.as_str()).await
+    if let Err(captcha_err) = validate_catpcha(
+        &config.captcha_verify_url,
+        &captcha_secret,
+        login_payload.g_recaptcha_response.as_str(),
+    )
+    .await
     {
         return handle_error(state, captcha_err);
     }
@@ -103,7 +107,7 @@ pub async fn post_login_handler(
         username: login_payload.username,
         password: login_payload.password,
     };
-    let login_result = authenticate(&config.api_url, auth_payload).await;
+    let login_result = state.files.authenticate(auth_payload).await;
     let auth = match login_result {
         Ok(val) => val,
         Err(err) => {
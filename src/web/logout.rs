@@ -1,9 +1,15 @@
-use axum::{http::Response, response::IntoResponse};
+use axum::{extract::State, http::Response, response::IntoResponse};
 use tower_cookies::{Cookie, Cookies};
 
+use crate::run::AppState;
+
 use super::AUTH_TOKEN_COOKIE;
 
-pub async fn logout_handler(cookies: Cookies) -> impl IntoResponse {
+pub async fn logout_handler(State(state): State<AppState>, cookies: Cookies) -> impl IntoResponse {
+    if let Some(cookie) = cookies.get(AUTH_TOKEN_COOKIE) {
+        state.actor_cache.invalidate(cookie.value());
+    }
+
     cookies.remove(Cookie::new(AUTH_TOKEN_COOKIE, ""));
 
     Response::builder()
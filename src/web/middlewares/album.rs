@@ -7,11 +7,9 @@ use axum::{
 
 use crate::{
     ctx::Ctx,
-    models::{AlbumParams, Pref},
+    models::{AlbumParams, Bucket, Pref},
     run::AppState,
-    services::get_album,
     web::{enforce_policy, handle_error, Action, Resource},
-    Error,
 };
 
 pub async fn album_listing_middleware(
@@ -40,6 +38,7 @@ pub async fn album_middleware(
     State(state): State<AppState>,
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
+    Extension(bucket): Extension<Bucket>,
     Path(params): Path<AlbumParams>,
     mut req: Request,
     next: Next,
@@ -55,20 +54,11 @@ pub async fn album_middleware(
         );
     }
 
-    let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(ctx.actor().clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            full_page,
-        );
-    };
-
     let album_id = params.album_id.expect("album_id is required");
-    let result = get_album(&state.config.api_url, ctx.token(), &bucket_id, &album_id).await;
+    let result = state
+        .files
+        .get_album(ctx.token(), &bucket.id, &album_id)
+        .await;
 
     match result {
         Ok(album) => {
@@ -10,7 +10,6 @@ use crate::{
     ctx::Ctx,
     models::Pref,
     run::AppState,
-    services::authenticate_token,
     web::{handle_error, AUTH_TOKEN_COOKIE},
     Error,
 };
@@ -22,7 +21,6 @@ pub async fn require_auth_middleware(
     mut req: Request,
     next: Next,
 ) -> Response {
-    let config = state.config.clone();
     let token = cookies
         .get(AUTH_TOKEN_COOKIE)
         .map(|c| c.value().to_string());
@@ -33,8 +31,17 @@ pub async fn require_auth_middleware(
 
     let full_page = req.headers().get("HX-Request").is_none();
 
-    // Validate token
-    let result = authenticate_token(&config.api_url, &token).await;
+    // Validate token, trusting recently validated actors
+    let result = match state.actor_cache.get(&token) {
+        Some(actor) => Ok(actor),
+        None => {
+            let result = state.files.authenticate_token(&token).await;
+            if let Ok(actor) = &result {
+                state.actor_cache.insert(&token, actor.clone());
+            }
+            result
+        }
+    };
 
     match result {
         Ok(actor) => {
@@ -42,12 +49,8 @@ pub async fn require_auth_middleware(
             req.extensions_mut().insert(ctx);
         }
         Err(err) => match err {
-            Error::LoginRequired(_) => {
-                if full_page {
-                    return Redirect::to("/login").into_response();
-                } else {
-                    return handle_error(&state, None, &pref, err.into(), full_page);
-                }
+            Error::LoginRequired(_) if full_page => {
+                return Redirect::to("/login").into_response();
             }
             _ => return handle_error(&state, None, &pref, err.into(), full_page),
         },
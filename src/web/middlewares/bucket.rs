@@ -0,0 +1,79 @@
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+
+use crate::{
+    ctx::Ctx,
+    models::{Bucket, BucketParams, Pref},
+    run::AppState,
+    web::{enforce_policy, handle_error, Action, Resource},
+    Error, Result,
+};
+
+/// Resolves the bucket to work on, from the url first, then the user's
+/// selected bucket, then the actor's default bucket, then the first one available
+pub async fn bucket_middleware(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    params: Option<Path<BucketParams>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let full_page = req.headers().get("HX-Request").is_none();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Bucket, Action::Read) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), full_page);
+    }
+
+    let buckets = match list_buckets(&state, &ctx).await {
+        Ok(buckets) => buckets,
+        Err(err) => {
+            return handle_error(&state, Some(actor.clone()), &pref, err.into(), full_page);
+        }
+    };
+
+    let find = |id: &str| buckets.iter().find(|b| b.id == id).cloned();
+    let path_bucket_id = params.and_then(|Path(p)| p.bucket_id);
+
+    let bucket = match path_bucket_id {
+        Some(id) => find(&id),
+        None => pref
+            .bucket_id
+            .as_deref()
+            .and_then(find)
+            .or_else(|| actor.default_bucket_id.as_deref().and_then(find))
+            .or_else(|| buckets.first().cloned()),
+    };
+
+    let Some(bucket) = bucket else {
+        return handle_error(
+            &state,
+            Some(actor.clone()),
+            &pref,
+            Error::BucketNotFound.into(),
+            full_page,
+        );
+    };
+
+    req.extensions_mut().insert(bucket);
+    req.extensions_mut().insert(buckets);
+
+    next.run(req).await
+}
+
+async fn list_buckets(state: &AppState, ctx: &Ctx) -> Result<Vec<Bucket>> {
+    if let Some(buckets) = state.actor_cache.get_buckets(ctx.token()) {
+        return Ok(buckets);
+    }
+
+    let buckets = state.files.list_buckets(ctx.token()).await?;
+    state
+        .actor_cache
+        .insert_buckets(ctx.token(), buckets.clone());
+    Ok(buckets)
+}
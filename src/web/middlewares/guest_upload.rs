@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use chrono::Utc;
+
+use crate::{
+    ctx::GuestUploadCtx,
+    models::{Album, GuestUploadParams, Pref},
+    run::AppState,
+    services::verify_guest_upload_token,
+    web::handle_error,
+    Error, Result,
+};
+
+/// Lets anyone holding a valid upload link add photos to the album, no login needed
+pub async fn guest_upload_middleware(
+    Extension(pref): Extension<Pref>,
+    State(state): State<AppState>,
+    Path(params): Path<GuestUploadParams>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let full_page = req.method() == "GET" && req.headers().get("HX-Request").is_none();
+
+    match load_guest_upload(&state, &params.guest_token).await {
+        Ok((ctx, album)) => {
+            req.extensions_mut().insert(ctx);
+            req.extensions_mut().insert(album);
+        }
+        Err(err) => return handle_error(&state, None, &pref, err.into(), full_page),
+    }
+
+    next.run(req).await
+}
+
+async fn load_guest_upload(state: &AppState, guest_token: &str) -> Result<(GuestUploadCtx, Album)> {
+    if !state.guest_upload_account.is_enabled() {
+        return Err(Error::GuestUploadNotFound);
+    }
+
+    let link_id = verify_guest_upload_token(guest_token, &state.config.jwt_secret)?;
+
+    // Revoked links are removed from the store
+    let Some(link) = state.guest_upload_links.get(&link_id) else {
+        return Err(Error::GuestUploadNotFound);
+    };
+    if link.expires_at <= Utc::now().timestamp() {
+        return Err(Error::GuestUploadNotFound);
+    }
+
+    let (token, album) = state
+        .guest_upload_account
+        .get_album(state.files.as_ref(), &link.bucket_id, &link.album_id)
+        .await?;
+
+    Ok((
+        GuestUploadCtx::new(token, guest_token.to_string(), link),
+        album,
+    ))
+}
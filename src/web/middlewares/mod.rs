@@ -1,9 +1,15 @@
 mod album;
 mod auth;
+mod bucket;
+mod guest_upload;
 mod photo;
 mod pref;
+mod share;
 
 pub use album::*;
 pub use auth::*;
+pub use bucket::*;
+pub use guest_upload::*;
 pub use photo::*;
 pub use pref::*;
+pub use share::*;
@@ -7,17 +7,16 @@ use axum::{
 
 use crate::{
     ctx::Ctx,
-    models::{PhotoParams, Pref},
+    models::{Album, PhotoParams, Pref},
     run::AppState,
-    services::get_photo,
     web::{enforce_policy, handle_error, Action, Resource},
-    Error,
 };
 
 pub async fn photo_middleware(
     State(state): State<AppState>,
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
     Path(params): Path<PhotoParams>,
     mut req: Request,
     next: Next,
@@ -33,33 +32,21 @@ pub async fn photo_middleware(
         );
     }
 
-    let album_id = params.album_id.expect("album_id is required");
     let photo_id = params.photo_id.expect("photo_id is required");
 
-    let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(ctx.actor().clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            full_page,
-        );
-    };
-
-    let config = state.config.clone();
-    let result = get_photo(
-        &config.api_url,
-        ctx.token(),
-        &bucket_id,
-        &album_id,
-        &photo_id,
-    )
-    .await;
+    let result = state
+        .files
+        .get_photo(ctx.token(), &album.bucket_id, &album.id, &photo_id)
+        .await;
 
     match result {
-        Ok(photo) => {
+        Ok(mut photo) => {
+            state.photo_meta.fill(std::slice::from_mut(&mut photo));
+            state.photo_tags.fill(std::slice::from_mut(&mut photo));
+            state
+                .photo_exif
+                .fill(std::slice::from_mut(&mut photo))
+                .await;
             req.extensions_mut().insert(photo);
         }
         Err(err) => {
@@ -1,7 +1,10 @@
 use axum::{extract::Request, middleware::Next, response::Response};
 use axum_extra::extract::CookieJar;
 
-use crate::{models::Pref, web::THEME_COOKIE};
+use crate::{
+    models::Pref,
+    web::{ALBUM_VIEW_COOKIE, BUCKET_COOKIE, THEME_COOKIE},
+};
 
 pub async fn pref_middleware(cookies: CookieJar, mut req: Request, next: Next) -> Response {
     let mut pref = Pref::new();
@@ -14,6 +17,14 @@ pub async fn pref_middleware(cookies: CookieJar, mut req: Request, next: Next) -
         }
     }
 
+    pref.bucket_id = cookies.get(BUCKET_COOKIE).map(|c| c.value().to_string());
+
+    if let Some(view) = cookies.get(ALBUM_VIEW_COOKIE) {
+        if view.value() == "list" || view.value() == "grid" {
+            pref.album_view = view.value().to_string();
+        }
+    }
+
     req.extensions_mut().insert(pref);
     next.run(req).await
 }
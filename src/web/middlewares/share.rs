@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use chrono::Utc;
+
+use crate::{
+    ctx::ShareCtx,
+    models::{Album, Pref, ShareParams},
+    run::AppState,
+    services::verify_share_token,
+    web::handle_error,
+    Error, Result,
+};
+
+/// Lets anyone holding a valid share link view the album, no login needed
+pub async fn share_middleware(
+    Extension(pref): Extension<Pref>,
+    State(state): State<AppState>,
+    Path(params): Path<ShareParams>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let full_page = req.headers().get("HX-Request").is_none();
+
+    match load_shared_album(&state, &params.share_token).await {
+        Ok((ctx, album)) => {
+            req.extensions_mut().insert(ctx);
+            req.extensions_mut().insert(album);
+        }
+        Err(err) => return handle_error(&state, None, &pref, err.into(), full_page),
+    }
+
+    next.run(req).await
+}
+
+async fn load_shared_album(state: &AppState, share_token: &str) -> Result<(ShareCtx, Album)> {
+    if !state.share_account.is_enabled() {
+        return Err(Error::ShareNotFound);
+    }
+    let link_id = verify_share_token(share_token, &state.config.jwt_secret)?;
+
+    // Revoked links are removed from the store
+    let Some(link) = state.share_links.get(&link_id) else {
+        return Err(Error::ShareNotFound);
+    };
+    if link.expires_at <= Utc::now().timestamp() {
+        return Err(Error::ShareNotFound);
+    }
+
+    let (token, album) = state
+        .share_account
+        .get_album(state.files.as_ref(), &link.bucket_id, &link.album_id)
+        .await?;
+
+    Ok((ShareCtx::new(token, share_token.to_string()), album))
+}
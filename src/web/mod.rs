@@ -11,6 +11,9 @@ mod routes;
 
 pub const AUTH_TOKEN_COOKIE: &str = "auth_token";
 pub const THEME_COOKIE: &str = "theme";
+pub const BUCKET_COOKIE: &str = "bucket";
+pub const ALBUM_VIEW_COOKIE: &str = "album_view";
+pub const UNLOCK_COOKIE: &str = "album_unlock";
 
 pub use albums::*;
 pub use error::*;
@@ -1,43 +1,86 @@
 use askama::Template;
+use axum::extract::Query;
 use axum::http::StatusCode;
 use axum::Form;
 use axum::{body::Body, extract::State, response::Response, Extension};
+use tracing::warn;
 
-use crate::models::{DeletePhotoForm, Photo};
+use crate::models::{
+    BulkDeletePhotosForm, BulkDeleteResult, DeletePhotoForm, ListPhotosParams, Photo,
+};
 use crate::run::AppState;
-use crate::services::{create_csrf_token, delete_photo};
-use crate::{ctx::Ctx, models::Album, Error};
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::{ctx::Ctx, models::Album, Error, Result};
 
 use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
 
 #[derive(Template)]
 #[template(path = "widgets/pre_delete_photo_form.html")]
 struct PreDeletePhotoTemplate {
+    album: Album,
     photo: Photo,
+    can_edit_photos: bool,
+    can_add_photos: bool,
+    can_delete_photos: bool,
 }
 
 #[derive(Template)]
 #[template(path = "widgets/confirm_delete_photo_form.html")]
 struct ConfirmDeletePhotoTemplate {
+    album: Album,
     photo: Photo,
     payload: DeletePhotoForm,
     error_message: Option<String>,
 }
 
-/// Shows pre-delete form controls
+#[derive(Template)]
+#[template(path = "widgets/bulk_delete_photos_form.html")]
+struct BulkDeletePhotosFormTemplate {
+    album: Album,
+    photo_ids: Vec<String>,
+    token: String,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/bulk_delete_photos_result.html")]
+struct BulkDeletePhotosResultTemplate {
+    results: Vec<BulkDeleteResult>,
+    deleted_count: usize,
+    failed_count: usize,
+    counter: Option<PhotosCounter>,
+}
+
+/// Values for the photos counter above the grid
+struct PhotosCounter {
+    current: i64,
+    total: i64,
+}
+
+/// Keeps a single request from tying up the server for too long
+const MAX_BULK_DELETE_PHOTOS: usize = 100;
+
+/// Shows the grid item controls, also when leaving the tags or delete forms
 pub async fn pre_delete_photo_handler(
     Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
     Extension(photo): Extension<Photo>,
 ) -> Response<Body> {
     let actor = ctx.actor();
 
-    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Delete) {
-        return handle_error_message(err);
+    let tpl = PreDeletePhotoTemplate {
+        album,
+        photo,
+        can_edit_photos: enforce_policy(actor, Resource::Photo, Action::Update).is_ok(),
+        can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
+        can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+    };
+    if !tpl.can_edit_photos && !tpl.can_add_photos && !tpl.can_delete_photos {
+        return handle_error_message(Error::Forbidden(
+            "You do not have permission to change photos.".to_string(),
+        ));
     }
 
-    // Just render the form on first load or on error
-    let tpl = PreDeletePhotoTemplate { photo };
-
     Response::builder()
         .status(200)
         .body(Body::from(tpl.render().unwrap()))
@@ -47,6 +90,7 @@ pub async fn pre_delete_photo_handler(
 /// Shows delete/cancel form controls
 pub async fn confirm_delete_photo_handler(
     Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
     Extension(photo): Extension<Photo>,
     State(state): State<AppState>,
 ) -> Response<Body> {
@@ -65,6 +109,7 @@ pub async fn confirm_delete_photo_handler(
 
     // Just render the form on first load or on error
     let tpl = ConfirmDeletePhotoTemplate {
+        album,
         photo,
         payload: DeletePhotoForm { token },
         error_message: None,
@@ -85,10 +130,6 @@ pub async fn exec_delete_photo_handler(
 ) -> Response<Body> {
     let config = state.config.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error_message(Error::NoDefaultBucket);
-    };
 
     if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Delete) {
         return handle_error_message(err);
@@ -104,15 +145,7 @@ pub async fn exec_delete_photo_handler(
     let mut error_message = Some("Invalid form data. Refresh the page and try again.".to_string());
 
     if let Some(form) = payload {
-        let result = delete_photo(
-            &config,
-            ctx.token(),
-            &bucket_id,
-            &album.id,
-            &photo.id,
-            &form.token,
-        )
-        .await;
+        let result = delete_confirmed_photo(&state, &ctx, &album, &photo, &form).await;
         match result {
             Ok(_) => {
                 return Response::builder()
@@ -132,6 +165,7 @@ pub async fn exec_delete_photo_handler(
     // Re-render the form with a new token
     // We may need to render an error message somewhere in the page
     let tpl = ConfirmDeletePhotoTemplate {
+        album,
         photo,
         payload: DeletePhotoForm { token },
         error_message,
@@ -142,3 +176,213 @@ pub async fn exec_delete_photo_handler(
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+/// Asks to confirm deleting the selected photos
+pub async fn bulk_delete_photos_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Delete) {
+        return handle_error_message(err);
+    }
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize delete photos form.".to_string(),
+        ));
+    };
+
+    let form = BulkDeletePhotosForm::from_pairs(query);
+    let mut status = 200;
+    let mut error_message = None;
+    if form.photo_ids.is_empty() {
+        status = 400;
+        error_message = Some("Select at least one photo.".to_string());
+    }
+
+    let tpl = BulkDeletePhotosFormTemplate {
+        album,
+        photo_ids: form.photo_ids,
+        token,
+        error_message,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Deletes the selected photos one by one, reporting each outcome
+pub async fn exec_bulk_delete_photos_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    payload: Option<Form<Vec<(String, String)>>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Delete) {
+        return handle_error_message(err);
+    }
+
+    let Some(Form(pairs)) = payload else {
+        return handle_error_message(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        ));
+    };
+    let form = BulkDeletePhotosForm::from_pairs(pairs);
+
+    if let Err(err) = validate_bulk_delete(&state, &album, &form) {
+        let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+            return handle_error_message(Error::AnyError(
+                "Failed to initialize delete photos form.".to_string(),
+            ));
+        };
+        let error_info: ErrorInfo = err.into();
+        let tpl = BulkDeletePhotosFormTemplate {
+            album,
+            photo_ids: form.photo_ids,
+            token,
+            error_message: Some(error_info.message),
+        };
+        return Response::builder()
+            .status(error_info.status_code)
+            .body(Body::from(tpl.render().unwrap()))
+            .unwrap();
+    }
+
+    let mut results: Vec<BulkDeleteResult> = Vec::new();
+    for photo_id in form.photo_ids.iter() {
+        results.push(delete_photo(&state, &ctx, &album, photo_id).await);
+    }
+
+    let failed_count = results.iter().filter(|r| r.error.is_some()).count();
+    let deleted_count = results.len() - failed_count;
+
+    // The counter is a nice to have, leave it alone when files-rs fails
+    let params = ListPhotosParams::default();
+    let counter = state
+        .files
+        .list_photos(ctx.token(), &album.bucket_id, &album.id, &params)
+        .await
+        .ok()
+        .map(|listing| {
+            let total = listing.meta.total_records;
+            let loaded = form.loaded.unwrap_or(total) - deleted_count as i64;
+            PhotosCounter {
+                current: loaded.clamp(0, total),
+                total,
+            }
+        });
+
+    let tpl = BulkDeletePhotosResultTemplate {
+        results,
+        deleted_count,
+        failed_count,
+        counter,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+async fn delete_confirmed_photo(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    photo: &Photo,
+    form: &DeletePhotoForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &photo.id, &state.config.jwt_secret)?;
+
+    state
+        .files
+        .delete_photo(ctx.token(), &album.bucket_id, &album.id, &photo.id)
+        .await?;
+    forget_photo(state, &album.id, &photo.id).await;
+    Ok(())
+}
+
+fn validate_bulk_delete(
+    state: &AppState,
+    album: &Album,
+    form: &BulkDeletePhotosForm,
+) -> Result<()> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    if form.photo_ids.is_empty() {
+        return Err(Error::ValidationError(
+            "Select at least one photo.".to_string(),
+        ));
+    }
+    if form.photo_ids.len() > MAX_BULK_DELETE_PHOTOS {
+        return Err(Error::ValidationError(format!(
+            "Select at most {} photos at a time.",
+            MAX_BULK_DELETE_PHOTOS
+        )));
+    }
+
+    Ok(())
+}
+
+async fn delete_photo(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    photo_id: &str,
+) -> BulkDeleteResult {
+    let mut result = BulkDeleteResult {
+        photo_id: photo_id.to_string(),
+        name: photo_id.to_string(),
+        error: None,
+    };
+
+    // Looking it up first makes sure the photo belongs to this album
+    let deleted = match state
+        .files
+        .get_photo(ctx.token(), &album.bucket_id, &album.id, photo_id)
+        .await
+    {
+        Ok(photo) => {
+            result.name = photo.name;
+            state
+                .files
+                .delete_photo(ctx.token(), &album.bucket_id, &album.id, photo_id)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+
+    match deleted {
+        Ok(_) => forget_photo(state, &album.id, photo_id).await,
+        Err(err) => result.error = Some(err.to_string()),
+    }
+
+    result
+}
+
+/// Drops what memo-rs keeps about a photo once it is gone from the album.
+/// The photo is already deleted in files-rs, failures here are only logged.
+pub async fn forget_photo(state: &AppState, album_id: &str, photo_id: &str) {
+    state.photo_listings.invalidate(album_id);
+
+    let results = [
+        state.photo_meta.remove(photo_id).await.map(|_| ()),
+        state.album_meta.clear_cover(album_id, photo_id).await,
+        state.favorites.forget_photo(photo_id).await,
+        state.photo_tags.remove(photo_id).await.map(|_| ()),
+        state.photo_exif.remove(photo_id).await,
+    ];
+    for err in results.into_iter().filter_map(|result| result.err()) {
+        warn!("Unable to forget deleted photo {}: {}", photo_id, err);
+    }
+}
@@ -0,0 +1,125 @@
+use askama::Template;
+use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::{body::Body, extract::State, response::Response, Extension};
+use chrono::DateTime;
+
+use crate::models::{ListPhotosParams, Photo, PhotoVersionDto, Pref, TemplateData};
+use crate::run::AppState;
+use crate::services::create_csrf_token;
+use crate::web::{album_photos, enforce_policy, handle_error, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album};
+
+#[derive(Template)]
+#[template(path = "pages/photo.html")]
+struct PhotoTemplate {
+    t: TemplateData,
+    album: Album,
+    photo: Photo,
+    versions: Vec<PhotoVersionDto>,
+    size: String,
+    created_at: String,
+    updated_at: String,
+    prev_id: Option<String>,
+    next_id: Option<String>,
+    position: usize,
+    total: usize,
+    nav_query: String,
+    can_edit_photo: bool,
+    can_add_photos: bool,
+    can_delete_photos: bool,
+    can_set_cover: bool,
+    is_cover: bool,
+    token: String,
+    updated: bool,
+}
+
+/// Shows a single photo with its metadata and links to its neighbours
+pub async fn photo_page_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+    Query(query): Query<ListPhotosParams>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+    let full_page = headers.get("HX-Request").is_none();
+
+    // Follow the same order and filter as the grid the photo was opened from
+    let photos = match album_photos(&state, ctx.token(), &album).await {
+        Ok(photos) => photos,
+        Err(err) => {
+            return handle_error(&state, Some(actor.clone()), &pref, err.into(), full_page);
+        }
+    };
+
+    // Without a chosen cover, the album falls back to its first photo
+    let cover_id = state
+        .album_meta
+        .get(&album.id)
+        .and_then(|meta| meta.cover_photo_id)
+        .or_else(|| photos.iter().find(|p| p.is_image()).map(|p| p.id.clone()));
+
+    let photos = if query.is_custom() {
+        query.apply(photos)
+    } else {
+        photos
+    };
+
+    let index = photos.iter().position(|p| p.id == photo.id);
+    let prev_id = index
+        .filter(|i| *i > 0)
+        .and_then(|i| photos.get(i - 1))
+        .map(|p| p.id.clone());
+    let next_id = index.and_then(|i| photos.get(i + 1)).map(|p| p.id.clone());
+
+    let Ok(token) = create_csrf_token(&photo.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize photo page.".to_string());
+        return handle_error(&state, Some(actor.clone()), &pref, error, full_page);
+    };
+
+    let mut t = TemplateData::new(&state, Some(actor.clone()), &pref);
+    t.title = format!("{} - {}", &photo.name, &album.label);
+
+    let tpl = PhotoTemplate {
+        t,
+        versions: vec![
+            photo.orig.clone(),
+            photo.preview.clone(),
+            photo.thumb.clone(),
+        ],
+        size: photo.size_label(),
+        created_at: format_timestamp(photo.created_at),
+        updated_at: format_timestamp(photo.updated_at),
+        prev_id,
+        next_id,
+        position: index.map(|i| i + 1).unwrap_or(0),
+        total: photos.len(),
+        nav_query: query.filter_query(),
+        can_edit_photo: enforce_policy(actor, Resource::Photo, Action::Update).is_ok(),
+        can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
+        can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+        can_set_cover: photo.is_image()
+            && enforce_policy(actor, Resource::Album, Action::Update).is_ok(),
+        is_cover: cover_id.as_deref() == Some(photo.id.as_str()),
+        token,
+        updated: false,
+        album,
+        photo,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
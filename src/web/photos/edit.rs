@@ -0,0 +1,205 @@
+use askama::Template;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use validator::Validate;
+
+use crate::models::{Photo, Pref, UpdatePhoto, UpdatePhotoForm};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::{ctx::Ctx, models::Album, Error};
+
+use crate::web::{enforce_policy, handle_error, Action, ErrorInfo, Resource};
+
+#[derive(Template)]
+#[template(path = "widgets/edit_photo_form.html")]
+struct EditPhotoFormTemplate {
+    payload: UpdatePhotoForm,
+    album: Album,
+    photo: Photo,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/photo_info.html")]
+struct PhotoInfoTemplate {
+    album: Album,
+    photo: Photo,
+    updated: bool,
+    can_edit_photo: bool,
+}
+
+/// Simply re-renders the photo name and caption
+pub async fn photo_info_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+) -> Response<Body> {
+    let tpl = PhotoInfoTemplate {
+        album,
+        photo,
+        updated: false,
+        can_edit_photo: enforce_policy(ctx.actor(), Resource::Photo, Action::Update).is_ok(),
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Renders the edit photo form
+pub async fn edit_photo_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Update) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), false);
+    }
+    let Ok(token) = create_csrf_token(&photo.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize edit photo form.".to_string());
+        return handle_error(&state, Some(actor.clone()), &pref, error, false);
+    };
+
+    let tpl = EditPhotoFormTemplate {
+        payload: UpdatePhotoForm {
+            name: photo.name.clone(),
+            caption: photo.caption.clone().unwrap_or_default(),
+            token,
+        },
+        album,
+        photo,
+        error_message: None,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Handles the edit photo submission, the name is saved in files-rs
+/// while the caption is kept on our side
+pub async fn post_edit_photo_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+    payload: Option<Form<UpdatePhotoForm>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Update) {
+        return handle_error(&state, Some(actor.clone()), &pref, err.into(), false);
+    }
+    let Ok(token) = create_csrf_token(&photo.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize edit photo form.".to_string());
+        return handle_error(&state, Some(actor.clone()), &pref, error, false);
+    };
+
+    let Some(Form(form)) = payload else {
+        let tpl = EditPhotoFormTemplate {
+            payload: UpdatePhotoForm {
+                name: photo.name.clone(),
+                caption: photo.caption.clone().unwrap_or_default(),
+                token,
+            },
+            album,
+            photo,
+            error_message: Some("Invalid form data.".to_string()),
+        };
+        return Response::builder()
+            .status(400)
+            .body(Body::from(tpl.render().unwrap()))
+            .unwrap();
+    };
+
+    let result = save_photo(&state, &ctx, &album, &photo, &form).await;
+
+    match result {
+        Ok(updated_photo) => {
+            let tpl = PhotoInfoTemplate {
+                album,
+                photo: updated_photo,
+                updated: true,
+                can_edit_photo: true,
+            };
+            Response::builder()
+                .status(200)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap()
+        }
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            let tpl = EditPhotoFormTemplate {
+                payload: UpdatePhotoForm { token, ..form },
+                album,
+                photo,
+                error_message: Some(error_info.message),
+            };
+            Response::builder()
+                .status(error_info.status_code)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap()
+        }
+    }
+}
+
+async fn save_photo(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    photo: &Photo,
+    form: &UpdatePhotoForm,
+) -> crate::Result<Photo> {
+    verify_csrf_subject(&form.token, &photo.id, &state.config.jwt_secret)?;
+
+    let name = form.name.trim().to_string();
+    let caption = form.caption.trim().to_string();
+    let data = UpdatePhotoForm {
+        name: name.clone(),
+        caption: caption.clone(),
+        token: "".to_string(),
+    };
+    if data.validate().is_err() {
+        return Err(Error::ValidationError(
+            "Name must be 1 to 100 characters and caption at most 500 characters.".to_string(),
+        ));
+    }
+
+    // Only bother files-rs when the name actually changed
+    let mut updated = photo.clone();
+    if name != photo.name {
+        updated = state
+            .files
+            .update_photo(
+                ctx.token(),
+                &album.bucket_id,
+                &album.id,
+                &photo.id,
+                &UpdatePhoto { name },
+            )
+            .await?;
+        // Sorting and searching go by name
+        state.photo_listings.invalidate(&album.id);
+    }
+
+    let mut meta = state.photo_meta.get(&photo.id).unwrap_or_default();
+    meta.caption = Some(caption).filter(|c| !c.is_empty());
+    if meta.is_empty() {
+        state.photo_meta.remove(&photo.id).await?;
+    } else {
+        state.photo_meta.set(&photo.id, meta.clone()).await?;
+    }
+
+    updated.caption = meta.caption;
+    updated.tags = photo.tags.clone();
+    updated.exif = photo.exif.clone();
+    Ok(updated)
+}
@@ -0,0 +1,74 @@
+use askama::Template;
+use axum::Form;
+use axum::{body::Body, extract::State, response::Response, Extension};
+use chrono::Utc;
+
+use crate::models::{FavoritePhoto, FavoritePhotoForm, Photo};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::handle_error_message;
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/favorite_button.html")]
+pub struct FavoriteButtonTemplate {
+    pub bucket_id: String,
+    pub photo: Photo,
+    pub token: String,
+}
+
+/// Stars the photo for the current user or takes the star off.
+/// Anyone who can view the photo can star it, nothing changes in files-rs.
+pub async fn favorite_photo_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+    payload: Option<Form<FavoritePhotoForm>>,
+) -> Response<Body> {
+    let result = match payload {
+        Some(Form(form)) => toggle_favorite(&state, &ctx, &album, &photo, &form).await,
+        None => Err(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        )),
+    };
+    let favorite = match result {
+        Ok(favorite) => favorite,
+        Err(err) => return handle_error_message(err),
+    };
+
+    let Ok(token) = create_csrf_token(&album.id, &state.config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize favorite button.".to_string(),
+        ));
+    };
+
+    let tpl = FavoriteButtonTemplate {
+        bucket_id: album.bucket_id,
+        photo: Photo { favorite, ..photo },
+        token,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+async fn toggle_favorite(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    photo: &Photo,
+    form: &FavoritePhotoForm,
+) -> Result<bool> {
+    verify_csrf_subject(&form.token, &album.id, &state.config.jwt_secret)?;
+
+    let favorite = FavoritePhoto {
+        bucket_id: album.bucket_id.clone(),
+        album_id: album.id.clone(),
+        photo_id: photo.id.clone(),
+        created_at: Utc::now().timestamp(),
+    };
+    state.favorites.toggle(&ctx.actor().user.id, favorite).await
+}
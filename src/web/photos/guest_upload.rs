@@ -0,0 +1,212 @@
+use askama::Template;
+use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::{body::Body, extract::State, response::Response, Extension};
+use chrono::Utc;
+
+use crate::ctx::GuestUploadCtx;
+use crate::models::{
+    Album, GuestUploadLink, GuestUploadQuery, NewStagedUpload, Photo, PhotoMeta, Pref, TemplateData,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{
+    handle_error, handle_error_message, needs_review, upload_and_index_photo, ErrorInfo,
+};
+
+use super::StagedUploadTemplate;
+use crate::{Error, Result};
+
+/// Longest name a guest can enter
+const GUEST_NAME_MAX_LENGTH: usize = 50;
+
+#[derive(Template)]
+#[template(path = "pages/guest_upload.html")]
+struct GuestUploadPageTemplate {
+    t: TemplateData,
+    base_url: String,
+    token: String,
+    album: Album,
+    uploads_left: u32,
+    needs_review: bool,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/guest_uploaded_photo.html")]
+struct GuestUploadedPhotoTemplate {
+    theme: String,
+    photo: Photo,
+}
+
+/// Upload page for guests, they only ever see the photos they uploaded
+pub async fn guest_upload_page_handler(
+    Extension(ctx): Extension<GuestUploadCtx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let link = ctx.link();
+
+    let Ok(token) = create_csrf_token(&link.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize upload photos form.".to_string());
+        return handle_error(&state, None, &pref, error, true);
+    };
+    let mut t = TemplateData::new(&state, None, &pref);
+
+    t.title = format!("Upload Photos - {}", &album.label);
+    t.scripts = vec![config.assets.upload_js.clone()];
+
+    let tpl = GuestUploadPageTemplate {
+        t,
+        base_url: ctx.base_url(),
+        token,
+        uploads_left: link.uploads_left(),
+        needs_review: needs_review(&state, &album.id, None),
+        album,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Uploads a single photo and records the name the guest entered with it
+pub async fn guest_upload_handler(
+    Extension(ctx): Extension<GuestUploadCtx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<GuestUploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let link = ctx.link();
+
+    let Ok(token) = create_csrf_token(&link.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize upload photos form.".to_string(),
+        ));
+    };
+
+    // We need the content type header
+    let Some(content_type) = headers.get("Content-Type") else {
+        return handle_error_message("Content-Type header is required.".into());
+    };
+    let Ok(content_type) = content_type.to_str() else {
+        return handle_error_message("Invalid Content-Type header.".into());
+    };
+    let content_length = headers
+        .get("Content-Length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let csrf_token = query.token.unwrap_or("".to_string());
+    let name = match verify_csrf_subject(&csrf_token, &link.id, &config.jwt_secret)
+        .and_then(|_| guest_name(query.name.as_deref()))
+    {
+        Ok(name) => name,
+        Err(err) => return handle_error_message(err),
+    };
+
+    match state
+        .guest_upload_links
+        .reserve_upload(&link.id, Utc::now().timestamp())
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return handle_error_message(Error::Forbidden(
+                "This link has reached its upload limit.".to_string(),
+            ))
+        }
+        Err(err) => return handle_error_message(err),
+    }
+
+    if needs_review(&state, &album.id, None) {
+        let data = NewStagedUpload {
+            bucket_id: album.bucket_id.clone(),
+            album_id: album.id.clone(),
+            uploaded_by: name,
+            guest: true,
+        };
+        return stage_guest_upload(&state, pref.theme, link, data, content_type, body, token).await;
+    }
+
+    let result = upload_and_index_photo(
+        &state,
+        ctx.token(),
+        &album,
+        content_type,
+        content_length,
+        body,
+    )
+    .await;
+
+    let photo = match result {
+        Ok(photo) => photo,
+        Err(err) => {
+            if let Error::LoginRequired(_) = err {
+                state.guest_upload_account.invalidate(ctx.token());
+            }
+            let _ = state.guest_upload_links.release_upload(&link.id).await;
+            return handle_error_message(err);
+        }
+    };
+
+    // The photo is already in the album, a lost name is not worth failing over
+    let meta = PhotoMeta {
+        uploaded_by: Some(name),
+        ..Default::default()
+    };
+    let _ = state.photo_meta.set(&photo.id, meta).await;
+
+    let tpl = GuestUploadedPhotoTemplate {
+        photo,
+        theme: pref.theme,
+    };
+    Response::builder()
+        .status(201)
+        .header("X-Next-Token", token)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Uploads to moderated albums wait for review, the guest name goes along
+async fn stage_guest_upload(
+    state: &AppState,
+    theme: String,
+    link: &GuestUploadLink,
+    data: NewStagedUpload,
+    content_type: &str,
+    body: Body,
+    token: String,
+) -> Response<Body> {
+    match state.staging.stage(data, content_type, body).await {
+        Ok(staged) => {
+            let tpl = StagedUploadTemplate { staged, theme };
+            Response::builder()
+                .status(202)
+                .header("X-Next-Token", token)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap()
+        }
+        Err(err) => {
+            let _ = state.guest_upload_links.release_upload(&link.id).await;
+            handle_error_message(err)
+        }
+    }
+}
+
+fn guest_name(name: Option<&str>) -> Result<String> {
+    let name = name.unwrap_or("").trim();
+    if name.is_empty() || name.chars().count() > GUEST_NAME_MAX_LENGTH {
+        return Err(Error::ValidationError(format!(
+            "Enter your name, up to {} characters.",
+            GUEST_NAME_MAX_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
@@ -1,15 +1,16 @@
 use askama::Template;
 use axum::extract::Query;
 use axum::{body::Body, extract::State, response::Response, Extension};
+use futures_util::{stream, StreamExt};
 
-use crate::models::{ListPhotosParams, PaginatedMeta, Pref};
+use crate::models::{ListPhotosParams, Paginated, PaginatedMeta, Pref, PHOTO_PAGE_SIZES};
 use crate::run::AppState;
-use crate::web::ErrorInfo;
+use crate::services::create_csrf_token;
+use crate::web::{enforce_review_policy, ErrorInfo};
 use crate::{
-    ctx::Ctx,
+    ctx::{Ctx, ShareCtx},
     models::{Album, Photo, TemplateData},
-    services::list_photos,
-    Error,
+    Error, Result,
 };
 
 use crate::web::policies::{enforce_policy, Action, Resource};
@@ -19,11 +20,40 @@ use crate::web::policies::{enforce_policy, Action, Resource};
 struct PhotosTemplate {
     t: TemplateData,
     album: Album,
+    base_url: String,
+    read_only: bool,
     updated: bool,
     can_edit: bool,
     can_delete: bool,
     can_add_photos: bool,
     can_delete_photos: bool,
+    can_review_uploads: bool,
+    query_params: String,
+    keyword: String,
+    camera: String,
+    page_sizes: Vec<SelectOption>,
+    sort_options: Vec<SelectOption>,
+}
+
+/// How the photo grid links and what visitors can do in it
+pub struct GridView {
+    /// Album page the grid belongs to, page and filter links start with it
+    pub base_url: String,
+    pub read_only: bool,
+    /// Only previews are linked so the originals stay private
+    pub no_downloads: bool,
+    /// Member whose stars are shown, visitors have none
+    pub user_id: Option<String>,
+    /// Photo controls are only shown to those allowed to use them
+    pub can_edit_photos: bool,
+    pub can_add_photos: bool,
+    pub can_delete_photos: bool,
+}
+
+struct SelectOption {
+    value: String,
+    label: String,
+    selected: bool,
 }
 
 #[derive(Template)]
@@ -31,11 +61,19 @@ struct PhotosTemplate {
 struct PhotoGridTemnplate {
     theme: String,
     album: Album,
+    base_url: String,
+    read_only: bool,
+    no_downloads: bool,
+    can_edit_photos: bool,
+    can_add_photos: bool,
+    can_delete_photos: bool,
     photos: Vec<Photo>,
     meta: Option<PaginatedMeta>,
     error_message: Option<String>,
     next_page: Option<i64>,
     last_item: String,
+    filter_query: String,
+    token: String,
 }
 
 pub async fn photos_page_handler(
@@ -43,6 +81,7 @@ pub async fn photos_page_handler(
     Extension(pref): Extension<Pref>,
     Extension(album): Extension<Album>,
     State(state): State<AppState>,
+    Query(query): Query<ListPhotosParams>,
 ) -> Response<Body> {
     let config = state.config.clone();
     let actor = ctx.actor();
@@ -54,12 +93,70 @@ pub async fn photos_page_handler(
 
     let tpl = PhotosTemplate {
         t,
+        base_url: format!("/buckets/{}/albums/{}", album.bucket_id, album.id),
         album,
+        read_only: false,
         updated: false,
         can_edit: enforce_policy(actor, Resource::Album, Action::Update).is_ok(),
         can_delete: enforce_policy(actor, Resource::Album, Action::Delete).is_ok(),
         can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
         can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+        can_review_uploads: enforce_review_policy(actor).is_ok(),
+        query_params: format!("page=1{}", query.filter_query()),
+        keyword: query.keyword().unwrap_or("").to_string(),
+        camera: query.camera().unwrap_or("").to_string(),
+        page_sizes: page_size_options(&query),
+        sort_options: sort_options(&query),
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Album page for share link visitors, without any of the member controls
+pub async fn shared_photos_page_handler(
+    Extension(share): Extension<ShareCtx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<ListPhotosParams>,
+) -> Response<Body> {
+    render_read_only_page(&state, &pref, album, &query, share.base_url())
+}
+
+/// Album page for visitors without an account
+pub fn render_read_only_page(
+    state: &AppState,
+    pref: &Pref,
+    album: Album,
+    query: &ListPhotosParams,
+    base_url: String,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let mut t = TemplateData::new(state, None, pref);
+
+    t.title = format!("Photos - {}", &album.label);
+    t.styles = vec![config.assets.gallery_css.clone()];
+    t.scripts = vec![config.assets.gallery_js.clone()];
+
+    let tpl = PhotosTemplate {
+        t,
+        album,
+        base_url,
+        read_only: true,
+        updated: false,
+        can_edit: false,
+        can_delete: false,
+        can_add_photos: false,
+        can_delete_photos: false,
+        can_review_uploads: false,
+        query_params: format!("page=1{}", query.filter_query()),
+        keyword: query.keyword().unwrap_or("").to_string(),
+        camera: query.camera().unwrap_or("").to_string(),
+        page_sizes: page_size_options(query),
+        sort_options: sort_options(query),
     };
 
     Response::builder()
@@ -74,31 +171,101 @@ pub async fn photo_listing_handler(
     Extension(album): Extension<Album>,
     Query(query): Query<ListPhotosParams>,
     State(state): State<AppState>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+    let view = GridView {
+        base_url: format!("/buckets/{}/albums/{}", album.bucket_id, album.id),
+        read_only: false,
+        no_downloads: false,
+        user_id: Some(actor.user.id.clone()),
+        can_edit_photos: enforce_policy(actor, Resource::Photo, Action::Update).is_ok(),
+        can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
+        can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+    };
+    render_photo_grid(&state, ctx.token(), pref.theme, album, &query, view).await
+}
+
+pub async fn shared_photo_listing_handler(
+    Extension(share): Extension<ShareCtx>,
+    Extension(pref): Extension<Pref>,
+    Extension(album): Extension<Album>,
+    Query(query): Query<ListPhotosParams>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let view = GridView {
+        base_url: share.base_url(),
+        read_only: true,
+        no_downloads: false,
+        user_id: None,
+        can_edit_photos: false,
+        can_add_photos: false,
+        can_delete_photos: false,
+    };
+    render_photo_grid(&state, share.token(), pref.theme, album, &query, view).await
+}
+
+/// Loads a page of photos into the grid
+pub async fn render_photo_grid(
+    state: &AppState,
+    token: &str,
+    theme: String,
+    album: Album,
+    query: &ListPhotosParams,
+    view: GridView,
 ) -> Response<Body> {
     let album_id = album.id.clone();
+    let bucket_id = album.bucket_id.clone();
+
+    // Stars are the only form in the grid itself
+    let csrf_token = match view.user_id {
+        Some(_) => create_csrf_token(&album_id, &state.config.jwt_secret).unwrap_or_default(),
+        None => "".to_string(),
+    };
 
     let mut tpl = PhotoGridTemnplate {
-        theme: pref.theme,
+        theme,
         album,
+        base_url: view.base_url,
+        read_only: view.read_only,
+        no_downloads: view.no_downloads,
+        can_edit_photos: view.can_edit_photos,
+        can_add_photos: view.can_add_photos,
+        can_delete_photos: view.can_delete_photos,
         photos: Vec::new(),
         meta: None,
         error_message: None,
         next_page: None,
         last_item: "".to_string(),
+        filter_query: query.filter_query(),
+        token: csrf_token,
     };
 
-    let config = state.config.clone();
-    let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-    let Some(bucket_id) = default_bucket_id else {
-        return build_error_response(tpl, Error::NoDefaultBucket);
+    let result = if query.is_custom() {
+        // files-rs has no sorting or filename search for files
+        match album_photos(state, token, &tpl.album).await {
+            Ok(photos) => Ok(Paginated::from_items(
+                query.apply(photos),
+                query.page.unwrap_or(1) as i64,
+                query.page_size() as i64,
+            )),
+            Err(err) => Err(err),
+        }
+    } else {
+        state
+            .files
+            .list_photos(token, &bucket_id, &album_id, query)
+            .await
     };
 
-    let result = list_photos(&config.api_url, ctx.token(), &bucket_id, &album_id, &query).await;
-
-    return match result {
+    match result {
         Ok(listing) => {
             tpl.photos = listing.data;
+            state.photo_meta.fill(&mut tpl.photos);
+            state.photo_exif.fill(&mut tpl.photos).await;
+            if let Some(user_id) = &view.user_id {
+                state.favorites.fill(user_id, &mut tpl.photos);
+                state.photo_tags.fill(&mut tpl.photos);
+            }
 
             if listing.meta.total_pages > listing.meta.page {
                 tpl.next_page = Some(listing.meta.page + 1);
@@ -113,7 +280,82 @@ pub async fn photo_listing_handler(
             build_response(tpl)
         }
         Err(err) => build_error_response(tpl, err),
+    }
+}
+
+/// Every photo of the album with its capture details, loaded from files-rs
+/// only when the cached listing is gone or out of date
+pub async fn album_photos(state: &AppState, token: &str, album: &Album) -> Result<Vec<Photo>> {
+    if let Some(photos) = state.photo_listings.get(album) {
+        return Ok(photos);
+    }
+
+    let mut photos = state
+        .files
+        .list_all_photos(token, &album.bucket_id, &album.id)
+        .await?;
+    // Sorting and filtering may need the capture details
+    state.photo_exif.fill(&mut photos).await;
+    state.photo_listings.insert(album, photos.clone());
+    Ok(photos)
+}
+
+/// Photos from across the bucket shown on one page, each one is a request to files-rs
+pub const MAX_BUCKET_PHOTOS_SHOWN: usize = 200;
+
+/// Photos fetched from files-rs at once
+const BUCKET_PHOTO_CONCURRENCY: usize = 8;
+
+/// Photos memo-rs keeps track of that may be in any album of the bucket
+pub struct BucketPhotos {
+    pub photos: Vec<Photo>,
+    /// Ids of photos deleted in files-rs in the meantime, with or without their album
+    pub missing: Vec<String>,
+    pub truncated: bool,
+    pub failed_count: usize,
+}
+
+/// Loads the (album id, photo id) pairs in order with their caption, tags and EXIF.
+/// Only an expired login fails the whole page, other errors are counted.
+pub async fn bucket_photos(
+    state: &AppState,
+    token: &str,
+    bucket_id: &str,
+    ids: Vec<(String, String)>,
+) -> Result<BucketPhotos> {
+    let truncated = ids.len() > MAX_BUCKET_PHOTOS_SHOWN;
+
+    let files = &state.files;
+    let results: Vec<_> = stream::iter(ids.into_iter().take(MAX_BUCKET_PHOTOS_SHOWN))
+        .map(|(album_id, photo_id)| async move {
+            let result = files
+                .get_photo(token, bucket_id, &album_id, &photo_id)
+                .await;
+            (photo_id, result)
+        })
+        .buffered(BUCKET_PHOTO_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut listing = BucketPhotos {
+        photos: Vec::new(),
+        missing: Vec::new(),
+        truncated,
+        failed_count: 0,
     };
+    for (photo_id, result) in results {
+        match result {
+            Ok(photo) => listing.photos.push(photo),
+            Err(Error::PhotoNotFound) | Err(Error::AlbumNotFound) => listing.missing.push(photo_id),
+            Err(err @ Error::LoginRequired(_)) => return Err(err),
+            Err(_) => listing.failed_count += 1,
+        }
+    }
+
+    state.photo_meta.fill(&mut listing.photos);
+    state.photo_tags.fill(&mut listing.photos);
+    state.photo_exif.fill(&mut listing.photos).await;
+    Ok(listing)
 }
 
 fn build_response(tpl: PhotoGridTemnplate) -> Response<Body> {
@@ -132,3 +374,42 @@ fn build_error_response(mut tpl: PhotoGridTemnplate, error: Error) -> Response<B
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+fn page_size_options(query: &ListPhotosParams) -> Vec<SelectOption> {
+    let current = query.page_size();
+    PHOTO_PAGE_SIZES
+        .iter()
+        .map(|size| SelectOption {
+            value: size.to_string(),
+            label: format!("{} per page", size),
+            selected: *size == current,
+        })
+        .collect()
+}
+
+fn sort_options(query: &ListPhotosParams) -> Vec<SelectOption> {
+    let current = query
+        .sort_order()
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let options = [
+        ("", "Default order"),
+        ("name", "Name (A-Z)"),
+        ("-name", "Name (Z-A)"),
+        ("-created_at", "Newest uploads"),
+        ("created_at", "Oldest uploads"),
+        ("-taken_at", "Newest taken"),
+        ("taken_at", "Oldest taken"),
+        ("-size", "Largest first"),
+        ("size", "Smallest first"),
+    ];
+
+    options
+        .into_iter()
+        .map(|(value, label)| SelectOption {
+            value: value.to_string(),
+            label: label.to_string(),
+            selected: value == current,
+        })
+        .collect()
+}
@@ -1,7 +1,21 @@
 mod del;
+mod detail;
+mod edit;
+mod favorite;
+mod guest_upload;
 mod listing;
+mod tags;
+mod transfer;
+mod tus;
 mod upload;
 
 pub use del::*;
+pub use detail::*;
+pub use edit::*;
+pub use favorite::*;
+pub use guest_upload::*;
 pub use listing::*;
+pub use tags::*;
+pub use transfer::*;
+pub use tus::*;
 pub use upload::*;
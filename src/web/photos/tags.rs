@@ -0,0 +1,146 @@
+use askama::Template;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use chrono::Utc;
+
+use crate::models::{parse_tags, Photo, PhotoTags, PhotoTagsForm, MAX_PHOTO_TAGS};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{enforce_policy, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+#[derive(Template)]
+#[template(path = "widgets/photo_tags_form.html")]
+struct PhotoTagsFormTemplate {
+    album: Album,
+    photo: Photo,
+    payload: PhotoTagsForm,
+    max_tags: usize,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/photo_tags_result.html")]
+struct PhotoTagsResultTemplate {
+    album: Album,
+    photo: Photo,
+    bucket_id: String,
+    can_edit_photos: bool,
+    can_add_photos: bool,
+    can_delete_photos: bool,
+}
+
+/// Shows the tags form in place of the grid item controls
+pub async fn photo_tags_form_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    if let Err(err) = enforce_policy(ctx.actor(), Resource::Photo, Action::Update) {
+        return handle_error_message(err);
+    }
+    let Ok(token) = create_csrf_token(&photo.id, &state.config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize tags form.".to_string(),
+        ));
+    };
+
+    let tpl = PhotoTagsFormTemplate {
+        payload: PhotoTagsForm {
+            tags: photo.tags.join(", "),
+            token,
+        },
+        album,
+        photo,
+        max_tags: MAX_PHOTO_TAGS,
+        error_message: None,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Saves the tags on our side and restores the grid item controls
+pub async fn post_photo_tags_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    Extension(photo): Extension<Photo>,
+    State(state): State<AppState>,
+    payload: Option<Form<PhotoTagsForm>>,
+) -> Response<Body> {
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Update) {
+        return handle_error_message(err);
+    }
+    let Ok(token) = create_csrf_token(&photo.id, &state.config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize tags form.".to_string(),
+        ));
+    };
+
+    let Some(Form(form)) = payload else {
+        return handle_error_message(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        ));
+    };
+
+    let result = save_tags(&state, &album, &photo, &form).await;
+
+    match result {
+        Ok(tags) => {
+            let tpl = PhotoTagsResultTemplate {
+                bucket_id: album.bucket_id.clone(),
+                album,
+                photo: Photo { tags, ..photo },
+                can_edit_photos: true,
+                can_add_photos: enforce_policy(actor, Resource::Photo, Action::Create).is_ok(),
+                can_delete_photos: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+            };
+            Response::builder()
+                .status(200)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap()
+        }
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            let tpl = PhotoTagsFormTemplate {
+                payload: PhotoTagsForm { token, ..form },
+                album,
+                photo,
+                max_tags: MAX_PHOTO_TAGS,
+                error_message: Some(error_info.message),
+            };
+            Response::builder()
+                .status(error_info.status_code)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap()
+        }
+    }
+}
+
+async fn save_tags(
+    state: &AppState,
+    album: &Album,
+    photo: &Photo,
+    form: &PhotoTagsForm,
+) -> Result<Vec<String>> {
+    verify_csrf_subject(&form.token, &photo.id, &state.config.jwt_secret)?;
+
+    let tags = parse_tags(&form.tags)?;
+    if tags.is_empty() {
+        state.photo_tags.remove(&photo.id).await?;
+        return Ok(tags);
+    }
+
+    let entry = PhotoTags {
+        bucket_id: album.bucket_id.clone(),
+        album_id: album.id.clone(),
+        tags: tags.clone(),
+        updated_at: Utc::now().timestamp(),
+    };
+    state.photo_tags.set(&photo.id, entry).await?;
+    Ok(tags)
+}
@@ -0,0 +1,311 @@
+use askama::Template;
+use axum::extract::Query;
+use axum::{body::Body, extract::State, response::Response, Extension, Form};
+use tracing::warn;
+
+use crate::models::{
+    Photo, PhotoTags, TransferMode, TransferPhotosForm, TransferResult, UpdatePhoto,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, verify_csrf_subject};
+use crate::web::{enforce_policy, forget_photo, handle_error_message, Action, ErrorInfo, Resource};
+use crate::{ctx::Ctx, models::Album, Error, Result};
+
+/// Keeps a single request from tying up the server for too long
+const MAX_TRANSFER_PHOTOS: usize = 100;
+
+#[derive(Template)]
+#[template(path = "widgets/transfer_photos_form.html")]
+struct TransferPhotosFormTemplate {
+    album: Album,
+    photo_ids: Vec<String>,
+    albums: Vec<Album>,
+    target_album_id: String,
+    mode: String,
+    can_move: bool,
+    token: String,
+    error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "widgets/transfer_photos_result.html")]
+struct TransferPhotosResultTemplate {
+    target: Album,
+    mode: String,
+    results: Vec<TransferResult>,
+    success_count: usize,
+    failed_count: usize,
+}
+
+/// Renders the form to pick the album to copy or move photos into
+pub async fn transfer_photos_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Create) {
+        return handle_error_message(err);
+    }
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize move photos form.".to_string(),
+        ));
+    };
+
+    let form = TransferPhotosForm::from_pairs(query);
+    let mut tpl = TransferPhotosFormTemplate {
+        album,
+        photo_ids: form.photo_ids,
+        albums: Vec::new(),
+        target_album_id: "".to_string(),
+        mode: TransferMode::Copy.to_string(),
+        can_move: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+        token,
+        error_message: None,
+    };
+
+    let mut status = 200;
+    if tpl.photo_ids.is_empty() {
+        status = 400;
+        tpl.error_message = Some("Select at least one photo.".to_string());
+    } else {
+        match target_albums(&state, &ctx, &tpl.album).await {
+            Ok(albums) => tpl.albums = albums,
+            Err(err) => {
+                let error_info: ErrorInfo = err.into();
+                status = error_info.status_code.as_u16();
+                tpl.error_message = Some(error_info.message);
+            }
+        }
+    }
+
+    Response::builder()
+        .status(status)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Copies or moves the selected photos one by one, reporting each outcome
+pub async fn post_transfer_photos_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    payload: Option<Form<Vec<(String, String)>>>,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    let Some(Form(pairs)) = payload else {
+        return handle_error_message(Error::BadRequest(
+            "Invalid form data. Refresh the page and try again.".to_string(),
+        ));
+    };
+    let form = TransferPhotosForm::from_pairs(pairs);
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        return handle_error_message(Error::AnyError(
+            "Failed to initialize move photos form.".to_string(),
+        ));
+    };
+
+    let target = match validate_transfer(&state, &ctx, &album, &form).await {
+        Ok(target) => target,
+        Err(err) => {
+            let error_info: ErrorInfo = err.into();
+            let tpl = TransferPhotosFormTemplate {
+                albums: target_albums(&state, &ctx, &album)
+                    .await
+                    .unwrap_or_default(),
+                album,
+                photo_ids: form.photo_ids,
+                target_album_id: form.target_album_id,
+                mode: form.mode.to_string(),
+                can_move: enforce_policy(actor, Resource::Photo, Action::Delete).is_ok(),
+                token,
+                error_message: Some(error_info.message),
+            };
+            return Response::builder()
+                .status(error_info.status_code)
+                .body(Body::from(tpl.render().unwrap()))
+                .unwrap();
+        }
+    };
+
+    let mut results: Vec<TransferResult> = Vec::new();
+    for photo_id in form.photo_ids.iter() {
+        results.push(transfer_photo(&state, &ctx, &album, &target, photo_id, form.mode).await);
+    }
+
+    let failed_count = results.iter().filter(|r| r.error.is_some()).count();
+    let tpl = TransferPhotosResultTemplate {
+        target,
+        mode: form.mode.to_string(),
+        success_count: results.len() - failed_count,
+        failed_count,
+        results,
+    };
+
+    Response::builder()
+        .status(200)
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Other albums in the same bucket
+async fn target_albums(state: &AppState, ctx: &Ctx, album: &Album) -> Result<Vec<Album>> {
+    let mut albums = state
+        .files
+        .list_all_albums(ctx.token(), &album.bucket_id, None)
+        .await?;
+    albums.retain(|a| a.id != album.id);
+    albums.sort_by_key(|a| a.label.to_lowercase());
+    Ok(albums)
+}
+
+async fn validate_transfer(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    form: &TransferPhotosForm,
+) -> Result<Album> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    verify_csrf_subject(&form.token, &album.id, &config.jwt_secret)?;
+
+    // Creating photos in the target album, removing them from this one when moving
+    enforce_policy(actor, Resource::Photo, Action::Create)?;
+    if form.mode == TransferMode::Move {
+        enforce_policy(actor, Resource::Photo, Action::Delete)?;
+    }
+
+    if form.photo_ids.is_empty() {
+        return Err(Error::ValidationError(
+            "Select at least one photo.".to_string(),
+        ));
+    }
+    if form.photo_ids.len() > MAX_TRANSFER_PHOTOS {
+        return Err(Error::ValidationError(format!(
+            "Select at most {} photos at a time.",
+            MAX_TRANSFER_PHOTOS
+        )));
+    }
+    if form.target_album_id.is_empty() || form.target_album_id == album.id {
+        return Err(Error::ValidationError("Select another album.".to_string()));
+    }
+
+    state
+        .files
+        .get_album(ctx.token(), &album.bucket_id, &form.target_album_id)
+        .await
+}
+
+async fn transfer_photo(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    target: &Album,
+    photo_id: &str,
+    mode: TransferMode,
+) -> TransferResult {
+    let mut result = TransferResult {
+        photo_id: photo_id.to_string(),
+        name: photo_id.to_string(),
+        error: None,
+        removed: false,
+    };
+
+    let photo = match state
+        .files
+        .get_photo(ctx.token(), &album.bucket_id, &album.id, photo_id)
+        .await
+    {
+        Ok(photo) => photo,
+        Err(err) => {
+            result.error = Some(err.to_string());
+            return result;
+        }
+    };
+    result.name = photo.name.clone();
+
+    if let Err(err) = copy_photo(state, ctx, target, &photo).await {
+        result.error = Some(err.to_string());
+        return result;
+    }
+
+    if mode == TransferMode::Move {
+        let deleted = state
+            .files
+            .delete_photo(ctx.token(), &album.bucket_id, &album.id, &photo.id)
+            .await;
+        match deleted {
+            Ok(_) => {
+                forget_photo(state, &album.id, &photo.id).await;
+                result.removed = true;
+            }
+            Err(err) => {
+                result.error = Some(format!("Copied, but the original was not removed: {}", err));
+            }
+        }
+    }
+
+    result
+}
+
+/// Uploads the original into the target and carries over the name, caption, tags and EXIF
+async fn copy_photo(state: &AppState, ctx: &Ctx, target: &Album, photo: &Photo) -> Result<Photo> {
+    let mut copy = state
+        .files
+        .copy_photo(ctx.token(), photo, &target.bucket_id, &target.id)
+        .await?;
+    state.photo_listings.invalidate(&target.id);
+
+    if copy.name != photo.name {
+        let data = UpdatePhoto {
+            name: photo.name.clone(),
+        };
+        copy = state
+            .files
+            .update_photo(ctx.token(), &target.bucket_id, &target.id, &copy.id, &data)
+            .await?;
+    }
+
+    // The copy is already in files-rs, lost details are not worth failing over
+    if let Err(err) = carry_over_details(state, target, photo, &copy).await {
+        warn!(
+            "Unable to carry over details of photo {} to {}: {}",
+            photo.id, copy.id, err
+        );
+    }
+
+    Ok(copy)
+}
+
+/// Caption, tags and EXIF are kept by memo-rs and keyed by photo
+async fn carry_over_details(
+    state: &AppState,
+    target: &Album,
+    photo: &Photo,
+    copy: &Photo,
+) -> Result<()> {
+    if let Some(meta) = state.photo_meta.get(&photo.id) {
+        state.photo_meta.set(&copy.id, meta).await?;
+    }
+    if let Some(tags) = state.photo_tags.get(&photo.id) {
+        let tags = PhotoTags {
+            bucket_id: target.bucket_id.clone(),
+            album_id: target.id.clone(),
+            ..tags
+        };
+        state.photo_tags.set(&copy.id, tags).await?;
+    }
+    if let Some(exif) = state.photo_exif.get(&photo.id).await {
+        state.photo_exif.set(&copy.id, &exif).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use axum::extract::{Path, Query};
+use axum::http::{HeaderMap, StatusCode};
+use axum::{body::Body, extract::State, response::Response, Extension};
+use base64::prelude::*;
+use chrono::DateTime;
+
+use crate::models::{
+    NewResumableUpload, NewStagedUpload, ResumableUpload, ResumableUploadParams, UploadParams,
+};
+use crate::run::AppState;
+use crate::services::{create_csrf_token, sanitize_filename, verify_csrf_subject};
+use crate::web::policies::{enforce_policy, Action, Resource};
+use crate::web::{needs_review, upload_and_index_photo, ErrorInfo};
+use crate::Error;
+use crate::{ctx::Ctx, models::Album};
+
+pub const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,expiration,termination";
+const TUS_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// Advertises what the resumable upload endpoint supports
+pub async fn tus_options_handler(State(state): State<AppState>) -> Response<Body> {
+    Response::builder()
+        .status(204)
+        .header("Tus-Resumable", TUS_VERSION)
+        .header("Tus-Version", TUS_VERSION)
+        .header("Tus-Extension", TUS_EXTENSIONS)
+        .header("Tus-Max-Size", state.config.upload_max_size.to_string())
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Starts a new resumable upload, the CSRF token is checked only here
+pub async fn tus_create_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Query(query): Query<UploadParams>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.clone();
+    let actor = ctx.actor();
+
+    if let Some(res) = check_tus_version(&headers) {
+        return res;
+    }
+    if let Err(err) = enforce_policy(actor, Resource::Photo, Action::Create) {
+        return tus_error(err);
+    }
+
+    let metadata = parse_metadata(header_str(&headers, "Upload-Metadata").unwrap_or(""));
+    let csrf_token = query
+        .token
+        .or_else(|| metadata_value(&metadata, "token"))
+        .unwrap_or("".to_string());
+    if let Err(err) = verify_csrf_subject(&csrf_token, &album.id, &config.jwt_secret) {
+        return tus_error(err);
+    }
+
+    let Some(length) = header_str(&headers, "Upload-Length").and_then(|v| v.parse::<u64>().ok())
+    else {
+        return tus_error(Error::BadRequest(
+            "Upload-Length header is required.".to_string(),
+        ));
+    };
+    if length > config.upload_max_size as u64 {
+        return tus_error(Error::UploadTooLarge);
+    }
+
+    let filename = sanitize_filename(&metadata_value(&metadata, "filename").unwrap_or_default());
+    let content_type = metadata_value(&metadata, "filetype")
+        .filter(|value| is_valid_content_type(value))
+        .unwrap_or("application/octet-stream".to_string());
+
+    let data = NewResumableUpload {
+        user_id: actor.user.id.clone(),
+        bucket_id: album.bucket_id.clone(),
+        album_id: album.id.clone(),
+        filename,
+        content_type,
+        length,
+    };
+
+    match state.uploads.create(data).await {
+        Ok(upload) => Response::builder()
+            .status(201)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header(
+                "Location",
+                format!(
+                    "/buckets/{}/albums/{}/upload/tus/{}",
+                    &album.bucket_id, &album.id, &upload.id
+                ),
+            )
+            .header("Upload-Expires", format_expires(upload.expires_at))
+            .body(Body::empty())
+            .unwrap(),
+        Err(err) => tus_error(err),
+    }
+}
+
+/// Reports how much of the upload has been received
+pub async fn tus_head_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<ResumableUploadParams>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Some(res) = check_tus_version(&headers) {
+        return res;
+    }
+
+    match find_upload(&state, &ctx, &album, params).await {
+        Ok(upload) => Response::builder()
+            .status(200)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Offset", upload.offset.to_string())
+            .header("Upload-Length", upload.length.to_string())
+            .header("Upload-Expires", format_expires(upload.expires_at))
+            .header("Cache-Control", "no-store")
+            .body(Body::empty())
+            .unwrap(),
+        Err(err) => tus_error(err),
+    }
+}
+
+/// Appends a chunk and hands the file over to files-rs once complete.
+/// If the hand over fails, the assembled file is kept until it expires
+/// and an empty PATCH at the final offset retries it.
+pub async fn tus_patch_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<ResumableUploadParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let config = state.config.clone();
+
+    if let Some(res) = check_tus_version(&headers) {
+        return res;
+    }
+    if header_str(&headers, "Content-Type") != Some(TUS_CONTENT_TYPE) {
+        return tus_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    let Some(offset) = header_str(&headers, "Upload-Offset").and_then(|v| v.parse::<u64>().ok())
+    else {
+        return tus_error(Error::BadRequest(
+            "Upload-Offset header is required.".to_string(),
+        ));
+    };
+
+    let upload = match find_upload(&state, &ctx, &album, params).await {
+        Ok(upload) => upload,
+        Err(err) => return tus_error(err),
+    };
+
+    let offset = match state.uploads.append(&upload, offset, body).await {
+        Ok(offset) => offset,
+        Err(err) => return tus_error(err),
+    };
+
+    if offset < upload.length {
+        return Response::builder()
+            .status(204)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Offset", offset.to_string())
+            .header("Upload-Expires", format_expires(upload.expires_at))
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
+        let error = ErrorInfo::new("Failed to initialize upload photos form.".to_string());
+        return tus_status(error.status_code);
+    };
+
+    if needs_review(&state, &album.id, Some(ctx.actor())) {
+        return stage_upload(&state, &ctx, &upload, offset, token).await;
+    }
+
+    let result = match state.uploads.multipart_body(&upload).await {
+        Ok((content_type, length, body)) => {
+            upload_and_index_photo(
+                &state,
+                ctx.token(),
+                &album,
+                &content_type,
+                Some(length),
+                body,
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(photo) => {
+            let _ = state.uploads.remove(&upload.id).await;
+
+            Response::builder()
+                .status(204)
+                .header("Tus-Resumable", TUS_VERSION)
+                .header("Upload-Offset", offset.to_string())
+                .header("X-Photo-Id", photo.id)
+                .header("X-Next-Token", token)
+                .body(Body::empty())
+                .unwrap()
+        }
+        Err(err) => tus_error(err),
+    }
+}
+
+/// Moves the assembled file to the review queue of a moderated album
+async fn stage_upload(
+    state: &AppState,
+    ctx: &Ctx,
+    upload: &ResumableUpload,
+    offset: u64,
+    token: String,
+) -> Response<Body> {
+    let data = NewStagedUpload {
+        bucket_id: upload.bucket_id.clone(),
+        album_id: upload.album_id.clone(),
+        uploaded_by: ctx.actor().user.username.clone(),
+        guest: false,
+    };
+    let result = match state.uploads.multipart_body(upload).await {
+        Ok((content_type, _, body)) => state.staging.stage(data, &content_type, body).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(staged) => {
+            let _ = state.uploads.remove(&upload.id).await;
+
+            Response::builder()
+                .status(204)
+                .header("Tus-Resumable", TUS_VERSION)
+                .header("Upload-Offset", offset.to_string())
+                .header("X-Staged-Id", staged.id)
+                .header("X-Next-Token", token)
+                .body(Body::empty())
+                .unwrap()
+        }
+        Err(err) => tus_error(err),
+    }
+}
+
+/// Cancels an unfinished upload
+pub async fn tus_delete_handler(
+    Extension(ctx): Extension<Ctx>,
+    Extension(album): Extension<Album>,
+    State(state): State<AppState>,
+    Path(params): Path<ResumableUploadParams>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Some(res) = check_tus_version(&headers) {
+        return res;
+    }
+
+    let result = match find_upload(&state, &ctx, &album, params).await {
+        Ok(upload) => state.uploads.remove(&upload.id).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(_) => tus_status(StatusCode::NO_CONTENT),
+        Err(err) => tus_error(err),
+    }
+}
+
+/// Uploads are only visible to the user who started them and within their album
+async fn find_upload(
+    state: &AppState,
+    ctx: &Ctx,
+    album: &Album,
+    params: ResumableUploadParams,
+) -> crate::Result<ResumableUpload> {
+    let upload_id = params.upload_id.expect("upload_id is required");
+    let upload = state.uploads.get(&upload_id).await?;
+
+    if upload.album_id != album.id || upload.user_id != ctx.actor().user.id {
+        return Err(Error::UploadNotFound);
+    }
+
+    Ok(upload)
+}
+
+fn check_tus_version(headers: &HeaderMap) -> Option<Response<Body>> {
+    if header_str(headers, "Tus-Resumable") == Some(TUS_VERSION) {
+        return None;
+    }
+
+    Some(
+        Response::builder()
+            .status(StatusCode::PRECONDITION_FAILED)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Tus-Version", TUS_VERSION)
+            .body(Body::empty())
+            .unwrap(),
+    )
+}
+
+fn tus_status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn tus_error(err: Error) -> Response<Body> {
+    let error: ErrorInfo = err.into();
+
+    Response::builder()
+        .status(error.status_code)
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::from(error.message))
+        .unwrap()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Parses `key base64value` pairs from the Upload-Metadata header
+fn parse_metadata(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let mut parts = pair.trim().splitn(2, ' ');
+            let key = parts.next()?.trim();
+            if key.is_empty() {
+                return None;
+            }
+            let decoded = match parts.next() {
+                Some(encoded) => BASE64_STANDARD.decode(encoded.trim()).ok()?,
+                None => Vec::new(),
+            };
+            let decoded = String::from_utf8(decoded).ok()?;
+            Some((key.to_string(), decoded))
+        })
+        .collect()
+}
+
+fn metadata_value(metadata: &[(String, String)], key: &str) -> Option<String> {
+    metadata
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+fn is_valid_content_type(value: &str) -> bool {
+    let mut parts = value.splitn(2, '/');
+    let valid_part = |part: Option<&str>| {
+        part.is_some_and(|p| {
+            !p.is_empty()
+                && p.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        })
+    };
+
+    valid_part(parts.next()) && valid_part(parts.next())
+}
+
+fn format_expires(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
@@ -1,17 +1,17 @@
 use askama::Template;
-use axum::body::Bytes;
 use axum::extract::Query;
 use axum::http::HeaderMap;
 use axum::{body::Body, extract::State, response::Response, Extension};
+use tracing::warn;
 
-use crate::models::{Pref, UploadParams};
+use crate::models::{NewStagedUpload, Pref, StagedUpload, UploadParams};
 use crate::run::AppState;
-use crate::services::{create_csrf_token, upload_photo};
-use crate::web::{handle_error, handle_error_message, ErrorInfo};
-use crate::Error;
+use crate::services::{create_csrf_token, normalize_upload, verify_csrf_subject, ExifProbe};
+use crate::web::{handle_error, handle_error_message, needs_review, normalizes_uploads, ErrorInfo};
 use crate::{
     ctx::Ctx,
     models::{Album, Photo, TemplateData},
+    Result,
 };
 
 use crate::web::policies::{enforce_policy, Action, Resource};
@@ -22,6 +22,7 @@ struct UploadPageTemplate {
     t: TemplateData,
     token: String,
     album: Album,
+    needs_review: bool,
 }
 
 #[derive(Template)]
@@ -31,6 +32,13 @@ struct UploadedPhotoTemplate {
     photo: Photo,
 }
 
+#[derive(Template)]
+#[template(path = "widgets/staged_upload.html")]
+pub struct StagedUploadTemplate {
+    pub theme: String,
+    pub staged: StagedUpload,
+}
+
 pub async fn upload_page_handler(
     Extension(ctx): Extension<Ctx>,
     Extension(pref): Extension<Pref>,
@@ -52,7 +60,12 @@ pub async fn upload_page_handler(
     t.title = format!("Photos - {} - Upload Photos", &album.label);
     t.scripts = vec![config.assets.upload_js.clone()];
 
-    let tpl = UploadPageTemplate { t, token, album };
+    let tpl = UploadPageTemplate {
+        t,
+        token,
+        needs_review: needs_review(&state, &album.id, Some(actor)),
+        album,
+    };
 
     Response::builder()
         .status(200)
@@ -67,34 +80,62 @@ pub async fn upload_handler(
     State(state): State<AppState>,
     Query(query): Query<UploadParams>,
     headers: HeaderMap,
-    body: Bytes,
+    body: Body,
 ) -> Response<Body> {
     let config = state.config.clone();
     let actor = ctx.actor();
-    let default_bucket_id = actor.default_bucket_id.clone();
-
-    let Some(bucket_id) = default_bucket_id else {
-        return handle_error(
-            &state,
-            Some(actor.clone()),
-            &pref,
-            Error::NoDefaultBucket.into(),
-            false,
-        );
-    };
 
     let Ok(token) = create_csrf_token(&album.id, &config.jwt_secret) else {
         let error = ErrorInfo::new("Failed to initialize upload photos form.".to_string());
         return handle_error(&state, Some(actor.clone()), &pref, error, true);
     };
 
-    let result = upload_photo(
-        &config,
+    // We need the content type header
+    let Some(content_type) = headers.get("Content-Type") else {
+        return handle_error_message("Content-Type header is required.".into());
+    };
+    let Ok(content_type) = content_type.to_str() else {
+        return handle_error_message("Invalid Content-Type header.".into());
+    };
+    let content_length = headers
+        .get("Content-Length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let csrf_token = query.token.unwrap_or("".to_string());
+    if let Err(err) = verify_csrf_subject(&csrf_token, &album.id, &config.jwt_secret) {
+        return handle_error_message(err);
+    }
+
+    if needs_review(&state, &album.id, Some(actor)) {
+        let data = NewStagedUpload {
+            bucket_id: album.bucket_id.clone(),
+            album_id: album.id.clone(),
+            uploaded_by: actor.user.username.clone(),
+            guest: false,
+        };
+        return match state.staging.stage(data, content_type, body).await {
+            Ok(staged) => {
+                let tpl = StagedUploadTemplate {
+                    staged,
+                    theme: pref.theme,
+                };
+                Response::builder()
+                    .status(202)
+                    .header("X-Next-Token", token)
+                    .body(Body::from(tpl.render().unwrap()))
+                    .unwrap()
+            }
+            Err(err) => handle_error_message(err),
+        };
+    }
+
+    let result = upload_and_index_photo(
+        &state,
         ctx.token(),
-        &bucket_id,
-        &album.id,
-        &headers,
-        query.token,
+        &album,
+        content_type,
+        content_length,
         body,
     )
     .await;
@@ -114,3 +155,44 @@ pub async fn upload_handler(
         Err(err) => handle_error_message(err),
     }
 }
+
+/// Uploads the multipart body to files-rs and keeps the EXIF found in the file.
+/// The photo is auto-rotated and stripped of location first if the album asks for it.
+pub async fn upload_and_index_photo(
+    state: &AppState,
+    token: &str,
+    album: &Album,
+    content_type: &str,
+    content_length: Option<u64>,
+    body: Body,
+) -> Result<Photo> {
+    let (content_type, content_length, body) = if normalizes_uploads(state, &album.id) {
+        normalize_upload(content_type, content_length, body).await?
+    } else {
+        (content_type.to_string(), content_length, body)
+    };
+
+    let probe = ExifProbe::default();
+    let mut photo = state
+        .files
+        .upload_photo(
+            token,
+            &album.bucket_id,
+            &album.id,
+            &content_type,
+            content_length,
+            probe.tap(body),
+        )
+        .await?;
+    state.photo_listings.invalidate(&album.id);
+
+    // The photo is already in the album, lost details are not worth failing over
+    if let Some(exif) = probe.read(&content_type).await {
+        if let Err(err) = state.photo_exif.set(&photo.id, &exif).await {
+            warn!("Unable to keep EXIF of photo {}: {}", photo.id, err);
+        }
+        photo.exif = Some(exif);
+    }
+
+    Ok(photo)
+}
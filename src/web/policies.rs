@@ -4,6 +4,7 @@ use std::result::Result as StdResult;
 use crate::{Error, Result};
 
 pub enum Resource {
+    Bucket,
     Album,
     Photo,
 }
@@ -17,6 +18,7 @@ pub enum Action {
 
 pub fn enforce_policy(actor: &Actor, resource: Resource, action: Action) -> Result<()> {
     let result = match resource {
+        Resource::Bucket => enforce_bucket_permissions(actor, action),
         Resource::Album => enforce_dir_permissions(actor, action),
         Resource::Photo => enforce_photo_permissions(actor, action),
     };
@@ -27,6 +29,18 @@ pub fn enforce_policy(actor: &Actor, resource: Resource, action: Action) -> Resu
     }
 }
 
+fn enforce_bucket_permissions(actor: &Actor, action: Action) -> StdResult<(), &str> {
+    // Buckets are managed in files-rs, memo-rs only lists them
+    let Action::Read = action else {
+        return Err("You do not have permission to manage buckets.");
+    };
+
+    if !actor.has_permissions(&vec![Permission::BucketsList, Permission::BucketsView]) {
+        return Err("You do not have permission to view buckets.");
+    }
+    Ok(())
+}
+
 fn enforce_dir_permissions(actor: &Actor, action: Action) -> StdResult<(), &str> {
     let (permissions, message) = match action {
         Action::Create => (
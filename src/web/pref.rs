@@ -1,10 +1,11 @@
 use askama::Template;
-use axum::{body::Body, extract::State, response::Response};
+use axum::{body::Body, extract::State, response::Response, Extension};
 use tower_cookies::{cookie::time::Duration, Cookie, Cookies};
 
+use crate::models::Bucket;
 use crate::run::AppState;
 
-use super::THEME_COOKIE;
+use super::{ALBUM_VIEW_COOKIE, BUCKET_COOKIE, THEME_COOKIE};
 
 #[derive(Template)]
 #[template(path = "widgets/set_theme.html")]
@@ -16,6 +17,12 @@ struct InnerTemplate {
     theme: String,
 }
 
+#[derive(Template)]
+#[template(path = "widgets/album_view_toggle.html")]
+struct AlbumViewTemplate {
+    album_view: String,
+}
+
 pub async fn light_theme_handler(
     cookies: Cookies,
     State(state): State<AppState>,
@@ -54,3 +61,61 @@ async fn theme_handler(
         .body(Body::from(tpl.render().unwrap()))
         .unwrap()
 }
+
+pub async fn list_album_view_handler(
+    cookies: Cookies,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    album_view_handler(state, cookies, "list").await
+}
+
+pub async fn grid_album_view_handler(
+    cookies: Cookies,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    album_view_handler(state, cookies, "grid").await
+}
+
+/// Switches the album listing between the list and the cover grid
+async fn album_view_handler(state: AppState, cookies: Cookies, view: &str) -> Response<Body> {
+    let view_cookie = Cookie::build((ALBUM_VIEW_COOKIE, view.to_string()))
+        .http_only(true)
+        .max_age(Duration::days(365))
+        .secure(state.config.ssl)
+        .path("/")
+        .build();
+
+    cookies.add(view_cookie);
+
+    let tpl = AlbumViewTemplate {
+        album_view: view.to_string(),
+    };
+
+    Response::builder()
+        .status(200)
+        .header("HX-Trigger", "AlbumViewSetEvent")
+        .body(Body::from(tpl.render().unwrap()))
+        .unwrap()
+}
+
+/// Remembers the bucket to use when none is given in the url
+pub async fn select_bucket_handler(
+    cookies: Cookies,
+    Extension(bucket): Extension<Bucket>,
+    State(state): State<AppState>,
+) -> Response<Body> {
+    let bucket_cookie = Cookie::build((BUCKET_COOKIE, bucket.id.clone()))
+        .http_only(true)
+        .max_age(Duration::days(365))
+        .secure(state.config.ssl)
+        .path("/")
+        .build();
+
+    cookies.add(bucket_cookie);
+
+    Response::builder()
+        .status(200)
+        .header("HX-Redirect", format!("/buckets/{}", &bucket.id))
+        .body(Body::from("".to_string()))
+        .unwrap()
+}
@@ -1,7 +1,7 @@
 use axum::extract::DefaultBodyLimit;
-use axum::routing::{get, get_service, post};
+use axum::routing::{get, get_service, head, options, post};
 use axum::{middleware, Router};
-use std::path::PathBuf;
+use std::path::Path;
 use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::services::{ServeDir, ServeFile};
 
@@ -12,14 +12,30 @@ use crate::web::{
 };
 
 use super::{
-    album_listing_handler, album_listing_middleware, album_middleware,
-    confirm_delete_photo_handler, dark_theme_handler, delete_album_handler,
-    edit_album_controls_handler, edit_album_handler, exec_delete_photo_handler,
-    light_theme_handler, photo_middleware, post_edit_album_handler, pre_delete_photo_handler,
-    pref_middleware, require_auth_middleware, upload_handler, upload_page_handler,
+    album_cover_handler, album_guest_uploads_handler, album_listing_handler,
+    album_listing_middleware, album_middleware, album_moderation_handler, album_passphrase_handler,
+    album_shares_handler, album_upload_settings_handler, bucket_middleware,
+    bulk_delete_photos_handler, confirm_delete_photo_handler, dark_theme_handler,
+    delete_album_handler, download_album_handler, edit_album_controls_handler, edit_album_handler,
+    edit_photo_handler, exec_bulk_delete_photos_handler, exec_delete_photo_handler,
+    favorite_photo_handler, favorites_page_handler, grid_album_view_handler, guest_upload_handler,
+    guest_upload_middleware, guest_upload_page_handler, light_theme_handler,
+    list_album_view_handler, moderation_page_handler, photo_info_handler, photo_middleware,
+    photo_page_handler, photo_tags_form_handler, post_album_guest_upload_handler,
+    post_album_moderation_handler, post_album_passphrase_handler, post_album_share_handler,
+    post_album_upload_settings_handler, post_edit_album_handler, post_edit_photo_handler,
+    post_moderation_handler, post_photo_tags_handler, post_transfer_photos_handler,
+    post_unlock_album_handler, pre_delete_photo_handler, pref_middleware,
+    remove_album_passphrase_handler, require_auth_middleware, revoke_album_guest_upload_handler,
+    revoke_album_share_handler, select_bucket_handler, set_album_cover_handler, share_middleware,
+    shared_photo_listing_handler, shared_photos_page_handler, slideshow_page_handler,
+    slideshow_playlist_handler, staged_upload_file_handler, tag_page_handler, tags_page_handler,
+    timeline_grid_handler, timeline_page_handler, transfer_photos_handler, tus_create_handler,
+    tus_delete_handler, tus_head_handler, tus_options_handler, tus_patch_handler,
+    unlock_album_handler, unlocked_photo_listing_handler, upload_handler, upload_page_handler,
 };
 
-pub fn assets_routes(dir: &PathBuf) -> Router {
+pub fn assets_routes(dir: &Path) -> Router {
     let target_dir = dir.join("public");
     Router::new()
         .route(
@@ -38,10 +54,19 @@ pub fn assets_routes(dir: &PathBuf) -> Router {
 
 pub fn private_routes(state: AppState) -> Router {
     Router::new()
-        .route("/", get(index_handler))
+        .merge(bucket_routes(state.clone()))
+        .nest("/buckets/:bucket_id", bucket_routes(state.clone()))
         .route("/prefs/theme/light", post(light_theme_handler))
         .route("/prefs/theme/dark", post(dark_theme_handler))
-        .nest("/albums", album_routes(state.clone()))
+        .route("/prefs/album-view/list", post(list_album_view_handler))
+        .route("/prefs/album-view/grid", post(grid_album_view_handler))
+        .route(
+            "/prefs/bucket/:bucket_id",
+            post(select_bucket_handler).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                bucket_middleware,
+            )),
+        )
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             require_auth_middleware,
@@ -50,6 +75,23 @@ pub fn private_routes(state: AppState) -> Router {
         .with_state(state)
 }
 
+/// Served under `/buckets/:bucket_id` and at the root for the selected bucket
+fn bucket_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/", get(index_handler))
+        .route("/favorites", get(favorites_page_handler))
+        .route("/tags", get(tags_page_handler))
+        .route("/tags/:tag", get(tag_page_handler))
+        .route("/timeline", get(timeline_page_handler))
+        .route("/timeline/grid", get(timeline_grid_handler))
+        .nest("/albums", album_routes(state.clone()))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            bucket_middleware,
+        ))
+        .with_state(state)
+}
+
 fn album_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/listing", get(album_listing_handler))
@@ -75,6 +117,52 @@ fn album_inner_routes(state: AppState) -> Router<AppState> {
             get(delete_album_handler).post(delete_album_handler),
         )
         .route("/photo-grid", get(photo_listing_handler))
+        .route("/download", get(download_album_handler))
+        .route("/slideshow", get(slideshow_page_handler))
+        .route("/slideshow/playlist", get(slideshow_playlist_handler))
+        .route("/cover", get(album_cover_handler))
+        .route(
+            "/bulk-delete",
+            get(bulk_delete_photos_handler).post(exec_bulk_delete_photos_handler),
+        )
+        .route(
+            "/transfer",
+            get(transfer_photos_handler).post(post_transfer_photos_handler),
+        )
+        .route(
+            "/shares",
+            get(album_shares_handler).post(post_album_share_handler),
+        )
+        .route("/shares/:share_id/revoke", post(revoke_album_share_handler))
+        .route(
+            "/passphrase",
+            get(album_passphrase_handler).post(post_album_passphrase_handler),
+        )
+        .route("/passphrase/remove", post(remove_album_passphrase_handler))
+        .route(
+            "/guest-uploads",
+            get(album_guest_uploads_handler).post(post_album_guest_upload_handler),
+        )
+        .route(
+            "/guest-uploads/:link_id/revoke",
+            post(revoke_album_guest_upload_handler),
+        )
+        .route(
+            "/moderation",
+            get(moderation_page_handler).post(post_moderation_handler),
+        )
+        .route(
+            "/moderation/settings",
+            get(album_moderation_handler).post(post_album_moderation_handler),
+        )
+        .route(
+            "/moderation/:staged_id/file",
+            get(staged_upload_file_handler),
+        )
+        .route(
+            "/upload-settings",
+            get(album_upload_settings_handler).post(post_album_upload_settings_handler),
+        )
         .nest("/upload", upload_route(state.clone()))
         .nest("/photos/:photo_id", photo_routes(state.clone()))
         .route_layer(middleware::from_fn_with_state(
@@ -85,20 +173,45 @@ fn album_inner_routes(state: AppState) -> Router<AppState> {
 }
 
 fn upload_route(state: AppState) -> Router<AppState> {
+    let max_size = state.config.upload_max_size;
+
+    // Uploads are streamed to files-rs, only the overall size is capped here
     Router::new()
         .route("/", get(upload_page_handler).post(upload_handler))
-        .layer(DefaultBodyLimit::max(8000000))
-        .layer(RequestBodyLimitLayer::new(8000000))
+        .route(
+            "/tus",
+            options(tus_options_handler).post(tus_create_handler),
+        )
+        .route(
+            "/tus/:upload_id",
+            head(tus_head_handler)
+                .patch(tus_patch_handler)
+                .delete(tus_delete_handler),
+        )
+        .layer(DefaultBodyLimit::max(max_size))
+        .layer(RequestBodyLimitLayer::new(max_size))
         .with_state(state)
 }
 
 fn photo_routes(state: AppState) -> Router<AppState> {
     Router::new()
+        .route("/", get(photo_page_handler))
+        .route("/info", get(photo_info_handler))
+        .route(
+            "/edit",
+            get(edit_photo_handler).post(post_edit_photo_handler),
+        )
         .route(
             "/delete",
             get(confirm_delete_photo_handler).post(exec_delete_photo_handler),
         )
         .route("/delete-controls", get(pre_delete_photo_handler))
+        .route("/cover", post(set_album_cover_handler))
+        .route("/favorite", post(favorite_photo_handler))
+        .route(
+            "/tags",
+            get(photo_tags_form_handler).post(post_photo_tags_handler),
+        )
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             photo_middleware,
@@ -110,6 +223,56 @@ pub fn public_routes(state: AppState) -> Router {
     Router::new()
         .route("/login", get(login_handler).post(post_login_handler))
         .route("/logout", post(logout_handler))
+        .nest("/share/:share_token", share_routes(state.clone()))
+        .nest("/albums/:album_id/unlock", unlock_routes())
+        .nest(
+            "/guest-upload/:guest_token",
+            guest_upload_routes(state.clone()),
+        )
+        .with_state(state)
+}
+
+/// Read-only album pages for anyone holding a share link
+fn share_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/", get(shared_photos_page_handler))
+        .route("/photo-grid", get(shared_photo_listing_handler))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            share_middleware,
+        ))
+        .route_layer(middleware::from_fn(pref_middleware))
+        .with_state(state)
+}
+
+/// Passphrase protected albums, served outside the bucket routes since
+/// visitors have no bucket to select
+fn unlock_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(unlock_album_handler).post(post_unlock_album_handler),
+        )
+        .route("/photo-grid", get(unlocked_photo_listing_handler))
+        .route_layer(middleware::from_fn(pref_middleware))
+}
+
+/// Upload page for guests holding an upload link, capped like member uploads
+fn guest_upload_routes(state: AppState) -> Router<AppState> {
+    let max_size = state.config.upload_max_size;
+
+    Router::new()
+        .route(
+            "/",
+            get(guest_upload_page_handler).post(guest_upload_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            guest_upload_middleware,
+        ))
+        .route_layer(middleware::from_fn(pref_middleware))
+        .layer(DefaultBodyLimit::max(max_size))
+        .layer(RequestBodyLimitLayer::new(max_size))
         .with_state(state)
 }
 